@@ -8,9 +8,35 @@ import {
   type SubagentStartHookInput,
   type SubagentStopHookInput,
 } from "@anthropic-ai/claude-agent-sdk";
+import * as fs from "fs";
+import * as path from "path";
 import * as readline from "readline";
+import { fileURLToPath } from "url";
 import { z } from "zod";
 
+// Protocol version reported in the ready handshake. Bump it together with
+// SIDECAR_PROTOCOL_VERSION in sidecar.rs whenever messages change incompatibly.
+const PROTOCOL_VERSION = 1;
+
+// Optional protocol features this build supports, reported in the ready handshake
+const CAPABILITIES = [
+  "request_ids", // Replies echo the requestId of create/update_model/update_thinking/close
+  "permission_requests", // Tool approvals go through permission_request/permission_response
+  "history_summary", // Restored history may contain a compacted summary message
+  "planning_answers", // Answers to planning_questions arrive as planning_answers
+];
+
+/** Version of the installed Claude Agent SDK, read from its package.json */
+function getSdkVersion(): string {
+  try {
+    const dir = path.dirname(fileURLToPath(import.meta.url));
+    const pkgPath = path.join(dir, "..", "node_modules", "@anthropic-ai", "claude-agent-sdk", "package.json");
+    return JSON.parse(fs.readFileSync(pkgPath, "utf-8")).version ?? "unknown";
+  } catch {
+    return "unknown";
+  }
+}
+
 // Planning question option schema
 const PlanningQuestionOptionSchema = z.object({
   label: z.string().describe("Short label for the option"),
@@ -187,11 +213,18 @@ interface HistoryToolResultMessage {
   output: string;
 }
 
+// Compacted summary of older turns (sent instead of them for long restored sessions)
+interface HistorySummaryMessage {
+  type: "summary";
+  content: string;
+}
+
 type HistoryMessage =
   | HistoryUserMessage
   | HistoryAssistantMessage
   | HistoryToolUseMessage
-  | HistoryToolResultMessage;
+  | HistoryToolResultMessage
+  | HistorySummaryMessage;
 
 // MCP Server configuration types
 interface McpServerConfig {
@@ -220,6 +253,7 @@ interface CreateMessage {
   options?: Partial<Options>;
   plan_mode?: boolean; // Whether this is a plan mode session (enables planning tools)
   mcp_servers?: McpServerConfig[]; // External MCP servers to register
+  requestId?: string; // Echoed in the reply so the app can await it
 }
 
 interface ImageData {
@@ -239,6 +273,7 @@ interface QueryMessage {
 interface CloseMessage {
   type: "close";
   id: string;
+  requestId?: string;
 }
 
 interface StopMessage {
@@ -250,12 +285,39 @@ interface UpdateModelMessage {
   type: "update_model";
   id: string;
   model: string;
+  requestId?: string;
 }
 
 interface UpdateThinkingMessage {
   type: "update_thinking";
   id: string;
   maxThinkingTokens: number | null;
+  requestId?: string;
+}
+
+// Answer to a permission_request
+interface PermissionResponseMessage {
+  type: "permission_response";
+  id: string;
+  toolUseId: string;
+  decision: "allow" | "deny" | "allow_always";
+  updatedInput?: Record<string, unknown>; // Replaces the tool input when allowing
+  message?: string; // Reason shown to Claude when denying
+}
+
+// Answers to planning_questions, validated by the app against the questions asked
+interface AnsweredPlanningQuestion {
+  header: string;
+  question: string;
+  multiSelect: boolean;
+  selected: string[]; // Labels of the chosen options
+  other?: string; // Free-text answer
+}
+
+interface PlanningAnswersMessage {
+  type: "planning_answers";
+  id: string;
+  answers: AnsweredPlanningQuestion[];
 }
 
 // LLM Feature: Generate repository description using Claude SDK
@@ -273,7 +335,9 @@ type InboundMessage =
   | StopMessage
   | UpdateModelMessage
   | UpdateThinkingMessage
-  | GenerateRepoDescriptionMessage;
+  | GenerateRepoDescriptionMessage
+  | PermissionResponseMessage
+  | PlanningAnswersMessage;
 
 interface Session {
   cwd: string;
@@ -295,6 +359,50 @@ const toolUseIdToName = new Map<string, string>();
 // Track thinking state per session (session_id -> { startTime, content })
 const thinkingState = new Map<string, { startTime: number; content: string }>();
 
+// Permission requests waiting for the app's answer (tool_use_id -> resolver)
+const pendingPermissions = new Map<string, (response: PermissionResponseMessage) => void>();
+let permissionRequestCounter = 0;
+
+/** Ask the app whether a tool may run; resolves with a deny if the query is interrupted first */
+function requestPermission(
+  id: string,
+  tool: string,
+  input: Record<string, unknown>,
+  toolUseId: string | undefined,
+  signal: AbortSignal
+): Promise<PermissionResponseMessage> {
+  const requestId = toolUseId ?? `permission-${++permissionRequestCounter}`;
+  return new Promise((resolve) => {
+    pendingPermissions.set(requestId, resolve);
+    signal.addEventListener("abort", () => {
+      if (pendingPermissions.delete(requestId)) {
+        resolve({
+          type: "permission_response",
+          id,
+          toolUseId: requestId,
+          decision: "deny",
+          message: "The query was interrupted",
+        });
+      }
+    });
+    send({ type: "permission_request", id, tool, input, toolUseId: requestId });
+  });
+}
+
+function handlePermissionResponse(msg: PermissionResponseMessage): void {
+  const resolve = pendingPermissions.get(msg.toolUseId);
+  if (!resolve) {
+    send({
+      type: "debug",
+      id: msg.id,
+      message: `No pending permission request for ${msg.toolUseId}`,
+    });
+    return;
+  }
+  pendingPermissions.delete(msg.toolUseId);
+  resolve(msg);
+}
+
 function send(msg: object): void {
   const line = JSON.stringify(msg) + "\n";
   process.stdout.write(line);
@@ -363,13 +471,15 @@ function sendProgressiveUsage(
     outputTokens: number;
     cacheReadTokens: number;
     cacheCreationTokens: number;
+    /** API response the message belongs to (every content block of a response repeats its usage) */
+    messageId?: string;
   }
 ): void {
   send({ type: "progressive_usage", id, ...usage });
 }
 
-function sendError(id: string, message: string): void {
-  send({ type: "error", id, message });
+function sendError(id: string, message: string, requestId?: string): void {
+  send({ type: "error", id, message, ...(requestId && { requestId }) });
 }
 
 function sendSubagentStart(
@@ -568,7 +678,11 @@ async function handleCreate(msg: CreateMessage): Promise<void> {
     ...msg.options,
     // Allow all MCP tools to execute without permission prompts
     // This callback fires when Claude would show a permission prompt
-    canUseTool: async (toolName: string, input: unknown) => {
+    canUseTool: async (
+      toolName: string,
+      input: Record<string, unknown>,
+      { signal, toolUseID }: { signal: AbortSignal; toolUseID?: string }
+    ) => {
       // Allow all MCP tools (they start with "mcp__")
       if (toolName.startsWith("mcp__")) {
         send({
@@ -578,8 +692,15 @@ async function handleCreate(msg: CreateMessage): Promise<void> {
         });
         return { behavior: "allow" as const, updatedInput: input };
       }
-      // For non-MCP tools, allow by default (acceptEdits handles file operations)
-      return { behavior: "allow" as const, updatedInput: input };
+      // Other tools are answered by the app (repo rules or the user)
+      const response = await requestPermission(msg.id, toolName, input, toolUseID, signal);
+      if (response.decision === "deny") {
+        return {
+          behavior: "deny" as const,
+          message: response.message ?? "The user denied this tool call",
+        };
+      }
+      return { behavior: "allow" as const, updatedInput: response.updatedInput ?? input };
     },
   };
 
@@ -753,7 +874,7 @@ async function handleCreate(msg: CreateMessage): Promise<void> {
     });
   }
 
-  send({ type: "created", id: msg.id });
+  send({ type: "created", id: msg.id, requestId: msg.requestId });
 }
 
 // Content block types for multimodal prompts (matching Anthropic API format)
@@ -810,6 +931,9 @@ function formatConversationHistory(messages: HistoryMessage[]): string {
             : msg.output;
         parts.push(`[Tool "${msg.tool}" result]: ${output}`);
         break;
+      case "summary":
+        parts.push(`[Summary of earlier conversation]: ${msg.content}`);
+        break;
     }
   }
 
@@ -1277,6 +1401,7 @@ function handleSdkMessage(id: string, message: SDKMessage): void {
           outputTokens: usage.output_tokens ?? 0,
           cacheReadTokens: usage.cache_read_input_tokens ?? 0,
           cacheCreationTokens: usage.cache_creation_input_tokens ?? 0,
+          messageId: message.message.id,
         });
       }
       break;
@@ -1422,6 +1547,44 @@ function handleSdkMessage(id: string, message: SDKMessage): void {
   }
 }
 
+// Turn planning answers into the next user turn. Keep in sync with answers_text in planning.rs,
+// which records the same text in the app's session history.
+function formatPlanningAnswers(answers: AnsweredPlanningQuestion[]): string {
+  let text = "Here are my answers to the planning questions:\n";
+  for (const answer of answers) {
+    text += `\n**${answer.header}**: ${answer.question}\n`;
+    if (answer.selected.length > 0) {
+      text += `- Selected: ${answer.selected.join(", ")}\n`;
+    }
+    if (answer.other) {
+      text += `- Other: ${answer.other}\n`;
+    }
+  }
+  return text.trimEnd();
+}
+
+async function handlePlanningAnswers(msg: PlanningAnswersMessage): Promise<void> {
+  const session = sessions.get(msg.id);
+  if (!session) {
+    sendError(msg.id, "Session not found");
+    return;
+  }
+  if (!session.planMode) {
+    sendError(msg.id, "Planning answers sent to a session that is not in plan mode");
+    return;
+  }
+  send({
+    type: "debug",
+    id: msg.id,
+    message: `Answering ${msg.answers.length} planning question(s)`,
+  });
+  await handleQuery({
+    type: "query",
+    id: msg.id,
+    prompt: formatPlanningAnswers(msg.answers),
+  });
+}
+
 async function handleStop(msg: StopMessage): Promise<void> {
   const session = sessions.get(msg.id);
   if (!session) {
@@ -1474,19 +1637,19 @@ async function handleStop(msg: StopMessage): Promise<void> {
 async function handleUpdateModel(msg: UpdateModelMessage): Promise<void> {
   const session = sessions.get(msg.id);
   if (!session) {
-    sendError(msg.id, "Session not found");
+    sendError(msg.id, "Session not found", msg.requestId);
     return;
   }
 
   // Update the model in the session options
   session.options.model = msg.model;
-  send({ type: "model_updated", id: msg.id, model: msg.model });
+  send({ type: "model_updated", id: msg.id, model: msg.model, requestId: msg.requestId });
 }
 
 async function handleUpdateThinking(msg: UpdateThinkingMessage): Promise<void> {
   const session = sessions.get(msg.id);
   if (!session) {
-    sendError(msg.id, "Session not found");
+    sendError(msg.id, "Session not found", msg.requestId);
     return;
   }
 
@@ -1523,6 +1686,7 @@ async function handleUpdateThinking(msg: UpdateThinkingMessage): Promise<void> {
     type: "thinking_updated",
     id: msg.id,
     maxThinkingTokens: msg.maxThinkingTokens,
+    requestId: msg.requestId,
   });
 }
 
@@ -1541,7 +1705,7 @@ async function handleClose(msg: CloseMessage): Promise<void> {
     }
   }
   sessions.delete(msg.id);
-  send({ type: "closed", id: msg.id });
+  send({ type: "closed", id: msg.id, requestId: msg.requestId });
 }
 
 async function handleMessage(msg: InboundMessage): Promise<void> {
@@ -1567,6 +1731,12 @@ async function handleMessage(msg: InboundMessage): Promise<void> {
     case "generate_repo_description":
       await handleGenerateRepoDescription(msg);
       break;
+    case "permission_response":
+      handlePermissionResponse(msg);
+      break;
+    case "planning_answers":
+      await handlePlanningAnswers(msg);
+      break;
     default:
       sendError(
         "unknown",
@@ -1582,11 +1752,14 @@ const rl = readline.createInterface({
 });
 
 rl.on("line", async (line: string) => {
+  let msg: InboundMessage | undefined;
   try {
-    const msg = JSON.parse(line) as InboundMessage;
+    msg = JSON.parse(line) as InboundMessage;
     await handleMessage(msg);
   } catch (err) {
-    sendError("unknown", err instanceof Error ? err.message : String(err));
+    // Answer the awaiting request, if any, so the app does not wait for a timeout
+    const requestId = msg && "requestId" in msg ? msg.requestId : undefined;
+    sendError("unknown", err instanceof Error ? err.message : String(err), requestId);
   }
 });
 
@@ -1627,5 +1800,10 @@ process.stdin.on("error", (err) => {
 process.stdin.resume();
 
 // Log startup
-send({ type: "ready" });
+send({
+  type: "ready",
+  protocolVersion: PROTOCOL_VERSION,
+  sdkVersion: getSdkVersion(),
+  capabilities: CAPABILITIES,
+});
 send({ type: "debug", id: "process", message: "Sidecar started successfully" });
@@ -0,0 +1,333 @@
+//! Budget enforcement for SDK sessions
+//!
+//! The sidecar reports cost and turns when a query finishes and a progressive usage update
+//! for every assistant message while it runs. Each live session tallies these together with
+//! the time spent querying; until a query's final cost arrives, its cost is estimated from
+//! the tokens of its responses and the model's price. Once a limit from the config is reached
+//! the running query is stopped and further prompts are refused until the limit is raised.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::{AppConfig, BudgetLimits, ClaudeModelPrice};
+
+/// Which limit a session ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetKind {
+    Cost,
+    Turns,
+    Duration,
+}
+
+/// Payload of the `sdk-budget-exceeded-{id}` event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetExceeded {
+    pub kind: BudgetKind,
+    /// The configured limit (USD, turns or minutes)
+    pub limit: f64,
+    /// What the session has used, in the same unit
+    pub used: f64,
+}
+
+impl BudgetExceeded {
+    pub fn describe(&self) -> String {
+        match self.kind {
+            BudgetKind::Cost => format!("cost ${:.2} of ${:.2}", self.used, self.limit),
+            BudgetKind::Turns => format!("{} of {} turns", self.used, self.limit),
+            BudgetKind::Duration => {
+                format!("{:.1} of {} minutes", self.used, self.limit)
+            }
+        }
+    }
+}
+
+/// Tokens of one API response, from a progressive usage update
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseTokens {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_creation: u64,
+}
+
+/// What a live session has used so far
+#[derive(Debug, Clone, Default)]
+pub struct SessionSpend {
+    /// Cost of finished queries
+    cost_usd: f64,
+    /// Turns of finished queries
+    turns: u64,
+    /// Responses seen in the running query (its turns until the final usage arrives)
+    query_turns: u64,
+    /// Tokens of the running query's responses, to estimate its cost until the final usage arrives
+    query_tokens: ResponseTokens,
+    /// Id of the last response counted, since every content block of a response reports it
+    last_response: Option<String>,
+    /// Time spent in finished queries
+    active: Duration,
+    query_started: Option<Instant>,
+    /// Set once the running query has been stopped, so it is only reported once
+    stopped: bool,
+}
+
+impl SessionSpend {
+    pub fn start_query(&mut self) {
+        self.query_started = Some(Instant::now());
+        self.clear_query_usage();
+        self.stopped = false;
+    }
+
+    pub fn end_query(&mut self) {
+        if let Some(started) = self.query_started.take() {
+            self.active += started.elapsed();
+        }
+        self.clear_query_usage();
+    }
+
+    /// Usage of an assistant message of the running query. Messages of a response that was
+    /// already counted are ignored.
+    pub fn record_response(&mut self, response_id: Option<&str>, tokens: ResponseTokens) {
+        if response_id.is_some() && response_id == self.last_response.as_deref() {
+            return;
+        }
+        self.last_response = response_id.map(str::to_string);
+        self.query_turns += 1;
+        self.query_tokens.input += tokens.input;
+        self.query_tokens.output += tokens.output;
+        self.query_tokens.cache_read += tokens.cache_read;
+        self.query_tokens.cache_creation += tokens.cache_creation;
+    }
+
+    /// Final usage of a query, which replaces the running estimate
+    pub fn record_usage(&mut self, cost_usd: f64, turns: u64) {
+        self.cost_usd += cost_usd;
+        self.turns += turns;
+        self.clear_query_usage();
+    }
+
+    fn clear_query_usage(&mut self) {
+        self.query_turns = 0;
+        self.query_tokens = ResponseTokens::default();
+        self.last_response = None;
+    }
+
+    /// Mark the running query as stopped; false if it already was
+    pub fn mark_stopped(&mut self) -> bool {
+        !std::mem::replace(&mut self.stopped, true)
+    }
+
+    fn duration(&self) -> Duration {
+        self.active + self.query_started.map(|s| s.elapsed()).unwrap_or_default()
+    }
+
+    /// Cost so far, with the running query estimated at `price` (not counted without one)
+    fn cost_usd(&self, price: Option<&ClaudeModelPrice>) -> f64 {
+        let tokens = &self.query_tokens;
+        self.cost_usd
+            + price.map_or(0.0, |price| {
+                price.cost_usd(
+                    tokens.input,
+                    tokens.output,
+                    tokens.cache_read,
+                    tokens.cache_creation,
+                )
+            })
+    }
+
+    /// The first limit this session has reached, if any. `price` is the price of the session's
+    /// model, used to estimate the cost of the running query.
+    pub fn check(
+        &self,
+        limits: &BudgetLimits,
+        price: Option<&ClaudeModelPrice>,
+    ) -> Option<BudgetExceeded> {
+        if let Some(max) = limits.max_cost_usd {
+            let cost = self.cost_usd(price);
+            if cost >= max {
+                return Some(BudgetExceeded {
+                    kind: BudgetKind::Cost,
+                    limit: max,
+                    used: cost,
+                });
+            }
+        }
+        if let Some(max) = limits.max_turns {
+            let turns = self.turns + self.query_turns;
+            if turns >= max {
+                return Some(BudgetExceeded {
+                    kind: BudgetKind::Turns,
+                    limit: max as f64,
+                    used: turns as f64,
+                });
+            }
+        }
+        if let Some(max) = limits.max_duration_minutes {
+            let minutes = self.duration().as_secs_f64() / 60.0;
+            if minutes >= max as f64 {
+                return Some(BudgetExceeded {
+                    kind: BudgetKind::Duration,
+                    limit: max as f64,
+                    used: minutes,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Limits for a session in `cwd`: the repo's limits, with unset ones taken from the global config
+pub fn limits_for(config: &AppConfig, cwd: &str) -> BudgetLimits {
+    match config.repo_for_path(cwd) {
+        Some(repo) => repo.budget.or(&config.session_budget),
+        None => config.session_budget.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoConfig;
+
+    fn limits(max_cost_usd: Option<f64>, max_turns: Option<u64>) -> BudgetLimits {
+        BudgetLimits {
+            max_cost_usd,
+            max_turns,
+            max_duration_minutes: None,
+        }
+    }
+
+    fn sonnet() -> ClaudeModelPrice {
+        ClaudeModelPrice {
+            model: "claude-sonnet-4-5".to_string(),
+            input_per_million_usd: 3.0,
+            output_per_million_usd: 15.0,
+        }
+    }
+
+    fn tokens(input: u64, output: u64) -> ResponseTokens {
+        ResponseTokens {
+            input,
+            output,
+            ..ResponseTokens::default()
+        }
+    }
+
+    #[test]
+    fn no_limits_never_stop() {
+        let mut spend = SessionSpend::default();
+        spend.start_query();
+        for i in 0..100 {
+            spend.record_response(Some(&format!("msg_{}", i)), tokens(1_000_000, 1_000_000));
+        }
+        assert_eq!(spend.check(&BudgetLimits::default(), Some(&sonnet())), None);
+    }
+
+    #[test]
+    fn turn_limit_counts_running_and_finished_queries() {
+        let limits = limits(None, Some(3));
+        let mut spend = SessionSpend::default();
+        spend.start_query();
+        spend.record_response(Some("msg_1"), tokens(10, 10));
+        // Further content blocks of the same response
+        spend.record_response(Some("msg_1"), tokens(10, 10));
+        spend.record_response(Some("msg_1"), tokens(10, 10));
+        assert_eq!(spend.check(&limits, None), None);
+
+        spend.record_usage(0.0, 2);
+        spend.end_query();
+        assert_eq!(spend.check(&limits, None), None);
+
+        spend.start_query();
+        spend.record_response(Some("msg_2"), tokens(10, 10));
+        assert_eq!(
+            spend.check(&limits, None),
+            Some(BudgetExceeded {
+                kind: BudgetKind::Turns,
+                limit: 3.0,
+                used: 3.0,
+            })
+        );
+    }
+
+    #[test]
+    fn cost_limit_stops_a_running_query() {
+        let limits = limits(Some(1.0), None);
+        let mut spend = SessionSpend::default();
+        spend.start_query();
+        // 200k input and 20k output tokens of Sonnet: $0.90
+        spend.record_response(Some("msg_1"), tokens(200_000, 20_000));
+        spend.record_response(Some("msg_1"), tokens(200_000, 20_000));
+        assert_eq!(spend.check(&limits, Some(&sonnet())), None);
+
+        spend.record_response(Some("msg_2"), tokens(40_000, 0));
+        let exceeded = spend.check(&limits, Some(&sonnet())).expect("cost limit");
+        assert_eq!(exceeded.kind, BudgetKind::Cost);
+        assert!((exceeded.used - 1.02).abs() < 1e-9, "{}", exceeded.used);
+
+        // Without a price the running query can't be estimated
+        assert_eq!(spend.check(&limits, None), None);
+    }
+
+    #[test]
+    fn final_cost_replaces_the_estimate() {
+        let limits = limits(Some(1.0), None);
+        let mut spend = SessionSpend::default();
+        spend.start_query();
+        spend.record_response(Some("msg_1"), tokens(400_000, 0));
+        assert!(spend.check(&limits, Some(&sonnet())).is_some());
+
+        spend.record_usage(0.25, 1);
+        spend.end_query();
+        assert_eq!(spend.check(&limits, Some(&sonnet())), None);
+
+        spend.start_query();
+        spend.record_usage(0.80, 1);
+        let exceeded = spend.check(&limits, Some(&sonnet())).expect("cost limit");
+        assert!((exceeded.used - 1.05).abs() < 1e-9, "{}", exceeded.used);
+    }
+
+    #[test]
+    fn stop_is_reported_once_per_query() {
+        let mut spend = SessionSpend::default();
+        spend.start_query();
+        assert!(spend.mark_stopped());
+        assert!(!spend.mark_stopped());
+        spend.start_query();
+        assert!(spend.mark_stopped());
+    }
+
+    #[test]
+    fn limits_for_repo_falls_back_to_global() {
+        let repo = RepoConfig {
+            path: "/work/app".to_string(),
+            name: "app".to_string(),
+            description: None,
+            keywords: None,
+            vocabulary: None,
+            mcp_servers: None,
+            permissions: Default::default(),
+            budget: limits(None, Some(50)),
+        };
+        let config = AppConfig {
+            repos: vec![repo],
+            session_budget: BudgetLimits {
+                max_cost_usd: Some(5.0),
+                max_turns: Some(10),
+                max_duration_minutes: Some(30),
+            },
+            ..AppConfig::default()
+        };
+
+        let expected = BudgetLimits {
+            max_cost_usd: Some(5.0),
+            max_turns: Some(50),
+            max_duration_minutes: Some(30),
+        };
+        assert_eq!(limits_for(&config, "/work/app"), expected);
+        assert_eq!(limits_for(&config, "/work/app/src"), expected);
+        assert_eq!(limits_for(&config, "/work/other"), config.session_budget);
+    }
+}
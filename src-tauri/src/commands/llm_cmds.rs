@@ -1,23 +1,61 @@
 use crate::commands::usage_cmds::UsageStatsState;
-use crate::config::{AppConfig, LlmProvider};
+use crate::config::{AppConfig, LlmConfig, LlmFeature, LlmProvider};
+use crate::git::GitManager;
 use crate::llm::{
-    ConnectionTestResult, InteractionAnalysis, LlmClient, ModelRecommendation,
-    QuickActionsResult, RepoDescriptionResult, RepoRecommendation, SessionNameResult,
-    SessionOutcomeResult, TranscriptionCleanupResult,
+    clear_response_cache, heuristic_interaction_analysis, heuristic_session_name,
+    lexical_recommendation, match_intent_rules, resolve_classification, CommitMessageResult,
+    ConnectionTestResult, GenerationResult, IntakeNeeds, InteractionAnalysis, LlmClient, LlmUsage,
+    ModelRecommendation,
+    PrDescriptionResult,
+    PromptEnhancementResult, PromptIntakeOptions, PromptIntakeResult, QuickActionsResult,
+    RepoDescriptionResult, RepoEntry, RepoRecommendation, SessionNameResult, SessionOutcomeResult,
+    TranscriptionCleanupResult, VoiceIntent, VoiceIntentAction,
 };
+use crate::repo_profile::profile_repo;
+use crate::session_history::{
+    estimate_restore, format_for_summary, split_recent_turns, CompactedHistory,
+};
+use crate::sidecar::HistoryMessage;
 use parking_lot::Mutex;
 use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, State};
 use tauri_plugin_keyring::KeyringExt;
 
-/// Helper to track LLM usage in the stats
-fn track_usage(stats: &State<UsageStatsState>, feature: &str, input_tokens: u64, output_tokens: u64) {
+/// Helper to track LLM usage (and its cost from the price table) in the stats
+fn track_usage(stats: &State<UsageStatsState>, llm: &LlmConfig, feature: LlmFeature, usage: &LlmUsage) {
     let mut s = stats.lock();
-    s.track_llm_token_usage(feature, input_tokens, output_tokens);
+    s.track_llm_token_usage(
+        feature,
+        &llm.provider,
+        &usage.model,
+        usage.input_tokens,
+        usage.output_tokens,
+        llm.price_for(&usage.model),
+    );
     let _ = s.save();
 }
 
+/// Helper to track a generation that may have been answered from the response cache
+fn track_generation<T>(
+    stats: &State<UsageStatsState>,
+    llm: &LlmConfig,
+    feature: LlmFeature,
+    result: &GenerationResult<T>,
+) {
+    if result.cached {
+        let mut s = stats.lock();
+        s.track_llm_cache_hit(
+            result.usage.input_tokens,
+            result.usage.output_tokens,
+            llm.price_for(&result.usage.model),
+        );
+        let _ = s.save();
+    } else {
+        track_usage(stats, llm, feature, &result.usage);
+    }
+}
+
 /// Service name for keyring storage
 const KEYRING_SERVICE: &str = "claude-whisperer";
 /// User/account name for the LLM API key
@@ -103,7 +141,8 @@ fn create_client(app: &AppHandle, config: &AppConfig) -> Result<LlmClient, Strin
         llm_config.endpoint.clone(),
         llm_config.auto_model,
         llm_config.model_priority.clone(),
-    ))
+    )
+    .with_cache(llm_config.cache.clone()))
 }
 
 /// Save the API key to the system keyring
@@ -183,7 +222,14 @@ pub async fn test_gemini_connection(
     client.test_connection().await
 }
 
+/// Clear the LLM response cache, returning the number of removed entries
+#[tauri::command]
+pub fn clear_llm_cache() -> Result<usize, String> {
+    clear_response_cache()
+}
+
 /// Generate a session name from the user prompt (called immediately when prompt is sent)
+/// Falls back to a keyword-based name when the LLM is disabled or the request fails
 #[tauri::command]
 pub async fn generate_session_name(
     app: AppHandle,
@@ -193,17 +239,28 @@ pub async fn generate_session_name(
 ) -> Result<SessionNameResult, String> {
     let cfg = config.lock().clone();
 
+    // Without an LLM, name the session locally
     if !cfg.llm.enabled {
-        return Err("LLM integration is not enabled".to_string());
+        return Ok(heuristic_session_name(&user_prompt));
     }
 
-    let client = create_client(&app, &cfg)?;
-    let result = client.generate_session_name_with_usage(&user_prompt).await?;
-
-    // Track usage
-    track_usage(&stats, "session_naming", result.usage.input_tokens, result.usage.output_tokens);
+    let result = match create_client(&app, &cfg) {
+        Ok(client) => client.generate_session_name_with_usage(&user_prompt).await,
+        Err(e) => Err(e),
+    };
 
-    Ok(result.data)
+    match result {
+        Ok(result) => {
+            // Track usage
+            track_generation(&stats, &cfg.llm, LlmFeature::SessionNaming, &result);
+            Ok(result.data)
+        }
+        Err(e) => {
+            // Rate limits and provider errors shouldn't leave the session unnamed
+            eprintln!("[llm] Session naming failed, using heuristic name: {}", e);
+            Ok(heuristic_session_name(&user_prompt))
+        }
+    }
 }
 
 /// Generate a session outcome after the session completes
@@ -227,12 +284,13 @@ pub async fn generate_session_outcome(
         .await?;
 
     // Track usage
-    track_usage(&stats, "session_outcome", result.usage.input_tokens, result.usage.output_tokens);
+    track_generation(&stats, &cfg.llm, LlmFeature::SessionOutcome, &result);
 
     Ok(result.data)
 }
 
 /// Analyze if the last message needs human interaction
+/// Falls back to rule-based detection when the LLM is disabled or the request fails
 #[tauri::command]
 pub async fn analyze_interaction_needed(
     app: AppHandle,
@@ -242,17 +300,27 @@ pub async fn analyze_interaction_needed(
 ) -> Result<InteractionAnalysis, String> {
     let cfg = config.lock().clone();
 
+    // Without an LLM, detect blocking questions locally
     if !cfg.llm.enabled {
-        return Err("LLM integration is not enabled".to_string());
+        return Ok(heuristic_interaction_analysis(&last_message));
     }
 
-    let client = create_client(&app, &cfg)?;
-    let result = client.analyze_interaction_needed_with_usage(&last_message).await?;
-
-    // Track usage
-    track_usage(&stats, "interaction_analysis", result.usage.input_tokens, result.usage.output_tokens);
+    let result = match create_client(&app, &cfg) {
+        Ok(client) => client.analyze_interaction_needed_with_usage(&last_message).await,
+        Err(e) => Err(e),
+    };
 
-    Ok(result.data)
+    match result {
+        Ok(result) => {
+            // Track usage
+            track_generation(&stats, &cfg.llm, LlmFeature::InteractionAnalysis, &result);
+            Ok(result.data)
+        }
+        Err(e) => {
+            eprintln!("[llm] Interaction analysis failed, using heuristic detection: {}", e);
+            Ok(heuristic_interaction_analysis(&last_message))
+        }
+    }
 }
 
 /// Clean up a voice transcription
@@ -291,7 +359,7 @@ pub async fn clean_transcription(
         .await?;
 
     // Track usage
-    track_usage(&stats, "transcription_cleanup", result.usage.input_tokens, result.usage.output_tokens);
+    track_usage(&stats, &cfg.llm, LlmFeature::TranscriptionCleanup, &result.usage);
 
     Ok(result.data)
 }
@@ -318,7 +386,7 @@ pub async fn recommend_model(
     let result = client.recommend_model_with_usage(&prompt).await?;
 
     // Track usage
-    track_usage(&stats, "model_recommendation", result.usage.input_tokens, result.usage.output_tokens);
+    track_usage(&stats, &cfg.llm, LlmFeature::ModelRecommendation, &result.usage);
 
     Ok(result.data)
 }
@@ -340,6 +408,23 @@ pub async fn generate_repo_description(
 
     let client = create_client(&app, &cfg)?;
 
+    // Optionally send the compact offline scan instead of the full docs
+    let scan_digest = if cfg.llm.repo_description_from_scan {
+        let (path, name) = (repo_path.clone(), repo_name.clone());
+        let scan = tauri::async_runtime::spawn_blocking(move || profile_repo(&path, &name))
+            .await
+            .map_err(|e| format!("Task failed: {}", e))?;
+        match scan {
+            Ok(profile) => Some(profile.digest),
+            Err(e) => {
+                eprintln!("[llm] Repo scan failed, using docs instead: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // Try to read CLAUDE.md first, then README.md
     let repo_path = PathBuf::from(&repo_path);
 
@@ -353,16 +438,22 @@ pub async fn generate_repo_description(
     };
 
     let result = client
-        .generate_repo_description_with_usage(&repo_name, claude_md_content.as_deref(), readme_content.as_deref())
+        .generate_repo_description_with_usage(
+            &repo_name,
+            claude_md_content.as_deref(),
+            readme_content.as_deref(),
+            scan_digest.as_deref(),
+        )
         .await?;
 
     // Track usage
-    track_usage(&stats, "repo_description", result.usage.input_tokens, result.usage.output_tokens);
+    track_usage(&stats, &cfg.llm, LlmFeature::RepoDescription, &result.usage);
 
     Ok(result.data)
 }
 
-/// Recommend the best repository for a given prompt
+/// Recommend the best repository for a given prompt.
+/// Falls back to the offline lexical matcher when the LLM is disabled, has no API key or fails.
 #[tauri::command]
 pub async fn recommend_repo(
     app: AppHandle,
@@ -373,18 +464,12 @@ pub async fn recommend_repo(
 ) -> Result<RepoRecommendation, String> {
     let cfg = config.lock().clone();
 
-    if !cfg.llm.enabled {
-        return Err("LLM integration is not enabled".to_string());
-    }
-
     if !cfg.llm.features.auto_select_repo {
         return Err("Auto-select repository feature is not enabled".to_string());
     }
 
-    let client = create_client(&app, &cfg)?;
-
     // Build repos list with descriptions, keywords, and vocabulary
-    let repos: Vec<(String, String, Option<String>, Option<Vec<String>>, Option<Vec<String>>)> = cfg
+    let repos: Vec<RepoEntry> = cfg
         .repos
         .iter()
         .map(|r| (
@@ -396,11 +481,32 @@ pub async fn recommend_repo(
         ))
         .collect();
 
-    let result = client.recommend_repo_with_usage(&prompt, &repos, is_transcribed.unwrap_or(false)).await?;
+    if !cfg.llm.enabled {
+        return Ok(lexical_recommendation(&prompt, &repos));
+    }
+
+    let client = match create_client(&app, &cfg) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("[llm] Using lexical repo recommendation: {}", e);
+            return Ok(lexical_recommendation(&prompt, &repos));
+        }
+    };
+
+    let result = match client
+        .recommend_repo_with_usage(&prompt, &repos, is_transcribed.unwrap_or(false), cfg.llm.repo_shortlist_size)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("[llm] Repo recommendation failed, using lexical fallback: {}", e);
+            return Ok(lexical_recommendation(&prompt, &repos));
+        }
+    };
 
     // Track usage (only if we actually made an LLM call - not for empty repos)
     if !repos.is_empty() {
-        track_usage(&stats, "repo_recommendation", result.usage.input_tokens, result.usage.output_tokens);
+        track_usage(&stats, &cfg.llm, LlmFeature::RepoRecommendation, &result.usage);
     }
 
     Ok(result.data)
@@ -431,7 +537,317 @@ pub async fn generate_quick_actions(
         .await?;
 
     // Track usage
-    track_usage(&stats, "quick_actions", result.usage.input_tokens, result.usage.output_tokens);
+    track_generation(&stats, &cfg.llm, LlmFeature::QuickActions, &result);
+
+    Ok(result.data)
+}
+
+/// Compact the history of a restored session: older turns are replaced with an LLM summary
+/// (files touched, decisions, open TODOs) and only the most recent turns are kept verbatim
+#[tauri::command]
+pub async fn compact_session_history(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    messages: Vec<HistoryMessage>,
+) -> Result<CompactedHistory, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.enabled {
+        return Err("LLM integration is not enabled".to_string());
+    }
+
+    let recent_turns = cfg.session_persistence.restore_recent_turns;
+    let estimate = estimate_restore(&messages, recent_turns);
+    let (older, recent) = split_recent_turns(&messages, recent_turns);
+
+    // Nothing old enough to summarize, or the history is short enough already - replay as is
+    if older.is_empty() || estimate.compacted_tokens >= estimate.full_tokens {
+        return Ok(CompactedHistory {
+            messages,
+            summary: None,
+            estimate,
+        });
+    }
+
+    let client = create_client(&app, &cfg)?;
+    let result = client
+        .summarize_conversation_with_usage(&format_for_summary(older))
+        .await?;
+
+    track_usage(&stats, &cfg.llm, LlmFeature::HistoryCompaction, &result.usage);
+
+    println!(
+        "[llm] Compacted {} older turns ({} -> ~{} tokens)",
+        estimate.older_turns, estimate.full_tokens, estimate.compacted_tokens
+    );
+
+    let mut compacted = vec![HistoryMessage::Summary {
+        content: result.data.to_history_text(),
+    }];
+    compacted.extend_from_slice(recent);
+
+    Ok(CompactedHistory {
+        messages: compacted,
+        summary: Some(result.data),
+        estimate,
+    })
+}
+
+/// Rewrite a prompt into a structured task spec, using the target repo's description
+/// and vocabulary as context
+#[tauri::command]
+pub async fn enhance_prompt(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    prompt: String,
+    repo_path: Option<String>,
+) -> Result<PromptEnhancementResult, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.enabled {
+        return Err("LLM integration is not enabled".to_string());
+    }
+
+    if !cfg.llm.features.enhance_prompts {
+        return Err("Prompt enhancement feature is not enabled".to_string());
+    }
+
+    let repo_context = repo_path
+        .as_deref()
+        .and_then(|path| cfg.repos.iter().find(|r| r.path == path))
+        .map(|repo| {
+            let mut parts = vec![format!("Repository: {}", repo.name)];
+            if let Some(description) = &repo.description {
+                parts.push(format!("Description: {}", description));
+            }
+            if let Some(vocabulary) = repo.vocabulary.as_ref().filter(|v| !v.is_empty()) {
+                parts.push(format!("Vocabulary (project-specific terms): {}", vocabulary.join(", ")));
+            }
+            parts.join("\n")
+        });
+
+    let client = create_client(&app, &cfg)?;
+    let result = client
+        .enhance_prompt_with_usage(&prompt, repo_context.as_deref())
+        .await?;
+
+    // Track usage
+    track_usage(&stats, &cfg.llm, LlmFeature::PromptEnhancement, &result.usage);
+
+    let system_prompt = result.data.to_system_prompt();
+    Ok(PromptEnhancementResult {
+        spec: result.data,
+        system_prompt,
+    })
+}
+
+/// Decide whether a voice transcript is an app-control instruction or a coding prompt.
+/// Local rules handle clear cases; the LLM is only asked about ambiguous transcripts.
+#[tauri::command]
+pub async fn route_voice_intent(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    transcript: String,
+) -> Result<VoiceIntent, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.features.route_voice_intents {
+        return Err("Voice intent routing is not enabled".to_string());
+    }
+
+    let repos: Vec<RepoEntry> = cfg
+        .repos
+        .iter()
+        .map(|r| (
+            r.name.clone(),
+            r.path.clone(),
+            r.description.clone(),
+            r.keywords.clone(),
+            r.vocabulary.clone(),
+        ))
+        .collect();
+
+    if let Some(intent) = match_intent_rules(&transcript, &repos, &cfg.enabled_models) {
+        return Ok(intent);
+    }
+
+    let prompt_intent = VoiceIntent {
+        action: VoiceIntentAction::Prompt,
+        confidence: "low".to_string(),
+        source: "rules".to_string(),
+    };
+
+    if !cfg.llm.enabled {
+        return Ok(prompt_intent);
+    }
+
+    let client = match create_client(&app, &cfg) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("[llm] Voice intent classification unavailable: {}", e);
+            return Ok(prompt_intent);
+        }
+    };
+
+    match client.classify_voice_intent_with_usage(&transcript, &repos).await {
+        Ok(result) => {
+            track_usage(&stats, &cfg.llm, LlmFeature::VoiceIntent, &result.usage);
+            Ok(resolve_classification(result.data, &repos, &cfg.enabled_models))
+        }
+        Err(e) => {
+            eprintln!("[llm] Voice intent classification failed: {}", e);
+            Ok(prompt_intent)
+        }
+    }
+}
+
+/// Collect the diff a session produced (staged changes, or everything since `base_commit`)
+fn session_diff(repo_path: &str, base_commit: Option<&str>, staged_only: bool) -> Result<String, String> {
+    let diff = GitManager::get_diff(repo_path, base_commit, staged_only)?;
+    if diff.trim().is_empty() {
+        return Err("No changes found to describe".to_string());
+    }
+    Ok(diff)
+}
+
+/// Generate a conventional commit message from the session's git diff
+#[tauri::command]
+pub async fn generate_commit_message(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    repo_path: String,
+    prompt: String,
+    base_commit: Option<String>,
+    staged_only: Option<bool>,
+) -> Result<CommitMessageResult, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.enabled {
+        return Err("LLM integration is not enabled".to_string());
+    }
+
+    let diff = session_diff(&repo_path, base_commit.as_deref(), staged_only.unwrap_or(false))?;
+
+    let client = create_client(&app, &cfg)?;
+    let result = client
+        .generate_commit_message_with_usage(&prompt, &diff)
+        .await?;
+
+    // Track usage
+    track_usage(&stats, &cfg.llm, LlmFeature::ChangeSummary, &result.usage);
+
+    Ok(result.data)
+}
+
+/// Generate a pull request title and body from the session's git diff
+#[tauri::command]
+pub async fn generate_pr_description(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    repo_path: String,
+    prompt: String,
+    base_commit: Option<String>,
+    staged_only: Option<bool>,
+) -> Result<PrDescriptionResult, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.enabled {
+        return Err("LLM integration is not enabled".to_string());
+    }
+
+    let diff = session_diff(&repo_path, base_commit.as_deref(), staged_only.unwrap_or(false))?;
+
+    let client = create_client(&app, &cfg)?;
+    let result = client
+        .generate_pr_description_with_usage(&prompt, &diff)
+        .await?;
+
+    // Track usage
+    track_usage(&stats, &cfg.llm, LlmFeature::ChangeSummary, &result.usage);
+
+    Ok(result.data)
+}
+
+/// Run all enabled prompt intake features (session naming, transcription cleanup,
+/// model and repo recommendation) in a single LLM request. `needs` leaves out
+/// recommendations the caller has no use for.
+#[tauri::command]
+pub async fn run_prompt_intake(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+    stats: State<'_, UsageStatsState>,
+    prompt: String,
+    vosk_transcription: Option<String>,
+    repo_context: Option<String>,
+    is_transcribed: Option<bool>,
+    needs: Option<IntakeNeeds>,
+) -> Result<PromptIntakeResult, String> {
+    let cfg = config.lock().clone();
+
+    if !cfg.llm.enabled {
+        return Err("LLM integration is not enabled".to_string());
+    }
+
+    let is_transcribed = is_transcribed.unwrap_or(false);
+    let needs = needs.unwrap_or_default();
+    let features = &cfg.llm.features;
+    let options = PromptIntakeOptions {
+        name_session: features.auto_name_sessions,
+        clean_transcription: features.clean_transcription && is_transcribed,
+        recommend_model: features.recommend_model && needs.model,
+        recommend_repo: features.auto_select_repo && cfg.repos.len() > 1 && needs.repo,
+        repo_shortlist_size: cfg.llm.repo_shortlist_size,
+    };
+
+    if !(options.name_session || options.clean_transcription || options.recommend_model || options.recommend_repo) {
+        return Ok(PromptIntakeResult::default());
+    }
+
+    let client = create_client(&app, &cfg)?;
+
+    // Only use dual transcription if the feature is enabled and Vosk transcription is provided
+    let vosk = if features.use_dual_transcription && cfg.vosk.enabled {
+        vosk_transcription.as_deref()
+    } else {
+        None
+    };
+
+    let repos: Vec<RepoEntry> = cfg
+        .repos
+        .iter()
+        .map(|r| (
+            r.name.clone(),
+            r.path.clone(),
+            r.description.clone(),
+            r.keywords.clone(),
+            r.vocabulary.clone(),
+        ))
+        .collect();
+
+    let (result, feature_usage) = client
+        .run_prompt_intake_with_usage(&prompt, vosk, repo_context.as_deref(), &repos, is_transcribed, &options)
+        .await?;
+
+    // Track usage as one request, split between the included features
+    let feature_usage: Vec<(LlmFeature, u64, u64)> = feature_usage
+        .into_iter()
+        .map(|u| (u.feature, u.input_tokens, u.output_tokens))
+        .collect();
+    {
+        let mut s = stats.lock();
+        s.track_llm_intake_usage(
+            &feature_usage,
+            &cfg.llm.provider,
+            &result.usage.model,
+            cfg.llm.price_for(&result.usage.model),
+        );
+        let _ = s.save();
+    }
 
     Ok(result.data)
 }
@@ -0,0 +1,76 @@
+use crate::config::McpServerConfig;
+use crate::plan_file::{self, PlanDocument, PlanImplementation, PlanWatcher};
+use crate::sidecar::{OutboundMessage, SidecarManager, REQUEST_TIMEOUT};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+
+#[tauri::command]
+pub fn read_plan_file(cwd: String, plan_path: String) -> Result<PlanDocument, String> {
+    plan_file::load_plan(&cwd, &plan_path)
+}
+
+/// Parse a plan and report its progress to `session_id` as the file changes
+#[tauri::command]
+pub fn watch_plan_file(
+    app: AppHandle,
+    plans: State<Arc<PlanWatcher>>,
+    session_id: String,
+    cwd: String,
+    plan_path: String,
+) -> Result<PlanDocument, String> {
+    let plan = plan_file::load_plan(&cwd, &plan_path)?;
+    plans.watch(app, session_id, &cwd, plan.clone());
+    Ok(plan)
+}
+
+#[tauri::command]
+pub fn unwatch_plan_file(plans: State<Arc<PlanWatcher>>, session_id: String) -> bool {
+    plans.unwatch(&session_id)
+}
+
+/// Create an implementation session for a completed plan and start watching the plan for it.
+/// The returned prompt (the plan's open items) is sent by the caller once it listens for the
+/// session's events; thinking is set the same way as for any new session.
+#[tauri::command]
+pub async fn spawn_plan_implementation(
+    app: AppHandle,
+    sidecar: State<'_, Arc<SidecarManager>>,
+    cwd: String,
+    plan_path: String,
+    feature_name: String,
+    model: Option<String>,
+    mcp_servers: Option<Vec<McpServerConfig>>,
+) -> Result<PlanImplementation, String> {
+    if !sidecar.is_started() {
+        return Err("Sidecar not started. Call start_sidecar first.".to_string());
+    }
+
+    let plan = plan_file::load_plan(&cwd, &plan_path)?;
+    let session_id = uuid::Uuid::new_v4().to_string();
+    sidecar
+        .send_request(
+            OutboundMessage::Create {
+                id: session_id.clone(),
+                cwd: cwd.clone(),
+                model,
+                system_prompt: None,
+                messages: None,
+                plan_mode: None,
+                mcp_servers,
+            },
+            REQUEST_TIMEOUT,
+        )
+        .await?;
+
+    println!(
+        "[spawn_plan_implementation] Session {} implements {} ({} of {} items done)",
+        session_id, plan_path, plan.checked_items, plan.total_items
+    );
+    app.state::<Arc<PlanWatcher>>()
+        .watch(app.clone(), session_id.clone(), &cwd, plan.clone());
+    Ok(PlanImplementation {
+        prompt: plan_file::implementation_prompt(&plan, &feature_name),
+        session_id,
+        plan,
+    })
+}
@@ -1,19 +1,69 @@
-use crate::config::McpServerConfig;
-use crate::sidecar::{HistoryMessage, ImageData, OutboundMessage, SidecarManager};
+use crate::config::{AppConfig, McpServerConfig};
+use crate::permissions::{exact_rules, PermissionDecision};
+use crate::planning::{self, PlanningAnswer};
+use crate::prompt_queue::QueuedPrompt;
+use crate::session_history::{estimate_restore, history_from_persisted, HistoryTokenEstimate};
+use crate::session_persistence::{PersistedSdkSession, PersistedSessions};
+use crate::sidecar::{
+    HistoryMessage, ImageData, OutboundMessage, SidecarInfo, SidecarManager, REQUEST_TIMEOUT,
+};
+use crate::sidecar_journal::SidecarJournal;
+use crate::sidecar_runtime::{self, SidecarDiagnosis};
+use crate::subagent_transcript::{self, SubagentTranscript};
+use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 
+/// Thinking budget for sessions with thinking on (matches `THINKING_BUDGETS` in the frontend)
+const THINKING_BUDGET_TOKENS: u32 = 31999;
+
+/// Start the sidecar and wait for its ready handshake (off the main thread)
 #[tauri::command]
-pub fn start_sidecar(
+pub async fn start_sidecar(
     app: AppHandle,
-    sidecar: State<Arc<SidecarManager>>,
+    sidecar: State<'_, Arc<SidecarManager>>,
 ) -> Result<(), String> {
-    sidecar.start(app)
+    start_blocking(app, sidecar.inner().clone()).await
+}
+
+async fn start_blocking(app: AppHandle, sidecar: Arc<SidecarManager>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || sidecar.start(app))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
 }
 
+/// Protocol version, SDK version and capabilities the running sidecar reported
 #[tauri::command]
-pub fn create_sdk_session(
-    sidecar: State<Arc<SidecarManager>>,
+pub fn get_sidecar_info(sidecar: State<Arc<SidecarManager>>) -> Option<SidecarInfo> {
+    sidecar.info()
+}
+
+/// Report which node binary and sidecar script would be used, and why other candidates failed
+#[tauri::command]
+pub async fn diagnose_sidecar(
+    app: AppHandle,
+    config: State<'_, Mutex<AppConfig>>,
+) -> Result<SidecarDiagnosis, String> {
+    let node_path = config.lock().system.node_path.clone();
+    let resource_dir = app.path().resource_dir().ok();
+    tauri::async_runtime::spawn_blocking(move || {
+        sidecar_runtime::diagnose(node_path.as_deref(), resource_dir.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))
+}
+
+/// Most recent sidecar stderr lines, for diagnosing crashes
+#[tauri::command]
+pub fn get_sidecar_stderr(sidecar: State<Arc<SidecarManager>>) -> Vec<String> {
+    sidecar.stderr_tail()
+}
+
+/// Create a session in the sidecar; resolves once the sidecar has created it
+#[tauri::command]
+pub async fn create_sdk_session(
+    sidecar: State<'_, Arc<SidecarManager>>,
     id: String,
     cwd: String,
     model: String, // Per-session model (required)
@@ -25,20 +75,112 @@ pub fn create_sdk_session(
     if !sidecar.is_started() {
         return Err("Sidecar not started. Call start_sidecar first.".to_string());
     }
-    sidecar.send(OutboundMessage::Create { id, cwd, model: Some(model), system_prompt, messages, plan_mode, mcp_servers })
+    sidecar
+        .send_request(
+            OutboundMessage::Create { id, cwd, model: Some(model), system_prompt, messages, plan_mode, mcp_servers },
+            REQUEST_TIMEOUT,
+        )
+        .await
 }
 
+/// Estimate the history tokens a restored session sends when resumed, in full and compacted
+#[tauri::command]
+pub fn estimate_history_tokens(
+    config: State<'_, Mutex<AppConfig>>,
+    messages: Vec<HistoryMessage>,
+) -> HistoryTokenEstimate {
+    let recent_turns = config.lock().session_persistence.restore_recent_turns;
+    estimate_restore(&messages, recent_turns)
+}
+
+/// Send a prompt. While the session is running a query the prompt is queued instead and the
+/// queued item is returned; it is sent when the query finishes.
 #[tauri::command]
 pub fn send_sdk_prompt(
+    app: AppHandle,
     sidecar: State<Arc<SidecarManager>>,
     id: String,
     prompt: String,
     images: Option<Vec<ImageData>>,
-) -> Result<(), String> {
+) -> Result<Option<QueuedPrompt>, String> {
     if !sidecar.is_started() {
         return Err("Sidecar not started".to_string());
     }
-    sidecar.send(OutboundMessage::Query { id, prompt, images })
+    if let Some(exceeded) = sidecar.check_budget(&app, &id) {
+        return Err(format!(
+            "Session budget reached ({}). Raise the limit to continue.",
+            exceeded.describe()
+        ));
+    }
+    sidecar.submit_prompt(&app, &id, prompt, images)
+}
+
+#[tauri::command]
+pub fn get_sdk_prompt_queue(
+    sidecar: State<Arc<SidecarManager>>,
+    id: String,
+) -> Result<Vec<QueuedPrompt>, String> {
+    sidecar.prompt_queue(&id)
+}
+
+#[tauri::command]
+pub fn cancel_queued_sdk_prompt(
+    app: AppHandle,
+    sidecar: State<Arc<SidecarManager>>,
+    id: String,
+    item_id: String,
+) -> Result<Vec<QueuedPrompt>, String> {
+    sidecar.update_prompt_queue(&app, &id, |queue| queue.cancel(&item_id).map(|_| ()))
+}
+
+/// Move a queued prompt to `index` (0 runs next)
+#[tauri::command]
+pub fn move_queued_sdk_prompt(
+    app: AppHandle,
+    sidecar: State<Arc<SidecarManager>>,
+    id: String,
+    item_id: String,
+    index: usize,
+) -> Result<Vec<QueuedPrompt>, String> {
+    sidecar.update_prompt_queue(&app, &id, |queue| queue.move_to(&item_id, index))
+}
+
+/// Put back the saved queue of a restored session. It stays paused until the user runs it.
+#[tauri::command]
+pub fn restore_sdk_prompt_queue(
+    app: AppHandle,
+    sidecar: State<Arc<SidecarManager>>,
+    id: String,
+    items: Vec<QueuedPrompt>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    sidecar.update_prompt_queue(&app, &id, |queue| {
+        queue.replace(items);
+        queue.pause();
+        Ok(())
+    })
+}
+
+/// Resume a paused queue, sending its next prompt if the session is idle
+#[tauri::command]
+pub fn run_sdk_prompt_queue(
+    app: AppHandle,
+    sidecar: State<Arc<SidecarManager>>,
+    id: String,
+) -> Result<bool, String> {
+    if !sidecar.is_started() {
+        return Err("Sidecar not started".to_string());
+    }
+    if let Some(exceeded) = sidecar.check_budget(&app, &id) {
+        return Err(format!(
+            "Session budget reached ({}). Raise the limit to continue.",
+            exceeded.describe()
+        ));
+    }
+    sidecar.update_prompt_queue(&app, &id, |queue| {
+        queue.resume();
+        Ok(())
+    })?;
+    Ok(sidecar.run_next_queued(&app, &id))
 }
 
 #[tauri::command]
@@ -53,38 +195,252 @@ pub fn stop_sdk_query(
 }
 
 #[tauri::command]
-pub fn update_sdk_model(
-    sidecar: State<Arc<SidecarManager>>,
+pub async fn update_sdk_model(
+    sidecar: State<'_, Arc<SidecarManager>>,
     id: String,
     model: String,
 ) -> Result<(), String> {
     if !sidecar.is_started() {
         return Err("Sidecar not started".to_string());
     }
-    sidecar.send(OutboundMessage::UpdateModel { id, model })
+    sidecar.send_request(OutboundMessage::UpdateModel { id, model }, REQUEST_TIMEOUT).await
 }
 
 #[tauri::command]
-pub fn update_sdk_thinking(
-    sidecar: State<Arc<SidecarManager>>,
+pub async fn update_sdk_thinking(
+    sidecar: State<'_, Arc<SidecarManager>>,
     id: String,
     max_thinking_tokens: Option<u32>,
 ) -> Result<(), String> {
     if !sidecar.is_started() {
         return Err("Sidecar not started".to_string());
     }
-    sidecar.send(OutboundMessage::UpdateThinking { id, max_thinking_tokens })
+    sidecar.send_request(OutboundMessage::UpdateThinking { id, max_thinking_tokens }, REQUEST_TIMEOUT).await
 }
 
+/// Send the answers to a session's planning questions as its next turn. Returns the text of
+/// that turn for display.
 #[tauri::command]
-pub fn close_sdk_session(
+pub fn answer_planning_questions(
+    app: AppHandle,
     sidecar: State<Arc<SidecarManager>>,
     id: String,
+    answers: Vec<PlanningAnswer>,
+) -> Result<String, String> {
+    if !sidecar.is_started() {
+        return Err("Sidecar not started".to_string());
+    }
+    if !sidecar.info().is_some_and(|info| info.supports("planning_answers")) {
+        return Err(
+            "The sidecar does not support planning answers. Rebuild it (npm run build in src-tauri/sidecar)."
+                .to_string(),
+        );
+    }
+    if sidecar.is_querying(&id) {
+        return Err("The session is still running; answer once it has finished".to_string());
+    }
+    if let Some(exceeded) = sidecar.check_budget(&app, &id) {
+        return Err(format!(
+            "Session budget reached ({}). Raise the limit to continue.",
+            exceeded.describe()
+        ));
+    }
+
+    // A restored session has not been asked again; use the questions saved with it
+    let mut questions = sidecar
+        .planning_questions(&id)
+        .ok_or_else(|| format!("Session {} is not live", id))?;
+    if questions.is_empty() {
+        questions = PersistedSessions::load()
+            .sdk_sessions
+            .into_iter()
+            .find(|s| s.id == id)
+            .and_then(|s| s.plan_mode)
+            .map(|plan_mode| plan_mode.questions)
+            .unwrap_or_default();
+    }
+    if questions.is_empty() {
+        return Err(format!("Session {} has no planning questions to answer", id));
+    }
+
+    let answered = planning::answer_questions(&questions, answers)?;
+    if answered.is_empty() {
+        return Err("No question was answered".to_string());
+    }
+    let text = planning::answers_text(&answered);
+    sidecar.send(OutboundMessage::PlanningAnswers {
+        id,
+        answers: answered,
+    })?;
+    Ok(text)
+}
+
+/// Parse the transcript of a finished Task subagent (the `transcriptPath` of its
+/// `subagent_stop` message) into its messages and a summary of what it did
+#[tauri::command]
+pub async fn read_subagent_transcript(
+    config: State<'_, Mutex<AppConfig>>,
+    transcript_path: String,
+) -> Result<SubagentTranscript, String> {
+    let path = transcript_path.clone();
+    let pricing = config.lock().claude_pricing.clone();
+    let transcript = tauri::async_runtime::spawn_blocking(move || {
+        subagent_transcript::load_transcript(&path, &pricing)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    if transcript.skipped_lines > 0 {
+        eprintln!(
+            "[read_subagent_transcript] Skipped {} unreadable lines of {}",
+            transcript.skipped_lines, transcript_path
+        );
+    }
+    Ok(transcript)
+}
+
+/// Fork a persisted session: a new live session with the same cwd, model, thinking and MCP
+/// servers whose history is the parent's messages up to and including `message_index`.
+/// The fork is saved with a link to its parent and returned.
+#[tauri::command]
+pub async fn fork_sdk_session(
+    sidecar: State<'_, Arc<SidecarManager>>,
+    session_id: String,
+    message_index: usize,
+    model: Option<String>, // Resolved model, when the parent requested "auto"
+    mcp_servers: Option<Vec<McpServerConfig>>, // Used when the parent is not live
+) -> Result<PersistedSdkSession, String> {
+    if !sidecar.is_started() {
+        return Err("Sidecar not started. Call start_sidecar first.".to_string());
+    }
+
+    let mut persisted = PersistedSessions::load();
+    let parent = persisted
+        .sdk_sessions
+        .iter()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| format!("Session {} not found in saved sessions", session_id))?;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let fork = parent.fork(uuid::Uuid::new_v4().to_string(), message_index, now)?;
+
+    let messages = history_from_persisted(&fork.messages);
+    let mcp_servers = sidecar.session_mcp_servers(&session_id).or(mcp_servers);
+    sidecar
+        .send_request(
+            OutboundMessage::Create {
+                id: fork.id.clone(),
+                cwd: fork.cwd.clone(),
+                model: Some(model.unwrap_or_else(|| fork.model.clone())),
+                system_prompt: None,
+                messages: (!messages.is_empty()).then_some(messages),
+                plan_mode: None,
+                mcp_servers,
+            },
+            REQUEST_TIMEOUT,
+        )
+        .await?;
+    if fork.thinking_level.is_some() {
+        sidecar
+            .send_request(
+                OutboundMessage::UpdateThinking {
+                    id: fork.id.clone(),
+                    max_thinking_tokens: Some(THINKING_BUDGET_TOKENS),
+                },
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+    }
+
+    println!(
+        "[fork_sdk_session] Forked {} at message {} into {}",
+        session_id, message_index, fork.id
+    );
+    persisted.sdk_sessions.push(fork.clone());
+    persisted.save()?;
+    Ok(fork)
+}
+
+#[tauri::command]
+pub async fn close_sdk_session(
+    sidecar: State<'_, Arc<SidecarManager>>,
+    id: String,
 ) -> Result<(), String> {
     if !sidecar.is_started() {
         return Err("Sidecar not started".to_string());
     }
-    sidecar.send(OutboundMessage::Close { id })
+    sidecar.send_request(OutboundMessage::Close { id }, REQUEST_TIMEOUT).await
+}
+
+/// Replay the inbound traffic of a recorded sidecar journal through the normal event handling.
+/// Defaults to the current journal file; see `SidecarManager::replay_journal`.
+#[tauri::command]
+pub async fn replay_sidecar_journal(
+    app: AppHandle,
+    path: Option<String>,
+    session_id: Option<String>,
+    target_session_id: Option<String>,
+    realtime: Option<bool>,
+) -> Result<usize, String> {
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(SidecarJournal::default_path);
+    tauri::async_runtime::spawn_blocking(move || {
+        SidecarManager::replay_journal(
+            &app,
+            &path,
+            session_id.as_deref(),
+            target_session_id.as_deref(),
+            realtime.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Answer a tool permission request the rules escalated to the user.
+/// "Allow always" also adds rules for the exact call to the repository containing the session.
+#[tauri::command]
+pub fn respond_sdk_permission(
+    sidecar: State<'_, Arc<SidecarManager>>,
+    config: State<'_, Mutex<AppConfig>>,
+    id: String,
+    tool_use_id: String,
+    decision: PermissionDecision,
+    updated_input: Option<serde_json::Value>,
+    message: Option<String>,
+) -> Result<(), String> {
+    if !sidecar.is_started() {
+        return Err("Sidecar not started".to_string());
+    }
+    let request = sidecar
+        .pending_permission(&id, &tool_use_id)
+        .ok_or_else(|| format!("No pending permission request {} for session {}", tool_use_id, id))?;
+
+    if decision == PermissionDecision::AllowAlways {
+        let rules = exact_rules(&request.tool, updated_input.as_ref().unwrap_or(&request.input));
+        let mut cfg = config.lock();
+        let repo = cfg
+            .repo_for_path_mut(&request.cwd)
+            .ok_or_else(|| {
+                format!("No configured repository contains {}, so the rule can't be saved", request.cwd)
+            })?;
+        let added: Vec<String> = rules
+            .into_iter()
+            .filter(|rule| !repo.permissions.allow.contains(rule))
+            .collect();
+        if !added.is_empty() {
+            println!("[respond_sdk_permission] Added rules {} for {}", added.join(", "), repo.path);
+            repo.permissions.allow.extend(added);
+            cfg.save()?;
+        }
+    }
+
+    sidecar.send(OutboundMessage::PermissionResponse {
+        id,
+        tool_use_id,
+        decision,
+        updated_input,
+        message,
+    })
 }
 
 /// Generate repository description using Claude SDK (Haiku model)
@@ -92,16 +448,16 @@ pub fn close_sdk_session(
 /// Results are returned via `repo-description-result-{id}` event.
 /// Errors are returned via `repo-description-error-{id}` event.
 #[tauri::command]
-pub fn generate_repo_description_with_claude(
+pub async fn generate_repo_description_with_claude(
     app: AppHandle,
-    sidecar: State<Arc<SidecarManager>>,
+    sidecar: State<'_, Arc<SidecarManager>>,
     id: String,
     repo_path: String,
     repo_name: String,
 ) -> Result<(), String> {
     // Start sidecar if not already running
     if !sidecar.is_started() {
-        sidecar.start(app)?;
+        start_blocking(app, sidecar.inner().clone()).await?;
     }
 
     sidecar.send(OutboundMessage::GenerateRepoDescription {
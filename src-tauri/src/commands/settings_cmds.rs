@@ -1,8 +1,11 @@
 use crate::config::{AppConfig, RepoConfig};
 use crate::git::GitManager;
+use crate::repo_profile::{profile_repo, RepoProfile};
+use crate::sidecar::SidecarManager;
 use tauri::State;
 use parking_lot::Mutex;
 use std::process::Command;
+use std::sync::Arc;
 
 pub type ConfigState = Mutex<AppConfig>;
 
@@ -12,17 +15,36 @@ pub fn get_config(config: State<ConfigState>) -> AppConfig {
 }
 
 #[tauri::command]
-pub fn save_config(config: State<ConfigState>, new_config: AppConfig) -> Result<(), String> {
+pub fn save_config(
+    config: State<ConfigState>,
+    sidecar: State<Arc<SidecarManager>>,
+    new_config: AppConfig,
+) -> Result<(), String> {
     let mut cfg = config.lock();
+    sidecar.set_journal_enabled(new_config.system.sidecar_journal);
+    sidecar.set_stream_interval_ms(new_config.system.stream_flush_interval_ms);
     *cfg = new_config;
     cfg.save()
 }
 
 #[tauri::command]
-pub fn add_repo(config: State<ConfigState>, path: String, name: String) -> Result<(), String> {
+pub async fn add_repo(config: State<'_, ConfigState>, path: String, name: String) -> Result<(), String> {
     println!("[add_repo] Called with path: {}, name: {}", path, name);
+    // Profile the repo offline so auto-selection works before any LLM description is generated
+    let profile = match scan_blocking(path.clone(), name.clone()).await {
+        Ok(profile) => Some(profile),
+        Err(e) => {
+            println!("[add_repo] Offline repo scan failed: {}", e);
+            None
+        }
+    };
+    let (description, keywords, vocabulary) = match profile {
+        Some(p) => (Some(p.description), Some(p.keywords), Some(p.vocabulary)),
+        None => (None, None, None),
+    };
+
     let mut cfg = config.lock();
-    cfg.repos.push(RepoConfig { path: path.clone(), name: name.clone(), description: None, keywords: None, vocabulary: None, mcp_servers: None });
+    cfg.repos.push(RepoConfig { path: path.clone(), name: name.clone(), description, keywords, vocabulary, mcp_servers: None, permissions: Default::default(), budget: Default::default() });
     println!("[add_repo] Repo added to config, total repos: {}", cfg.repos.len());
     let result = cfg.save();
     match &result {
@@ -32,6 +54,19 @@ pub fn add_repo(config: State<ConfigState>, path: String, name: String) -> Resul
     result
 }
 
+/// Scan a repository offline and derive its description, keywords and vocabulary
+#[tauri::command]
+pub async fn scan_repo_profile(repo_path: String, repo_name: String) -> Result<RepoProfile, String> {
+    scan_blocking(repo_path, repo_name).await
+}
+
+/// The scan walks the whole tree, so keep it off the async runtime
+async fn scan_blocking(repo_path: String, repo_name: String) -> Result<RepoProfile, String> {
+    tauri::async_runtime::spawn_blocking(move || profile_repo(&repo_path, &repo_name))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
+}
+
 #[tauri::command]
 pub fn remove_repo(config: State<ConfigState>, index: usize) -> Result<(), String> {
     let mut cfg = config.lock();
@@ -76,6 +111,11 @@ pub fn get_git_branch(repo_path: String) -> Result<String, String> {
     GitManager::get_current_branch(&repo_path)
 }
 
+#[tauri::command]
+pub fn get_git_head(repo_path: String) -> Result<String, String> {
+    GitManager::get_head_commit(&repo_path)
+}
+
 /// Run a command in a new terminal window (cross-platform)
 #[tauri::command]
 pub fn run_in_terminal(command: String) -> Result<(), String> {
@@ -1,4 +1,4 @@
-use crate::config::UsageStats;
+use crate::config::{AppConfig, LlmFeature, UsageStats};
 use parking_lot::Mutex;
 use tauri::State;
 
@@ -65,13 +65,25 @@ pub fn track_token_usage(
 
 #[tauri::command]
 pub fn track_llm_token_usage(
+    config: State<Mutex<AppConfig>>,
     stats: State<UsageStatsState>,
     feature: String,
     input_tokens: u64,
     output_tokens: u64,
+    model: Option<String>,
 ) -> Result<(), String> {
+    let feature: LlmFeature = feature.parse()?;
+    let llm = config.lock().llm.clone();
+    let model = model.unwrap_or_else(|| llm.model.clone());
     let mut s = stats.lock();
-    s.track_llm_token_usage(&feature, input_tokens, output_tokens);
+    s.track_llm_token_usage(
+        feature,
+        &llm.provider,
+        &model,
+        input_tokens,
+        output_tokens,
+        llm.price_for(&model),
+    );
     s.save()
 }
 
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Provider type for Whisper-compatible APIs
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -217,6 +218,19 @@ pub struct SystemConfig {
     pub minimize_to_tray: bool,
     pub start_minimized: bool,
     pub autostart: bool,
+    /// Record raw sidecar traffic to a rotating journal for debugging
+    #[serde(default)]
+    pub sidecar_journal: bool,
+    /// Node binary for the SDK sidecar; discovered automatically when unset
+    #[serde(default)]
+    pub node_path: Option<String>,
+    /// Interval streaming SDK text and usage updates are batched over, in ms (0 disables batching)
+    #[serde(default = "default_stream_flush_interval_ms")]
+    pub stream_flush_interval_ms: u64,
+}
+
+fn default_stream_flush_interval_ms() -> u64 {
+    50
 }
 
 impl Default for SystemConfig {
@@ -225,6 +239,9 @@ impl Default for SystemConfig {
             minimize_to_tray: false,
             start_minimized: false,
             autostart: false,
+            sidecar_journal: false,
+            node_path: None,
+            stream_flush_interval_ms: default_stream_flush_interval_ms(),
         }
     }
 }
@@ -235,18 +252,30 @@ pub struct SessionPersistenceConfig {
     pub max_sessions: usize,
     #[serde(default = "default_restore_sessions")]
     pub restore_sessions: usize,
+    /// When resuming a restored session, replace older turns with an LLM summary
+    #[serde(default)]
+    pub compact_history: bool,
+    /// Number of most recent turns replayed verbatim when compacting history
+    #[serde(default = "default_restore_recent_turns")]
+    pub restore_recent_turns: usize,
 }
 
 fn default_restore_sessions() -> usize {
     10
 }
 
+fn default_restore_recent_turns() -> usize {
+    4
+}
+
 impl Default for SessionPersistenceConfig {
     fn default() -> Self {
         Self {
             enabled: true,
             max_sessions: 50,
             restore_sessions: 10,
+            compact_history: false,
+            restore_recent_turns: default_restore_recent_turns(),
         }
     }
 }
@@ -274,34 +303,168 @@ pub struct TokenStats {
     pub total_cost_usd: f64,
 }
 
+/// Features of the LLM integration layer, used as keys of the usage breakdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmFeature {
+    SessionNaming,
+    SessionOutcome,
+    InteractionAnalysis,
+    QuickActions,
+    TranscriptionCleanup,
+    ModelRecommendation,
+    RepoDescription,
+    RepoRecommendation,
+    /// Combined naming/cleanup/recommendation request
+    PromptIntake,
+    /// Commit message and PR description generation from session diffs
+    ChangeSummary,
+    /// Voice intent classification (only ambiguous transcripts reach the LLM)
+    VoiceIntent,
+    PromptEnhancement,
+    /// Conversation summaries for restored sessions
+    HistoryCompaction,
+}
+
+impl LlmFeature {
+    pub const ALL: &'static [LlmFeature] = &[
+        LlmFeature::SessionNaming,
+        LlmFeature::SessionOutcome,
+        LlmFeature::InteractionAnalysis,
+        LlmFeature::QuickActions,
+        LlmFeature::TranscriptionCleanup,
+        LlmFeature::ModelRecommendation,
+        LlmFeature::RepoDescription,
+        LlmFeature::RepoRecommendation,
+        LlmFeature::PromptIntake,
+        LlmFeature::ChangeSummary,
+        LlmFeature::VoiceIntent,
+        LlmFeature::PromptEnhancement,
+        LlmFeature::HistoryCompaction,
+    ];
+
+    /// Key used in the usage breakdown, response cache keys and the frontend
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LlmFeature::SessionNaming => "session_naming",
+            LlmFeature::SessionOutcome => "session_outcome",
+            LlmFeature::InteractionAnalysis => "interaction_analysis",
+            LlmFeature::QuickActions => "quick_actions",
+            LlmFeature::TranscriptionCleanup => "transcription_cleanup",
+            LlmFeature::ModelRecommendation => "model_recommendation",
+            LlmFeature::RepoDescription => "repo_description",
+            LlmFeature::RepoRecommendation => "repo_recommendation",
+            LlmFeature::PromptIntake => "prompt_intake",
+            LlmFeature::ChangeSummary => "change_summary",
+            LlmFeature::VoiceIntent => "voice_intent",
+            LlmFeature::PromptEnhancement => "prompt_enhancement",
+            LlmFeature::HistoryCompaction => "history_compaction",
+        }
+    }
+}
+
+impl std::str::FromStr for LlmFeature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|f| f.as_str() == s)
+            .ok_or_else(|| format!("Unknown LLM feature: {}", s))
+    }
+}
+
+/// Requests, tokens and estimated cost for one slice of LLM usage
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct LlmUsageCounter {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl LlmUsageCounter {
+    fn add(&mut self, input_tokens: u64, output_tokens: u64, cost_usd: f64) {
+        self.requests += 1;
+        self.input_tokens += input_tokens;
+        self.output_tokens += output_tokens;
+        self.cost_usd += cost_usd;
+    }
+}
+
+/// LLM usage for a day or month: totals plus the per-feature breakdown
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct LlmPeriodUsage {
+    pub total: LlmUsageCounter,
+    pub by_feature: BTreeMap<String, LlmUsageCounter>,
+}
+
 /// Token usage stats for the LLM integration layer (Gemini/OpenAI/Groq/Local)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LlmTokenStats {
     pub total_input_tokens: u64,
     pub total_output_tokens: u64,
     pub total_requests: u64,
-    /// Breakdown by feature (requests + input/output tokens)
-    pub session_naming_requests: u64,
-    pub session_naming_input_tokens: u64,
-    pub session_naming_output_tokens: u64,
-    pub session_outcome_requests: u64,
-    pub session_outcome_input_tokens: u64,
-    pub session_outcome_output_tokens: u64,
-    pub interaction_analysis_requests: u64,
-    pub interaction_analysis_input_tokens: u64,
-    pub interaction_analysis_output_tokens: u64,
-    pub transcription_cleanup_requests: u64,
-    pub transcription_cleanup_input_tokens: u64,
-    pub transcription_cleanup_output_tokens: u64,
-    pub model_recommendation_requests: u64,
-    pub model_recommendation_input_tokens: u64,
-    pub model_recommendation_output_tokens: u64,
-    pub repo_description_requests: u64,
-    pub repo_description_input_tokens: u64,
-    pub repo_description_output_tokens: u64,
-    pub repo_recommendation_requests: u64,
-    pub repo_recommendation_input_tokens: u64,
-    pub repo_recommendation_output_tokens: u64,
+    /// Estimated cost from the configured price table
+    #[serde(default)]
+    pub total_cost_usd: f64,
+    /// Requests whose model has no entry in the price table (not included in the cost)
+    #[serde(default)]
+    pub unpriced_requests: u64,
+    /// Breakdown by feature (`LlmFeature::as_str` keys). A prompt intake request counts as a
+    /// request of each feature it included, with its tokens and cost split between them.
+    #[serde(default)]
+    pub by_feature: BTreeMap<String, LlmUsageCounter>,
+    /// Breakdown by provider and model, e.g. `Groq/llama-3.1-8b-instant`
+    #[serde(default)]
+    pub by_model: BTreeMap<String, LlmUsageCounter>,
+    /// Requests answered from the response cache (not counted in the totals);
+    /// tokens and cost are the ones saved
+    #[serde(default)]
+    pub cache_saved: LlmUsageCounter,
+    /// Per-month usage, oldest first
+    #[serde(default)]
+    pub monthly: Vec<LlmMonthlyStats>,
+    /// Counters of the old flat layout (`session_naming_requests`, ...), folded into
+    /// `by_feature` on load
+    #[serde(flatten, skip_serializing)]
+    legacy_fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl LlmTokenStats {
+    /// Move counters from the old one-field-per-feature layout into `by_feature`
+    fn migrate_legacy_fields(&mut self) {
+        if self.legacy_fields.is_empty() {
+            return;
+        }
+        let legacy = std::mem::take(&mut self.legacy_fields);
+        let count = |key: &str| legacy.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        for feature in LlmFeature::ALL {
+            let name = feature.as_str();
+            let requests = count(&format!("{}_requests", name));
+            if requests == 0 {
+                continue;
+            }
+            let counter = self.by_feature.entry(name.to_string()).or_default();
+            counter.requests += requests;
+            counter.input_tokens += count(&format!("{}_input_tokens", name));
+            counter.output_tokens += count(&format!("{}_output_tokens", name));
+        }
+
+        println!("[usage] Migrated LLM usage stats to the per-feature layout");
+    }
+}
+
+/// LLM integration usage for one calendar month
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LlmMonthlyStats {
+    pub month: String, // YYYY-MM format
+    #[serde(flatten)]
+    pub usage: LlmPeriodUsage,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -325,6 +488,8 @@ pub struct DailyStats {
     pub prompts: u64,
     pub recordings: u64,
     pub tool_calls: u64,
+    #[serde(default)]
+    pub llm: LlmPeriodUsage,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -378,8 +543,11 @@ impl UsageStats {
         let path = Self::stats_path();
         if path.exists() {
             match fs::read_to_string(&path) {
-                Ok(content) => match serde_json::from_str(&content) {
-                    Ok(stats) => return stats,
+                Ok(content) => match serde_json::from_str::<Self>(&content) {
+                    Ok(mut stats) => {
+                        stats.llm_token_stats.migrate_legacy_fields();
+                        return stats;
+                    }
                     Err(e) => eprintln!("Failed to parse usage stats: {}", e),
                 },
                 Err(e) => eprintln!("Failed to read usage stats: {}", e),
@@ -413,6 +581,7 @@ impl UsageStats {
                 prompts: 0,
                 recordings: 0,
                 tool_calls: 0,
+                ..Default::default()
             });
             // Keep only last 90 days
             if self.daily_stats.len() > 90 {
@@ -537,57 +706,132 @@ impl UsageStats {
         self.token_stats.total_cost_usd += cost_usd;
     }
 
-    /// Track token usage from the LLM integration layer (Gemini/OpenAI/Groq/Local)
+    /// Track token usage from the LLM integration layer (Gemini/OpenAI/Groq/Local).
+    /// `price` is the price table entry for the model that served the request, if any.
     pub fn track_llm_token_usage(
         &mut self,
-        feature: &str,
+        feature: LlmFeature,
+        provider: &LlmProvider,
+        model: &str,
         input_tokens: u64,
         output_tokens: u64,
+        price: Option<&LlmModelPrice>,
     ) {
-        // Update totals
-        self.llm_token_stats.total_input_tokens += input_tokens;
-        self.llm_token_stats.total_output_tokens += output_tokens;
-        self.llm_token_stats.total_requests += 1;
-
-        // Update per-feature stats (input/output separately)
-        match feature {
-            "session_naming" => {
-                self.llm_token_stats.session_naming_requests += 1;
-                self.llm_token_stats.session_naming_input_tokens += input_tokens;
-                self.llm_token_stats.session_naming_output_tokens += output_tokens;
-            }
-            "session_outcome" => {
-                self.llm_token_stats.session_outcome_requests += 1;
-                self.llm_token_stats.session_outcome_input_tokens += input_tokens;
-                self.llm_token_stats.session_outcome_output_tokens += output_tokens;
-            }
-            "interaction_analysis" => {
-                self.llm_token_stats.interaction_analysis_requests += 1;
-                self.llm_token_stats.interaction_analysis_input_tokens += input_tokens;
-                self.llm_token_stats.interaction_analysis_output_tokens += output_tokens;
-            }
-            "transcription_cleanup" => {
-                self.llm_token_stats.transcription_cleanup_requests += 1;
-                self.llm_token_stats.transcription_cleanup_input_tokens += input_tokens;
-                self.llm_token_stats.transcription_cleanup_output_tokens += output_tokens;
-            }
-            "model_recommendation" => {
-                self.llm_token_stats.model_recommendation_requests += 1;
-                self.llm_token_stats.model_recommendation_input_tokens += input_tokens;
-                self.llm_token_stats.model_recommendation_output_tokens += output_tokens;
-            }
-            "repo_description" => {
-                self.llm_token_stats.repo_description_requests += 1;
-                self.llm_token_stats.repo_description_input_tokens += input_tokens;
-                self.llm_token_stats.repo_description_output_tokens += output_tokens;
-            }
-            "repo_recommendation" => {
-                self.llm_token_stats.repo_recommendation_requests += 1;
-                self.llm_token_stats.repo_recommendation_input_tokens += input_tokens;
-                self.llm_token_stats.repo_recommendation_output_tokens += output_tokens;
+        let cost = price.map(|p| p.cost_usd(input_tokens, output_tokens));
+        self.track_llm_request(provider, model, input_tokens, output_tokens, cost);
+        self.track_llm_feature(feature, input_tokens, output_tokens, cost.unwrap_or(0.0));
+    }
+
+    /// Track a combined prompt intake request: one request overall, with its tokens split
+    /// between the included features as `(feature, input_tokens, output_tokens)`
+    pub fn track_llm_intake_usage(
+        &mut self,
+        feature_usage: &[(LlmFeature, u64, u64)],
+        provider: &LlmProvider,
+        model: &str,
+        price: Option<&LlmModelPrice>,
+    ) {
+        let input_tokens: u64 = feature_usage.iter().map(|(_, input, _)| input).sum();
+        let output_tokens: u64 = feature_usage.iter().map(|(_, _, output)| output).sum();
+        let cost_of = |input: u64, output: u64| price.map(|p| p.cost_usd(input, output));
+
+        let cost = cost_of(input_tokens, output_tokens);
+        self.track_llm_request(provider, model, input_tokens, output_tokens, cost);
+        for (feature, input, output) in feature_usage {
+            self.track_llm_feature(*feature, *input, *output, cost_of(*input, *output).unwrap_or(0.0));
+        }
+    }
+
+    /// Track a request answered from the LLM response cache (tokens are the ones saved)
+    pub fn track_llm_cache_hit(
+        &mut self,
+        input_tokens: u64,
+        output_tokens: u64,
+        price: Option<&LlmModelPrice>,
+    ) {
+        let cost = price.map(|p| p.cost_usd(input_tokens, output_tokens));
+        self.llm_token_stats
+            .cache_saved
+            .add(input_tokens, output_tokens, cost.unwrap_or(0.0));
+    }
+
+    fn get_current_month() -> String {
+        chrono::Local::now().format("%Y-%m").to_string()
+    }
+
+    fn current_llm_month(&mut self) -> &mut LlmPeriodUsage {
+        let month = Self::get_current_month();
+        let monthly = &mut self.llm_token_stats.monthly;
+        if monthly.last().map(|m| &m.month) != Some(&month) {
+            monthly.push(LlmMonthlyStats {
+                month,
+                usage: LlmPeriodUsage::default(),
+            });
+
+            // Keep only last 24 months
+            if monthly.len() > 24 {
+                monthly.remove(0);
             }
-            _ => {}
         }
+        &mut monthly.last_mut().expect("current month was just ensured").usage
+    }
+
+    /// Update request, token and cost totals (overall, by model, today and this month)
+    fn track_llm_request(
+        &mut self,
+        provider: &LlmProvider,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        cost_usd: Option<f64>,
+    ) {
+        let stats = &mut self.llm_token_stats;
+        stats.total_input_tokens += input_tokens;
+        stats.total_output_tokens += output_tokens;
+        stats.total_requests += 1;
+        match cost_usd {
+            Some(cost) => stats.total_cost_usd += cost,
+            None => stats.unpriced_requests += 1,
+        }
+        let cost_usd = cost_usd.unwrap_or(0.0);
+        stats
+            .by_model
+            .entry(format!("{}/{}", provider.as_str(), model))
+            .or_default()
+            .add(input_tokens, output_tokens, cost_usd);
+
+        self.ensure_today_stats();
+        if let Some(today) = self.daily_stats.last_mut() {
+            today.llm.total.add(input_tokens, output_tokens, cost_usd);
+        }
+        self.current_llm_month()
+            .total
+            .add(input_tokens, output_tokens, cost_usd);
+    }
+
+    /// Update the per-feature breakdown (overall, today and this month)
+    fn track_llm_feature(&mut self, feature: LlmFeature, input_tokens: u64, output_tokens: u64, cost_usd: f64) {
+        let key = feature.as_str();
+        self.llm_token_stats
+            .by_feature
+            .entry(key.to_string())
+            .or_default()
+            .add(input_tokens, output_tokens, cost_usd);
+
+        self.ensure_today_stats();
+        if let Some(today) = self.daily_stats.last_mut() {
+            today
+                .llm
+                .by_feature
+                .entry(key.to_string())
+                .or_default()
+                .add(input_tokens, output_tokens, cost_usd);
+        }
+        self.current_llm_month()
+            .by_feature
+            .entry(key.to_string())
+            .or_default()
+            .add(input_tokens, output_tokens, cost_usd);
     }
 
     fn update_streak(&mut self) {
@@ -787,6 +1031,106 @@ pub struct RepoConfig {
     /// List of MCP server IDs to use for this repository (overrides global servers)
     #[serde(default)]
     pub mcp_servers: Option<Vec<String>>,
+    /// Tool permission rules for SDK sessions in this repository
+    #[serde(default)]
+    pub permissions: PermissionRules,
+    /// Budget limits for SDK sessions in this repository; unset limits fall back to the global ones
+    #[serde(default)]
+    pub budget: BudgetLimits,
+}
+
+/// Tool permission patterns such as `Read`, `Bash(cargo test*)` or `mcp__github__*`.
+/// `*` matches any run of characters; deny rules are checked before allow rules.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PermissionRules {
+    /// Tool calls matching these run without asking
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Tool calls matching these are refused without asking
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Limits on what a single SDK session may spend. A session that reaches one is stopped.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BudgetLimits {
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+    /// Agent turns (model round trips) across all queries
+    #[serde(default)]
+    pub max_turns: Option<u64>,
+    /// Time spent running queries, in minutes
+    #[serde(default)]
+    pub max_duration_minutes: Option<u64>,
+}
+
+impl BudgetLimits {
+    /// These limits with unset ones taken from `fallback`
+    pub fn or(&self, fallback: &BudgetLimits) -> BudgetLimits {
+        BudgetLimits {
+            max_cost_usd: self.max_cost_usd.or(fallback.max_cost_usd),
+            max_turns: self.max_turns.or(fallback.max_turns),
+            max_duration_minutes: self.max_duration_minutes.or(fallback.max_duration_minutes),
+        }
+    }
+}
+
+/// Price of a Claude model in USD per million tokens, used to estimate what SDK sessions and
+/// their subagents cost before (or without) the SDK reporting it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClaudeModelPrice {
+    /// Model name; also matches dated/suffixed variants (`claude-sonnet-4-5` matches `claude-sonnet-4-5-20250929`)
+    pub model: String,
+    pub input_per_million_usd: f64,
+    pub output_per_million_usd: f64,
+}
+
+impl ClaudeModelPrice {
+    fn new(model: &str, input: f64, output: f64) -> Self {
+        Self {
+            model: model.to_string(),
+            input_per_million_usd: input,
+            output_per_million_usd: output,
+        }
+    }
+
+    /// Entry of `pricing` for a model. An exact match wins, otherwise the longest entry the
+    /// model name starts with.
+    pub fn find<'a>(pricing: &'a [ClaudeModelPrice], model: &str) -> Option<&'a ClaudeModelPrice> {
+        pricing
+            .iter()
+            .filter(|p| model.starts_with(p.model.as_str()))
+            .max_by_key(|p| p.model.len())
+    }
+
+    /// Cost of a response. Cache writes cost 1.25x and cache reads 0.1x the input price.
+    pub fn cost_usd(
+        &self,
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_read_tokens: u64,
+        cache_creation_tokens: u64,
+    ) -> f64 {
+        let input = input_tokens as f64
+            + cache_creation_tokens as f64 * 1.25
+            + cache_read_tokens as f64 * 0.1;
+        (input * self.input_per_million_usd + output_tokens as f64 * self.output_per_million_usd)
+            / 1_000_000.0
+    }
+}
+
+/// Default Claude prices (USD per million tokens, list prices as of late 2025)
+fn default_claude_pricing() -> Vec<ClaudeModelPrice> {
+    vec![
+        ClaudeModelPrice::new("claude-opus-4-5", 5.0, 25.0),
+        ClaudeModelPrice::new("claude-opus-4-1", 15.0, 75.0),
+        ClaudeModelPrice::new("claude-opus-4", 15.0, 75.0),
+        ClaudeModelPrice::new("claude-sonnet-4-5", 3.0, 15.0),
+        ClaudeModelPrice::new("claude-sonnet-4", 3.0, 15.0),
+        ClaudeModelPrice::new("claude-3-7-sonnet", 3.0, 15.0),
+        ClaudeModelPrice::new("claude-haiku-4-5", 1.0, 5.0),
+        ClaudeModelPrice::new("claude-3-5-haiku", 0.80, 4.0),
+    ]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -1008,6 +1352,12 @@ pub struct AppConfig {
     /// MCP server configuration
     #[serde(default)]
     pub mcp: McpConfig,
+    /// Budget limits for every SDK session (repos can override them)
+    #[serde(default)]
+    pub session_budget: BudgetLimits,
+    /// Claude prices for estimating SDK session and subagent costs
+    #[serde(default = "default_claude_pricing")]
+    pub claude_pricing: Vec<ClaudeModelPrice>,
 }
 
 fn default_mark_sessions_unread() -> bool {
@@ -1041,6 +1391,19 @@ pub enum LlmProvider {
     Custom,
 }
 
+impl LlmProvider {
+    /// Stable name for cache keys and usage stats (the same as the serialized name)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProvider::Groq => "Groq",
+            LlmProvider::Gemini => "Gemini",
+            LlmProvider::OpenAI => "OpenAI",
+            LlmProvider::Local => "Local",
+            LlmProvider::Custom => "Custom",
+        }
+    }
+}
+
 // Type alias for backwards compatibility
 pub type GeminiProvider = LlmProvider;
 
@@ -1092,6 +1455,18 @@ pub struct LlmFeaturesConfig {
     /// Auto-select repository based on prompt content
     #[serde(default)]
     pub auto_select_repo: bool,
+    /// Run naming, cleanup and model/repo recommendation as one combined request
+    #[serde(default)]
+    pub combined_intake: bool,
+    /// Recognize spoken app-control instructions (switch repo, change model, stop session, ...)
+    #[serde(default)]
+    pub route_voice_intents: bool,
+    /// Rewrite prompts into a structured task spec (goal, constraints, acceptance criteria, ...)
+    #[serde(default)]
+    pub enhance_prompts: bool,
+    /// Pass the task spec to Claude as the session system prompt
+    #[serde(default)]
+    pub task_spec_system_prompt: bool,
 }
 
 // Type alias for backwards compatibility
@@ -1107,6 +1482,10 @@ impl Default for LlmFeaturesConfig {
             use_dual_transcription: false,
             recommend_model: false,
             auto_select_repo: false,
+            combined_intake: false,
+            route_voice_intents: false,
+            enhance_prompts: false,
+            task_spec_system_prompt: false,
         }
     }
 }
@@ -1136,9 +1515,101 @@ pub struct LlmConfig {
     /// Minimum confidence level required for auto-selecting a repository
     #[serde(default)]
     pub min_auto_select_confidence: RepoAutoSelectConfidence,
+    /// Maximum number of repos sent to the LLM for repo recommendation;
+    /// larger lists are pre-filtered to the best lexical matches
+    #[serde(default = "default_repo_shortlist_size")]
+    pub repo_shortlist_size: usize,
+    /// Send the offline repo scan summary instead of the full CLAUDE.md/README
+    /// when generating repo descriptions (fewer input tokens)
+    #[serde(default)]
+    pub repo_description_from_scan: bool,
+    /// On-disk cache for repeated requests (session names, outcomes, quick actions)
+    #[serde(default)]
+    pub cache: LlmCacheConfig,
+    /// Per-model prices used to estimate the cost of LLM requests
+    #[serde(default = "default_llm_pricing")]
+    pub pricing: Vec<LlmModelPrice>,
     // API key is stored securely, not in config
 }
 
+/// Response cache settings for the LLM integration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmCacheConfig {
+    pub enabled: bool,
+    /// Entries older than this are ignored and pruned
+    pub ttl_hours: u64,
+    /// Oldest entries are evicted beyond this count
+    pub max_entries: usize,
+}
+
+impl Default for LlmCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_hours: 24 * 7,
+            max_entries: 500,
+        }
+    }
+}
+
+/// Price of a provider/model in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmModelPrice {
+    pub provider: LlmProvider,
+    /// Model name; also matches dated/suffixed variants (`gpt-4o-mini` matches `gpt-4o-mini-2024-07-18`)
+    pub model: String,
+    pub input_per_million_usd: f64,
+    pub output_per_million_usd: f64,
+}
+
+impl LlmModelPrice {
+    fn new(provider: LlmProvider, model: &str, input: f64, output: f64) -> Self {
+        Self {
+            provider,
+            model: model.to_string(),
+            input_per_million_usd: input,
+            output_per_million_usd: output,
+        }
+    }
+
+    pub fn cost_usd(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million_usd
+            + output_tokens as f64 * self.output_per_million_usd)
+            / 1_000_000.0
+    }
+}
+
+/// Default prices (USD per million tokens, list prices as of late 2025).
+/// Local and custom endpoints have no entry, so their requests are counted as unpriced.
+fn default_llm_pricing() -> Vec<LlmModelPrice> {
+    use LlmProvider::{Gemini, Groq, OpenAI};
+    vec![
+        LlmModelPrice::new(Gemini, "gemini-2.5-flash-lite", 0.10, 0.40),
+        LlmModelPrice::new(Gemini, "gemini-2.5-flash", 0.30, 2.50),
+        LlmModelPrice::new(OpenAI, "gpt-4o-mini", 0.15, 0.60),
+        LlmModelPrice::new(OpenAI, "gpt-4o", 2.50, 10.00),
+        LlmModelPrice::new(OpenAI, "gpt-4-turbo", 10.00, 30.00),
+        LlmModelPrice::new(OpenAI, "gpt-3.5-turbo", 0.50, 1.50),
+        LlmModelPrice::new(Groq, "meta-llama/llama-4-maverick-17b-128e-instruct", 0.20, 0.60),
+        LlmModelPrice::new(Groq, "meta-llama/llama-4-scout-17b-16e-instruct", 0.11, 0.34),
+        LlmModelPrice::new(Groq, "llama-3.3-70b-versatile", 0.59, 0.79),
+        LlmModelPrice::new(Groq, "llama-3.1-8b-instant", 0.05, 0.08),
+        LlmModelPrice::new(Groq, "openai/gpt-oss-120b", 0.15, 0.75),
+        LlmModelPrice::new(Groq, "openai/gpt-oss-20b", 0.10, 0.50),
+    ]
+}
+
+impl LlmConfig {
+    /// Price table entry for a model of the configured provider. An exact match wins,
+    /// otherwise the longest entry the model name starts with.
+    pub fn price_for(&self, model: &str) -> Option<&LlmModelPrice> {
+        self.pricing
+            .iter()
+            .filter(|p| p.provider == self.provider && model.starts_with(p.model.as_str()))
+            .max_by_key(|p| p.model.len())
+    }
+}
+
 // Type alias for backwards compatibility
 pub type GeminiConfig = LlmConfig;
 
@@ -1150,6 +1621,10 @@ fn default_auto_model() -> bool {
     true
 }
 
+fn default_repo_shortlist_size() -> usize {
+    8
+}
+
 impl Default for LlmConfig {
     fn default() -> Self {
         Self {
@@ -1162,6 +1637,10 @@ impl Default for LlmConfig {
             features: LlmFeaturesConfig::default(),
             confirm_repo_selection: false,
             min_auto_select_confidence: RepoAutoSelectConfidence::default(),
+            repo_shortlist_size: default_repo_shortlist_size(),
+            repo_description_from_scan: false,
+            cache: LlmCacheConfig::default(),
+            pricing: default_llm_pricing(),
         }
     }
 }
@@ -1205,6 +1684,8 @@ impl Default for AppConfig {
             sessions_view: SessionsViewConfig::default(),
             llm: LlmConfig::default(),
             mcp: McpConfig::default(),
+            session_budget: BudgetLimits::default(),
+            claude_pricing: default_claude_pricing(),
         }
     }
 }
@@ -1259,4 +1740,192 @@ impl AppConfig {
     pub fn get_active_repo(&self) -> Option<&RepoConfig> {
         self.repos.get(self.active_repo_index)
     }
+
+    pub fn claude_price_for(&self, model: &str) -> Option<&ClaudeModelPrice> {
+        ClaudeModelPrice::find(&self.claude_pricing, model)
+    }
+
+    /// The repository containing `path` (the repo root or any directory below it). When
+    /// repositories are nested the innermost one wins. Paths are compared by component, so
+    /// trailing slashes and `.` segments don't matter.
+    pub fn repo_for_path(&self, path: &str) -> Option<&RepoConfig> {
+        self.repo_index_for_path(path).map(|i| &self.repos[i])
+    }
+
+    pub fn repo_for_path_mut(&mut self, path: &str) -> Option<&mut RepoConfig> {
+        self.repo_index_for_path(path).map(|i| &mut self.repos[i])
+    }
+
+    fn repo_index_for_path(&self, path: &str) -> Option<usize> {
+        let path = Path::new(path);
+        self.repos
+            .iter()
+            .enumerate()
+            .filter(|(_, repo)| !repo.path.is_empty() && path.starts_with(&repo.path))
+            .max_by_key(|(_, repo)| Path::new(&repo.path).components().count())
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(path: &str) -> RepoConfig {
+        RepoConfig {
+            path: path.to_string(),
+            name: path.to_string(),
+            description: None,
+            keywords: None,
+            vocabulary: None,
+            mcp_servers: None,
+            permissions: PermissionRules::default(),
+            budget: BudgetLimits::default(),
+        }
+    }
+
+    fn price(provider: LlmProvider, model: &str) -> LlmModelPrice {
+        LlmModelPrice::new(provider, model, 1.0, 2.0)
+    }
+
+    #[test]
+    fn price_for_prefers_exact_then_longest_prefix() {
+        let config = LlmConfig {
+            provider: LlmProvider::OpenAI,
+            pricing: vec![
+                price(LlmProvider::OpenAI, "gpt-4o"),
+                price(LlmProvider::OpenAI, "gpt-4o-mini"),
+                price(LlmProvider::Groq, "gpt-4o-nano"),
+            ],
+            ..LlmConfig::default()
+        };
+        let model = |name: &str| config.price_for(name).map(|p| p.model.as_str());
+
+        assert_eq!(model("gpt-4o"), Some("gpt-4o"));
+        assert_eq!(model("gpt-4o-mini"), Some("gpt-4o-mini"));
+        assert_eq!(model("gpt-4o-mini-2024-07-18"), Some("gpt-4o-mini"));
+        assert_eq!(model("gpt-4o-2024-08-06"), Some("gpt-4o"));
+        // Entries of other providers don't apply
+        assert_eq!(model("gpt-4o-nano"), Some("gpt-4o"));
+        assert_eq!(model("gpt-4"), None);
+        assert_eq!(model("o3"), None);
+    }
+
+    #[test]
+    fn model_price_cost() {
+        let price = LlmModelPrice::new(LlmProvider::Groq, "m", 0.5, 2.0);
+        assert!((price.cost_usd(2_000_000, 500_000) - 2.0).abs() < 1e-12);
+        assert_eq!(price.cost_usd(0, 0), 0.0);
+    }
+
+    #[test]
+    fn legacy_llm_stats_move_into_by_feature() {
+        let json = r#"{
+            "total_input_tokens": 900,
+            "total_output_tokens": 90,
+            "total_requests": 4,
+            "session_naming_requests": 3,
+            "session_naming_input_tokens": 600,
+            "session_naming_output_tokens": 60,
+            "repo_recommendation_requests": 1,
+            "repo_recommendation_input_tokens": 300,
+            "repo_recommendation_output_tokens": 30,
+            "session_outcome_requests": 0,
+            "session_outcome_input_tokens": 0,
+            "session_outcome_output_tokens": 0
+        }"#;
+        let mut stats: LlmTokenStats = serde_json::from_str(json).unwrap();
+        stats.migrate_legacy_fields();
+
+        assert_eq!(stats.total_requests, 4);
+        let features: Vec<_> = stats
+            .by_feature
+            .iter()
+            .map(|(name, c)| (name.as_str(), c.requests, c.input_tokens, c.output_tokens))
+            .collect();
+        assert_eq!(
+            features,
+            [("repo_recommendation", 1, 300, 30), ("session_naming", 3, 600, 60)]
+        );
+
+        // Migrated fields are not written back, so the next load doesn't count them again
+        let saved = serde_json::to_value(&stats).unwrap();
+        assert!(saved.get("session_naming_requests").is_none());
+        let mut reloaded: LlmTokenStats = serde_json::from_value(saved).unwrap();
+        reloaded.migrate_legacy_fields();
+        assert_eq!(reloaded.by_feature["session_naming"].requests, 3);
+    }
+
+    #[test]
+    fn intake_usage_is_split_between_features() {
+        let mut stats = UsageStats::default();
+        let price = LlmModelPrice::new(LlmProvider::Groq, "m", 1.0, 2.0);
+        stats.track_llm_intake_usage(
+            &[
+                (LlmFeature::SessionNaming, 600_000, 100_000),
+                (LlmFeature::RepoRecommendation, 400_000, 50_000),
+            ],
+            &LlmProvider::Groq,
+            "m",
+            Some(&price),
+        );
+
+        let llm = &stats.llm_token_stats;
+        assert_eq!((llm.total_requests, llm.total_input_tokens, llm.total_output_tokens), (1, 1_000_000, 150_000));
+        assert!((llm.total_cost_usd - 1.3).abs() < 1e-9);
+        assert!(!llm.by_feature.contains_key("prompt_intake"));
+
+        let input: u64 = llm.by_feature.values().map(|c| c.input_tokens).sum();
+        let output: u64 = llm.by_feature.values().map(|c| c.output_tokens).sum();
+        let cost: f64 = llm.by_feature.values().map(|c| c.cost_usd).sum();
+        assert_eq!((input, output), (1_000_000, 150_000));
+        assert!((cost - llm.total_cost_usd).abs() < 1e-9);
+        assert_eq!(llm.by_feature["session_naming"].requests, 1);
+        assert_eq!(llm.by_feature["repo_recommendation"].input_tokens, 400_000);
+
+        let month = &llm.monthly.last().unwrap().usage;
+        assert_eq!(month.total.requests, 1);
+        assert!(!month.by_feature.contains_key("prompt_intake"));
+    }
+
+    #[test]
+    fn llm_usage_by_model_uses_provider_names() {
+        let mut stats = UsageStats::default();
+        stats.track_llm_token_usage(
+            LlmFeature::SessionNaming,
+            &LlmProvider::OpenAI,
+            "gpt-4o-mini",
+            100,
+            10,
+            None,
+        );
+        let keys: Vec<_> = stats.llm_token_stats.by_model.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["OpenAI/gpt-4o-mini"]);
+        assert_eq!(stats.llm_token_stats.unpriced_requests, 1);
+    }
+
+    #[test]
+    fn provider_names_match_serialized_names() {
+        use LlmProvider::*;
+        for provider in [Groq, Gemini, OpenAI, Local, Custom] {
+            assert_eq!(serde_json::to_value(&provider).unwrap(), provider.as_str());
+        }
+    }
+
+    #[test]
+    fn repo_for_path_matches_subdirectories_and_path_forms() {
+        let config = AppConfig {
+            repos: vec![repo("/work/app"), repo("/work/app/vendor/lib"), repo("/work/other/")],
+            ..AppConfig::default()
+        };
+        let found = |path: &str| config.repo_for_path(path).map(|repo| repo.path.as_str());
+
+        assert_eq!(found("/work/app"), Some("/work/app"));
+        assert_eq!(found("/work/app/"), Some("/work/app"));
+        assert_eq!(found("/work/app/src/./bin"), Some("/work/app"));
+        assert_eq!(found("/work/app/vendor/lib/src"), Some("/work/app/vendor/lib"));
+        assert_eq!(found("/work/other"), Some("/work/other/"));
+        assert_eq!(found("/work/application"), None);
+        assert_eq!(found("/work"), None);
+    }
 }
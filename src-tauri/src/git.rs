@@ -153,6 +153,86 @@ impl GitManager {
         Ok(())
     }
 
+    pub fn get_head_commit(repo_path: &str) -> Result<String, String> {
+        let mut cmd = Command::new("git");
+        cmd.args(["rev-parse", "HEAD"])
+            .current_dir(repo_path);
+
+        #[cfg(windows)]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Diff of the session's changes: staged changes only, or everything since `base_commit`
+    /// (HEAD when unknown) including a list of untracked files
+    pub fn get_diff(repo_path: &str, base_commit: Option<&str>, staged_only: bool) -> Result<String, String> {
+        let base = match base_commit {
+            // The base comes from the frontend, so it must not be able to pass options to git
+            Some(sha) if !Self::is_commit_sha(sha) => {
+                return Err(format!("Invalid base commit: {}", sha));
+            }
+            Some(sha) => sha,
+            None => "HEAD",
+        };
+        let mut args = vec!["diff", "--stat", "--patch", "--no-color", "--no-ext-diff"];
+        if staged_only {
+            args.push("--cached");
+        } else {
+            args.push(base);
+        }
+        args.push("--");
+
+        let mut cmd = Command::new("git");
+        cmd.args(&args)
+            .current_dir(repo_path);
+
+        #[cfg(windows)]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        let output = cmd
+            .output()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        let mut diff = String::from_utf8_lossy(&output.stdout).to_string();
+
+        if !staged_only {
+            let mut cmd = Command::new("git");
+            cmd.args(["ls-files", "--others", "--exclude-standard"])
+                .current_dir(repo_path);
+
+            #[cfg(windows)]
+            cmd.creation_flags(CREATE_NO_WINDOW);
+
+            if let Ok(output) = cmd.output() {
+                let untracked = String::from_utf8_lossy(&output.stdout);
+                if output.status.success() && !untracked.trim().is_empty() {
+                    diff.push_str("\nUntracked files:\n");
+                    diff.push_str(&untracked);
+                }
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Abbreviated or full hex commit hash (SHA-1 or SHA-256)
+    fn is_commit_sha(sha: &str) -> bool {
+        (7..=64).contains(&sha.len()) && sha.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
     pub fn generate_branch_name(prompt: &str) -> String {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -188,3 +268,97 @@ impl GitManager {
             .to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// A repository with one commit containing `tracked.txt`
+    fn test_repo(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("git-diff-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q"]);
+        fs::write(dir.join("tracked.txt"), "one\n").unwrap();
+        git(&dir, &["add", "tracked.txt"]);
+        git(&dir, &["commit", "-q", "-m", "initial"]);
+        dir
+    }
+
+    #[test]
+    fn commit_shas() {
+        let cases = [
+            ("abc1234", true),
+            ("0123456789abcdef0123456789abcdef01234567", true),
+            (&"a".repeat(64), true),
+            ("ABCDEF0", true),
+            ("abc123", false),
+            (&"a".repeat(65), false),
+            ("HEAD", false),
+            ("main", false),
+            ("--output=/tmp/x", false),
+            ("abc1234 ", false),
+            ("", false),
+        ];
+        for (sha, expected) in cases {
+            assert_eq!(GitManager::is_commit_sha(sha), expected, "{:?}", sha);
+        }
+    }
+
+    #[test]
+    fn diff_includes_changes_and_untracked_files() {
+        let repo = test_repo("worktree");
+        let path = repo.to_str().unwrap();
+        fs::write(repo.join("tracked.txt"), "one\ntwo\n").unwrap();
+        fs::write(repo.join("staged.txt"), "staged\n").unwrap();
+        git(&repo, &["add", "staged.txt"]);
+        fs::write(repo.join("untracked.txt"), "new\n").unwrap();
+
+        let diff = GitManager::get_diff(path, None, false).unwrap();
+        assert!(diff.contains("+two"), "{}", diff);
+        assert!(diff.contains("+staged"), "{}", diff);
+        assert!(diff.ends_with("\nUntracked files:\nuntracked.txt\n"), "{}", diff);
+
+        let staged = GitManager::get_diff(path, None, true).unwrap();
+        assert!(staged.contains("+staged"), "{}", staged);
+        assert!(!staged.contains("+two"), "{}", staged);
+        assert!(!staged.contains("Untracked files"), "{}", staged);
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn diff_against_a_base_commit() {
+        let repo = test_repo("base");
+        let path = repo.to_str().unwrap();
+        let base = git(&repo, &["rev-parse", "HEAD"]);
+        fs::write(repo.join("tracked.txt"), "one\ncommitted\n").unwrap();
+        git(&repo, &["commit", "-q", "-am", "second"]);
+
+        assert_eq!(GitManager::get_diff(path, None, false).unwrap(), "");
+
+        let diff = GitManager::get_diff(path, Some(&base[..7]), false).unwrap();
+        assert!(diff.contains("+committed"), "{}", diff);
+        assert!(diff.contains("1 file changed"), "{}", diff);
+
+        // The base can't smuggle options into the git command line
+        let err = GitManager::get_diff(path, Some("--output=/tmp/pwned"), false).unwrap_err();
+        assert_eq!(err, "Invalid base commit: --output=/tmp/pwned");
+        assert!(GitManager::get_diff(path, Some("1234567"), false).is_err());
+
+        let _ = fs::remove_dir_all(&repo);
+    }
+}
@@ -1,16 +1,28 @@
+mod budget;
 mod commands;
 mod config;
 mod llm;
 mod git;
+mod permissions;
+mod plan_file;
+mod planning;
+mod prompt_queue;
+mod repo_profile;
+mod session_history;
 mod session_persistence;
 mod sidecar;
+mod sidecar_journal;
+mod sidecar_runtime;
+mod sidecar_stream;
+mod subagent_transcript;
 mod terminal;
 mod vosk;
 mod whisper;
 
-use commands::{audio_cmds, llm_cmds, input_cmds, mcp_cmds, sdk_cmds, session_cmds, settings_cmds, terminal_cmds, usage_cmds, vosk_cmds};
+use commands::{audio_cmds, llm_cmds, input_cmds, mcp_cmds, plan_cmds, sdk_cmds, session_cmds, settings_cmds, terminal_cmds, usage_cmds, vosk_cmds};
 use config::{AppConfig, UsageStats};
 use parking_lot::Mutex;
+use plan_file::PlanWatcher;
 use sidecar::SidecarManager;
 use std::sync::Arc;
 use vosk::VoskManager;
@@ -53,7 +65,10 @@ pub fn run() {
     let start_minimized = config.system.start_minimized;
     let terminal_manager = Arc::new(TerminalManager::new());
     let sidecar_manager = Arc::new(SidecarManager::new());
+    sidecar_manager.set_journal_enabled(config.system.sidecar_journal);
+    sidecar_manager.set_stream_interval_ms(config.system.stream_flush_interval_ms);
     let vosk_manager = Arc::new(VoskManager::new());
+    let plan_watcher = Arc::new(PlanWatcher::default());
 
     let builder = tauri::Builder::default();
 
@@ -89,6 +104,7 @@ pub fn run() {
         .manage(terminal_manager)
         .manage(sidecar_manager)
         .manage(vosk_manager)
+        .manage(plan_watcher)
         .setup(move |app| {
             // Build tray menu
             let show_item = MenuItemBuilder::new("Show")
@@ -188,11 +204,13 @@ pub fn run() {
             settings_cmds::get_config,
             settings_cmds::save_config,
             settings_cmds::add_repo,
+            settings_cmds::scan_repo_profile,
             settings_cmds::remove_repo,
             settings_cmds::set_active_repo,
             settings_cmds::set_auto_repo_mode,
             settings_cmds::get_active_repo,
             settings_cmds::get_git_branch,
+            settings_cmds::get_git_head,
             settings_cmds::run_in_terminal,
             terminal_cmds::create_terminal_session,
             terminal_cmds::create_interactive_session,
@@ -204,12 +222,30 @@ pub fn run() {
             audio_cmds::transcribe_audio,
             audio_cmds::test_whisper_connection,
             sdk_cmds::start_sidecar,
+            sdk_cmds::get_sidecar_stderr,
+            sdk_cmds::get_sidecar_info,
+            sdk_cmds::diagnose_sidecar,
+            sdk_cmds::replay_sidecar_journal,
             sdk_cmds::create_sdk_session,
+            sdk_cmds::fork_sdk_session,
+            sdk_cmds::estimate_history_tokens,
             sdk_cmds::send_sdk_prompt,
+            sdk_cmds::get_sdk_prompt_queue,
+            sdk_cmds::cancel_queued_sdk_prompt,
+            sdk_cmds::move_queued_sdk_prompt,
+            sdk_cmds::restore_sdk_prompt_queue,
+            sdk_cmds::run_sdk_prompt_queue,
             sdk_cmds::stop_sdk_query,
             sdk_cmds::update_sdk_model,
             sdk_cmds::update_sdk_thinking,
             sdk_cmds::close_sdk_session,
+            sdk_cmds::respond_sdk_permission,
+            sdk_cmds::answer_planning_questions,
+            sdk_cmds::read_subagent_transcript,
+            plan_cmds::read_plan_file,
+            plan_cmds::watch_plan_file,
+            plan_cmds::unwatch_plan_file,
+            plan_cmds::spawn_plan_implementation,
             sdk_cmds::generate_repo_description_with_claude,
             session_cmds::get_persisted_sessions,
             session_cmds::save_persisted_sessions,
@@ -227,6 +263,8 @@ pub fn run() {
             toggle_autostart,
             input_cmds::paste_text,
             llm_cmds::test_gemini_connection,
+            llm_cmds::clear_llm_cache,
+            llm_cmds::compact_session_history,
             llm_cmds::generate_session_name,
             llm_cmds::generate_session_outcome,
             llm_cmds::analyze_interaction_needed,
@@ -238,6 +276,11 @@ pub fn run() {
             llm_cmds::generate_repo_description,
             llm_cmds::recommend_repo,
             llm_cmds::generate_quick_actions,
+            llm_cmds::run_prompt_intake,
+            llm_cmds::generate_commit_message,
+            llm_cmds::generate_pr_description,
+            llm_cmds::route_voice_intent,
+            llm_cmds::enhance_prompt,
             vosk_cmds::test_vosk_connection,
             vosk_cmds::start_vosk_session,
             vosk_cmds::send_vosk_audio,
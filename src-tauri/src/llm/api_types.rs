@@ -10,6 +10,9 @@ pub struct LlmUsage {
     pub input_tokens: u64,
     pub output_tokens: u64,
     pub total_tokens: u64,
+    /// Model that served the request (differs from the configured one with Gemini auto model)
+    #[serde(default)]
+    pub model: String,
 }
 
 // ============================================================================
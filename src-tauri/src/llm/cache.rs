@@ -0,0 +1,390 @@
+//! Persistent response cache for repeatable LLM requests
+//!
+//! Restoring sessions or retrying re-sends identical prompts for naming, outcomes, interaction
+//! analysis and quick actions. Responses are keyed by (feature, provider, model, prompt hash)
+//! and stored on disk so they survive restarts. New entries are written out in the
+//! background, a few seconds after the last change.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{AppConfig, LlmFeature, LlmProvider};
+
+use super::api_types::LlmUsage;
+use super::providers::{parse_structured, GenerationResult};
+use super::utils::extract_json;
+use super::LlmClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    feature: String,
+    /// Extracted JSON response text
+    response: String,
+    input_tokens: u64,
+    output_tokens: u64,
+    /// Model that produced the response
+    #[serde(default)]
+    model: String,
+    /// Unix timestamp (seconds)
+    created_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LlmCache {
+    #[serde(default)]
+    entries: HashMap<String, CacheEntry>,
+}
+
+static CACHE: OnceLock<Mutex<LlmCache>> = OnceLock::new();
+
+/// Set while a background save is pending, so a burst of misses is written once
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+/// Serializes disk writes so an older snapshot never overwrites a newer one
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// How long to wait after a change before writing the cache to disk
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+fn cache() -> &'static Mutex<LlmCache> {
+    CACHE.get_or_init(|| Mutex::new(LlmCache::load()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl LlmCache {
+    fn cache_path() -> PathBuf {
+        // Use separate cache file for debug builds to avoid conflicts
+        #[cfg(debug_assertions)]
+        let filename = "llm_cache.dev.json";
+        #[cfg(not(debug_assertions))]
+        let filename = "llm_cache.json";
+
+        AppConfig::config_dir().join(filename)
+    }
+
+    fn load() -> Self {
+        let path = Self::cache_path();
+        if path.exists() {
+            match fs::read_to_string(&path) {
+                Ok(content) => match serde_json::from_str(&content) {
+                    Ok(cache) => return cache,
+                    Err(e) => eprintln!("[llm-cache] Failed to parse cache: {}", e),
+                },
+                Err(e) => eprintln!("[llm-cache] Failed to read cache: {}", e),
+            }
+        }
+        Self::default()
+    }
+
+    /// Write the current cache to disk. The global lock is only held while serializing.
+    fn save() -> Result<(), String> {
+        let _write = WRITE_LOCK.lock();
+        let dir = AppConfig::config_dir();
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+
+        let content = serde_json::to_string(&*cache().lock())
+            .map_err(|e| format!("Failed to serialize LLM cache: {}", e))?;
+        fs::write(Self::cache_path(), content).map_err(|e| format!("Failed to write LLM cache: {}", e))
+    }
+
+    /// Save on a background thread after `SAVE_DELAY`, unless a save is already pending
+    fn schedule_save() {
+        if SAVE_PENDING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        thread::spawn(|| {
+            thread::sleep(SAVE_DELAY);
+            // Clear first so changes made while writing schedule another save
+            SAVE_PENDING.store(false, Ordering::SeqCst);
+            if let Err(e) = Self::save() {
+                eprintln!("[llm-cache] {}", e);
+            }
+        });
+    }
+
+    /// The entry for `key` unless it is older than `ttl_secs` at `now`
+    fn lookup(&self, key: &str, ttl_secs: u64, now: u64) -> Option<&CacheEntry> {
+        self.entries
+            .get(key)
+            .filter(|e| now.saturating_sub(e.created_at) < ttl_secs)
+    }
+
+    /// Drop expired entries, then the oldest ones beyond `max_entries`
+    fn prune(&mut self, now: u64, ttl_secs: u64, max_entries: usize) {
+        self.entries
+            .retain(|_, e| now.saturating_sub(e.created_at) < ttl_secs);
+
+        if self.entries.len() > max_entries {
+            let mut by_age: Vec<(String, u64)> = self
+                .entries
+                .iter()
+                .map(|(k, e)| (k.clone(), e.created_at))
+                .collect();
+            by_age.sort_by_key(|(_, created_at)| *created_at);
+            let excess = self.entries.len() - max_entries;
+            for (key, _) in by_age.into_iter().take(excess) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// Collapse whitespace so formatting-only differences still hit the cache
+fn normalize_prompt(prompt: &str) -> String {
+    prompt.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Key of a cached response. Only stable names go into it, so entries keep matching across
+/// releases.
+fn cache_key(feature: LlmFeature, provider: &LlmProvider, model: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(feature.as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(provider.as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(model.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(normalize_prompt(prompt).as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Remove all cached responses, returning how many were removed
+pub fn clear_response_cache() -> Result<usize, String> {
+    let removed = {
+        let mut cache = cache().lock();
+        let removed = cache.entries.len();
+        cache.entries.clear();
+        removed
+    };
+    LlmCache::save()?;
+    println!("[llm-cache] Cleared {} entries", removed);
+    Ok(removed)
+}
+
+impl LlmClient {
+    /// Structured generation that is answered from the on-disk cache when an identical
+    /// request was made before. Falls back to a normal request when caching is disabled.
+    pub(super) async fn generate_cached_with_usage<T: DeserializeOwned>(
+        &self,
+        feature: LlmFeature,
+        prompt: &str,
+        schema: Option<serde_json::Value>,
+    ) -> Result<GenerationResult<T>, String> {
+        let Some(config) = self.cache_config.as_ref().filter(|c| c.enabled) else {
+            return self.generate_structured_with_usage(prompt, schema).await;
+        };
+        let ttl_secs = config.ttl_hours * 3600;
+        // Look up answers from the model that is tried first; fallback answers are stored
+        // under the fallback model, so they are not returned as answers of the preferred one
+        let preferred_model = self
+            .get_model_fallback_chain()
+            .first()
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| self.model.clone());
+        let key = cache_key(feature, &self.provider, &preferred_model, prompt);
+
+        let hit = cache().lock().lookup(&key, ttl_secs, now_secs()).cloned();
+        if let Some(entry) = hit {
+            match serde_json::from_str::<T>(&entry.response) {
+                Ok(data) => {
+                    println!("[llm-cache] Hit for {}", feature.as_str());
+                    return Ok(GenerationResult {
+                        data,
+                        usage: LlmUsage {
+                            input_tokens: entry.input_tokens,
+                            output_tokens: entry.output_tokens,
+                            total_tokens: entry.input_tokens + entry.output_tokens,
+                            model: entry.model,
+                        },
+                        cached: true,
+                    });
+                }
+                Err(e) => eprintln!("[llm-cache] Ignoring unreadable entry for {}: {}", feature.as_str(), e),
+            }
+        }
+
+        let (text, usage) = self.generate_text_with_usage(prompt, schema).await?;
+        let data: T = parse_structured(&text)?;
+
+        let key = cache_key(feature, &self.provider, &usage.model, prompt);
+        let mut cache = cache().lock();
+        cache.entries.insert(
+            key,
+            CacheEntry {
+                feature: feature.as_str().to_string(),
+                response: extract_json(&text),
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                model: usage.model.clone(),
+                created_at: now_secs(),
+            },
+        );
+        cache.prune(now_secs(), ttl_secs, config.max_entries);
+        drop(cache);
+        LlmCache::schedule_save();
+
+        Ok(GenerationResult {
+            data,
+            usage,
+            cached: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+
+    fn entry(created_at: u64) -> CacheEntry {
+        CacheEntry {
+            feature: "session_naming".to_string(),
+            response: "{}".to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            model: "llama-3.1-8b-instant".to_string(),
+            created_at,
+        }
+    }
+
+    fn cache_with(entries: &[(&str, u64)]) -> LlmCache {
+        LlmCache {
+            entries: entries
+                .iter()
+                .map(|(key, created_at)| (key.to_string(), entry(*created_at)))
+                .collect(),
+        }
+    }
+
+    fn keys(cache: &LlmCache) -> Vec<&str> {
+        let mut keys: Vec<&str> = cache.entries.keys().map(String::as_str).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn cache_key_is_stable() {
+        // Changing this digest invalidates every cache on disk
+        assert_eq!(
+            cache_key(
+                LlmFeature::SessionNaming,
+                &LlmProvider::Groq,
+                "llama-3.1-8b-instant",
+                "Name this session: fix the login bug"
+            ),
+            "42d665bf5cfcec9d99233f35d7e2d221b838dcee9508569d8a071f11783cbe31"
+        );
+    }
+
+    #[test]
+    fn cache_key_separates_requests() {
+        let key = |feature, provider, model, prompt| cache_key(feature, &provider, model, prompt);
+        let base = key(LlmFeature::SessionNaming, LlmProvider::Groq, "m", "prompt");
+        assert_ne!(
+            base,
+            key(LlmFeature::SessionOutcome, LlmProvider::Groq, "m", "prompt")
+        );
+        assert_ne!(
+            base,
+            key(
+                LlmFeature::SessionNaming,
+                LlmProvider::OpenAI,
+                "m",
+                "prompt"
+            )
+        );
+        assert_ne!(
+            base,
+            key(LlmFeature::SessionNaming, LlmProvider::Groq, "m2", "prompt")
+        );
+        assert_ne!(
+            base,
+            key(
+                LlmFeature::SessionNaming,
+                LlmProvider::Groq,
+                "m",
+                "prompt 2"
+            )
+        );
+        assert_eq!(
+            base,
+            key(
+                LlmFeature::SessionNaming,
+                LlmProvider::Groq,
+                "m",
+                "  prompt\n"
+            )
+        );
+    }
+
+    #[test]
+    fn normalize_prompt_collapses_whitespace() {
+        assert_eq!(normalize_prompt("  fix\tthe\n\n  bug  "), "fix the bug");
+        assert_eq!(normalize_prompt("a  b"), normalize_prompt("a b"));
+        assert_eq!(normalize_prompt(" \n "), "");
+        assert_ne!(normalize_prompt("ab"), normalize_prompt("a b"));
+    }
+
+    #[test]
+    fn lookup_skips_expired_entries() {
+        let now = 100 * HOUR;
+        let cache = cache_with(&[
+            ("fresh", now - HOUR),
+            ("old", now - 3 * HOUR),
+            ("edge", now - 2 * HOUR),
+        ]);
+        assert!(cache.lookup("fresh", 2 * HOUR, now).is_some());
+        assert!(cache.lookup("old", 2 * HOUR, now).is_none());
+        assert!(cache.lookup("edge", 2 * HOUR, now).is_none());
+        assert!(cache.lookup("missing", 2 * HOUR, now).is_none());
+        // Entries from the future (clock changes) are kept
+        assert!(cache_with(&[("future", now + HOUR)])
+            .lookup("future", HOUR, now)
+            .is_some());
+    }
+
+    #[test]
+    fn prune_drops_expired_entries() {
+        let now = 100 * HOUR;
+        let mut cache = cache_with(&[("a", now), ("b", now - HOUR), ("c", now - 5 * HOUR)]);
+        cache.prune(now, 2 * HOUR, 10);
+        assert_eq!(keys(&cache), ["a", "b"]);
+    }
+
+    #[test]
+    fn prune_evicts_oldest_beyond_max_entries() {
+        let now = 100 * HOUR;
+        let mut cache = cache_with(&[
+            ("a", now - 4 * HOUR),
+            ("b", now - HOUR),
+            ("c", now - 3 * HOUR),
+            ("d", now),
+            ("e", now - 2 * HOUR),
+        ]);
+        cache.prune(now, 10 * HOUR, 3);
+        assert_eq!(keys(&cache), ["b", "d", "e"]);
+
+        cache.prune(now, 10 * HOUR, 0);
+        assert!(cache.entries.is_empty());
+    }
+}
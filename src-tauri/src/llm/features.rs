@@ -1,7 +1,10 @@
 //! LLM feature methods (session naming, transcription cleanup, model recommendations, etc.)
 
+use crate::config::LlmFeature;
+
 use super::api_types::LlmUsage;
 use super::providers::GenerationResult;
+use super::repo_matcher::{shortlist_repos, MAX_CANDIDATES};
 use super::types::*;
 use super::utils::truncate_text;
 use super::LlmClient;
@@ -23,21 +26,7 @@ Respond with ONLY a JSON object in this exact format:
             truncate_text(user_prompt, 500)
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {
-                    "type": "string",
-                    "description": "A concise session name (3-6 words, no special characters)"
-                },
-                "category": {
-                    "type": "string",
-                    "enum": ["feature", "bugfix", "refactor", "research", "question", "plan", "other"],
-                    "description": "The type of task"
-                }
-            },
-            "required": ["name", "category"]
-        });
+        let schema = session_name_schema();
 
         self.generate_structured(&prompt, Some(schema)).await
     }
@@ -224,21 +213,7 @@ Respond with ONLY a JSON object in this exact format:
             transcription_section
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "cleaned_text": {
-                    "type": "string",
-                    "description": "The corrected transcription with proper punctuation and fixed errors"
-                },
-                "corrections_made": {
-                    "type": "array",
-                    "items": {"type": "string"},
-                    "description": "List of corrections made"
-                }
-            },
-            "required": ["cleaned_text", "corrections_made"]
-        });
+        let schema = transcription_cleanup_schema();
 
         self.generate_structured(&prompt, Some(schema)).await
     }
@@ -267,31 +242,7 @@ Respond with ONLY a JSON object in this exact format:
             truncate_text(prompt, 1500)
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "recommended_model": {
-                    "type": "string",
-                    "enum": ["haiku", "sonnet", "opus"],
-                    "description": "The recommended Claude model"
-                },
-                "reasoning": {
-                    "type": "string",
-                    "description": "Brief explanation of why this model was chosen"
-                },
-                "confidence": {
-                    "type": "string",
-                    "enum": ["low", "medium", "high"],
-                    "description": "Confidence level in this recommendation"
-                },
-                "suggested_thinking": {
-                    "type": "string",
-                    "enum": ["null", "on"],
-                    "description": "Suggested extended thinking: null (off) or on (31999 tokens)"
-                }
-            },
-            "required": ["recommended_model", "reasoning", "confidence", "suggested_thinking"]
-        });
+        let schema = model_recommendation_schema();
 
         self.generate_structured(&prompt_text, Some(schema)).await
     }
@@ -367,106 +318,19 @@ Respond with ONLY a JSON object in this exact format:
         self.generate_structured(&prompt, Some(schema)).await
     }
 
-    /// Recommend the best repository for a given prompt
+    /// Recommend the best repository for a given prompt.
+    /// With more than `shortlist_size` repos, only the best lexical matches are sent to the LLM.
     pub async fn recommend_repo(
         &self,
         prompt: &str,
-        repos: &[(String, String, Option<String>, Option<Vec<String>>, Option<Vec<String>>)], // (name, path, description, keywords, vocabulary)
+        repos: &[RepoEntry],
         is_transcribed: bool,
+        shortlist_size: usize,
     ) -> Result<RepoRecommendation, String> {
-        if repos.is_empty() {
-            return Ok(RepoRecommendation {
-                recommended_index: -1,
-                recommended_name: String::new(),
-                confidence: "low".to_string(),
-                reasoning: "No repositories configured".to_string(),
-            });
-        }
-
-        let repos_list = repos
-            .iter()
-            .enumerate()
-            .map(|(i, (name, path, desc, keywords, vocabulary))| {
-                let desc_text = desc.as_deref().unwrap_or("No description");
-                let keywords_text = keywords
-                    .as_ref()
-                    .map(|kw| kw.join(", "))
-                    .unwrap_or_else(|| "None".to_string());
-                let vocab_text = vocabulary
-                    .as_ref()
-                    .map(|v| v.join(", "))
-                    .unwrap_or_else(|| "None".to_string());
-                format!(
-                    "{}. {} ({})\n   Description: {}\n   Keywords: {}\n   Vocabulary: {}",
-                    i, name, path, desc_text, keywords_text, vocab_text
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n\n");
-
-        // Add transcription context if the prompt was voice-transcribed
-        let transcription_notice = if is_transcribed {
-            "\n\nNOTE: The user's prompt was recorded via voice and transcribed using speech-to-text. \
-             There may be minor transcription errors such as homophones, missing punctuation, or misheard words. \
-             Pay special attention to the Vocabulary field - if the prompt contains words that sound like items in a repo's vocabulary, that's a strong match signal.\n"
-        } else {
-            ""
-        };
-
-        let prompt_text = format!(
-            r#"Based on the user's prompt, recommend which repository they should work in.
-
-Available repositories:
-{}
-{transcription_notice}
-User's prompt:
-{}
-
-Analyze the prompt and determine which repository best matches. Consider:
-- **Keywords**: Categorical terms that match the user's intent (e.g., "authentication", "frontend")
-- **Vocabulary**: Project-specific lingo - if the prompt mentions terms from a repo's vocabulary, it's likely the right repo
-- Project names or terminology mentioned
-- Technologies or frameworks referenced
-- Domain or feature areas discussed
-
-For voice-transcribed prompts: The vocabulary is especially important because speech-to-text might transcribe project-specific terms incorrectly. Look for words that sound similar to vocabulary items.
-
-IMPORTANT: If the prompt doesn't contain enough information to make a meaningful recommendation (e.g., generic requests like "help me with this" or "fix the bug"), return -1 for recommended_index and empty string for recommended_name. Only recommend a repository if you have actual evidence from the prompt to support the choice.
-
-Respond with ONLY a JSON object in this exact format:
-{{"recommended_index": 0, "recommended_name": "repo name", "confidence": "low|medium|high", "reasoning": "brief explanation"}}
-
-Or if no clear match:
-{{"recommended_index": -1, "recommended_name": "", "confidence": "low", "reasoning": "Not enough information to determine repository"}}"#,
-            repos_list,
-            truncate_text(prompt, 1500)
-        );
-
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "recommended_index": {
-                    "type": "integer",
-                    "description": "The index of the recommended repository (0-based), or -1 if no clear match"
-                },
-                "recommended_name": {
-                    "type": "string",
-                    "description": "The name of the recommended repository, or empty string if no clear match"
-                },
-                "confidence": {
-                    "type": "string",
-                    "enum": ["low", "medium", "high"],
-                    "description": "Confidence level in this recommendation"
-                },
-                "reasoning": {
-                    "type": "string",
-                    "description": "Brief explanation of why this repo was chosen or why no recommendation could be made"
-                }
-            },
-            "required": ["recommended_index", "recommended_name", "confidence", "reasoning"]
-        });
-
-        self.generate_structured(&prompt_text, Some(schema)).await
+        let result = self
+            .recommend_repo_with_usage(prompt, repos, is_transcribed, shortlist_size)
+            .await?;
+        Ok(result.data)
     }
 
     /// Generate contextual quick actions based on the session's final message
@@ -555,23 +419,9 @@ Respond with ONLY a JSON object in this exact format:
             truncate_text(user_prompt, 500)
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "name": {
-                    "type": "string",
-                    "description": "A concise session name (3-6 words, no special characters)"
-                },
-                "category": {
-                    "type": "string",
-                    "enum": ["feature", "bugfix", "refactor", "research", "question", "plan", "other"],
-                    "description": "The type of task"
-                }
-            },
-            "required": ["name", "category"]
-        });
+        let schema = session_name_schema();
 
-        self.generate_structured_with_usage(&prompt, Some(schema)).await
+        self.generate_cached_with_usage(LlmFeature::SessionNaming, &prompt, Some(schema)).await
     }
 
     /// Generate session outcome with usage tracking
@@ -627,7 +477,7 @@ Respond with ONLY a JSON object in this exact format:
             "required": ["outcome"]
         });
 
-        self.generate_structured_with_usage(&prompt, Some(schema)).await
+        self.generate_cached_with_usage(LlmFeature::SessionOutcome, &prompt, Some(schema)).await
     }
 
     /// Analyze interaction needed with usage tracking
@@ -689,7 +539,7 @@ Respond with ONLY a JSON object in this exact format:
             "required": ["needs_interaction", "urgency"]
         });
 
-        self.generate_structured_with_usage(&prompt, Some(schema)).await
+        self.generate_cached_with_usage(LlmFeature::InteractionAnalysis, &prompt, Some(schema)).await
     }
 
     /// Clean transcription with usage tracking
@@ -752,21 +602,7 @@ Respond with ONLY a JSON object in this exact format:
             transcription_section
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "cleaned_text": {
-                    "type": "string",
-                    "description": "The corrected transcription with proper punctuation and fixed errors"
-                },
-                "corrections_made": {
-                    "type": "array",
-                    "items": {"type": "string"},
-                    "description": "List of corrections made"
-                }
-            },
-            "required": ["cleaned_text", "corrections_made"]
-        });
+        let schema = transcription_cleanup_schema();
 
         self.generate_structured_with_usage(&prompt, Some(schema)).await
     }
@@ -795,31 +631,7 @@ Respond with ONLY a JSON object in this exact format:
             truncate_text(prompt, 1500)
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "recommended_model": {
-                    "type": "string",
-                    "enum": ["haiku", "sonnet", "opus"],
-                    "description": "The recommended Claude model"
-                },
-                "reasoning": {
-                    "type": "string",
-                    "description": "Brief explanation of why this model was chosen"
-                },
-                "confidence": {
-                    "type": "string",
-                    "enum": ["low", "medium", "high"],
-                    "description": "Confidence level in this recommendation"
-                },
-                "suggested_thinking": {
-                    "type": "string",
-                    "enum": ["null", "on"],
-                    "description": "Suggested extended thinking: null (off) or on (31999 tokens)"
-                }
-            },
-            "required": ["recommended_model", "reasoning", "confidence", "suggested_thinking"]
-        });
+        let schema = model_recommendation_schema();
 
         self.generate_structured_with_usage(&prompt_text, Some(schema)).await
     }
@@ -830,11 +642,16 @@ Respond with ONLY a JSON object in this exact format:
         repo_name: &str,
         claude_md_content: Option<&str>,
         readme_content: Option<&str>,
+        scan_digest: Option<&str>,
     ) -> Result<GenerationResult<RepoDescriptionResult>, String> {
-        let content = match (claude_md_content, readme_content) {
-            (Some(claude_md), _) => format!("CLAUDE.md content:\n{}", truncate_text(claude_md, 4000)),
-            (None, Some(readme)) => format!("README content:\n{}", truncate_text(readme, 4000)),
-            (None, None) => format!("Repository name: {}", repo_name),
+        let content = match (scan_digest, claude_md_content, readme_content) {
+            (Some(digest), _, _) => format!(
+                "Offline scan summary (manifests, README intro and frequent source identifiers):\n{}",
+                truncate_text(digest, 4000)
+            ),
+            (None, Some(claude_md), _) => format!("CLAUDE.md content:\n{}", truncate_text(claude_md, 4000)),
+            (None, None, Some(readme)) => format!("README content:\n{}", truncate_text(readme, 4000)),
+            (None, None, None) => format!("Repository name: {}", repo_name),
         };
 
         let prompt = format!(
@@ -899,41 +716,21 @@ Respond with ONLY a JSON object in this exact format:
     pub async fn recommend_repo_with_usage(
         &self,
         prompt: &str,
-        repos: &[(String, String, Option<String>, Option<Vec<String>>, Option<Vec<String>>)],
+        repos: &[RepoEntry],
         is_transcribed: bool,
+        shortlist_size: usize,
     ) -> Result<GenerationResult<RepoRecommendation>, String> {
         if repos.is_empty() {
             return Ok(GenerationResult {
-                data: RepoRecommendation {
-                    recommended_index: -1,
-                    recommended_name: String::new(),
-                    confidence: "low".to_string(),
-                    reasoning: "No repositories configured".to_string(),
-                },
+                data: RepoRecommendation::none("No repositories configured"),
                 usage: LlmUsage::default(),
+                cached: false,
             });
         }
 
-        let repos_list = repos
-            .iter()
-            .enumerate()
-            .map(|(i, (name, path, desc, keywords, vocabulary))| {
-                let desc_text = desc.as_deref().unwrap_or("No description");
-                let keywords_text = keywords
-                    .as_ref()
-                    .map(|kw| kw.join(", "))
-                    .unwrap_or_else(|| "None".to_string());
-                let vocab_text = vocabulary
-                    .as_ref()
-                    .map(|v| v.join(", "))
-                    .unwrap_or_else(|| "None".to_string());
-                format!(
-                    "{}. {} ({})\n   Description: {}\n   Keywords: {}\n   Vocabulary: {}",
-                    i, name, path, desc_text, keywords_text, vocab_text
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n\n");
+        // Pre-filter with the lexical scorer so large repo lists don't blow up the prompt
+        let shortlist = shortlist_repos(prompt, repos, shortlist_size);
+        let repos_list = format_repos_list(repos, &shortlist);
 
         let transcription_notice = if is_transcribed {
             "\n\nNOTE: The user's prompt was recorded via voice and transcribed using speech-to-text. \
@@ -952,7 +749,7 @@ Available repositories:
 User's prompt:
 {}
 
-Analyze the prompt and determine which repository best matches. Consider:
+Analyze the prompt and rank the repositories that match. Consider:
 - **Keywords**: Categorical terms that match the user's intent (e.g., "authentication", "frontend")
 - **Vocabulary**: Project-specific lingo - if the prompt mentions terms from a repo's vocabulary, it's likely the right repo
 - Project names or terminology mentioned
@@ -961,42 +758,25 @@ Analyze the prompt and determine which repository best matches. Consider:
 
 For voice-transcribed prompts: The vocabulary is especially important because speech-to-text might transcribe project-specific terms incorrectly. Look for words that sound similar to vocabulary items.
 
-IMPORTANT: If the prompt doesn't contain enough information to make a meaningful recommendation (e.g., generic requests like "help me with this" or "fix the bug"), return -1 for recommended_index and empty string for recommended_name. Only recommend a repository if you have actual evidence from the prompt to support the choice.
+List up to 5 plausible candidates, best first, each with a relevance score from 0.0 to 1.0. Use the repository numbers shown above as indices. The recommended repository should be the first candidate.
+
+IMPORTANT: If the prompt doesn't contain enough information to make a meaningful recommendation (e.g., generic requests like "help me with this" or "fix the bug"), return -1 for recommended_index, empty string for recommended_name and an empty candidates list. Only recommend a repository if you have actual evidence from the prompt to support the choice.
 
 Respond with ONLY a JSON object in this exact format:
-{{"recommended_index": 0, "recommended_name": "repo name", "confidence": "low|medium|high", "reasoning": "brief explanation"}}
+{{"recommended_index": 0, "recommended_name": "repo name", "confidence": "low|medium|high", "reasoning": "brief explanation", "candidates": [{{"index": 0, "name": "repo name", "score": 0.9}}]}}
 
 Or if no clear match:
-{{"recommended_index": -1, "recommended_name": "", "confidence": "low", "reasoning": "Not enough information to determine repository"}}"#,
+{{"recommended_index": -1, "recommended_name": "", "confidence": "low", "reasoning": "Not enough information to determine repository", "candidates": []}}"#,
             repos_list,
             truncate_text(prompt, 1500)
         );
 
-        let schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "recommended_index": {
-                    "type": "integer",
-                    "description": "The index of the recommended repository (0-based), or -1 if no clear match"
-                },
-                "recommended_name": {
-                    "type": "string",
-                    "description": "The name of the recommended repository, or empty string if no clear match"
-                },
-                "confidence": {
-                    "type": "string",
-                    "enum": ["low", "medium", "high"],
-                    "description": "Confidence level in this recommendation"
-                },
-                "reasoning": {
-                    "type": "string",
-                    "description": "Brief explanation of why this repo was chosen or why no recommendation could be made"
-                }
-            },
-            "required": ["recommended_index", "recommended_name", "confidence", "reasoning"]
-        });
+        let schema = repo_recommendation_schema();
 
-        self.generate_structured_with_usage(&prompt_text, Some(schema)).await
+        let mut result: GenerationResult<RepoRecommendation> =
+            self.generate_structured_with_usage(&prompt_text, Some(schema)).await?;
+        result.data = normalize_repo_recommendation(result.data, repos);
+        Ok(result)
     }
 
     /// Generate quick actions with usage tracking
@@ -1062,6 +842,727 @@ Respond with ONLY a JSON object in this exact format:
             "required": ["actions"]
         });
 
+        self.generate_cached_with_usage(LlmFeature::QuickActions, &prompt, Some(schema)).await
+    }
+
+    // ============================================================================
+    // Change summaries - commit message and PR description from the session diff
+    // ============================================================================
+
+    /// Generate a conventional commit message from the session's diff
+    pub async fn generate_commit_message_with_usage(
+        &self,
+        user_prompt: &str,
+        diff: &str,
+    ) -> Result<GenerationResult<CommitMessageResult>, String> {
+        let prompt = format!(
+            r#"Write a git commit message for the following changes made during a coding session.
+
+Use the Conventional Commits format for the subject line:
+  type(scope): summary
+- type is one of: feat, fix, refactor, perf, docs, test, build, ci, chore, style
+- scope is optional - the main module or area touched (e.g. "sidecar", "settings")
+- summary is imperative mood, lowercase, no trailing period, at most 72 characters in total
+- add "!" after the type/scope only if the change is breaking
+
+The body explains WHAT changed and WHY in a few short lines wrapped at 72 characters.
+Leave the body empty for trivial changes. Do not list every file.
+
+The user's original request for the session (for intent only - describe what the diff actually does):
+{}
+
+Diff:
+{}
+
+Respond with ONLY a JSON object in this exact format:
+{{"subject": "type(scope): summary", "body": "optional body"}}"#,
+            truncate_text(user_prompt, 1000),
+            truncate_text(diff, 12000)
+        );
+
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "subject": {
+                    "type": "string",
+                    "description": "Conventional commit subject line, at most 72 characters"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Commit body explaining what and why, wrapped at 72 characters; empty for trivial changes"
+                }
+            },
+            "required": ["subject", "body"]
+        });
+
+        self.generate_structured_with_usage(&prompt, Some(schema)).await
+    }
+
+    /// Generate a pull request title and Markdown body from the session's diff
+    pub async fn generate_pr_description_with_usage(
+        &self,
+        user_prompt: &str,
+        diff: &str,
+    ) -> Result<GenerationResult<PrDescriptionResult>, String> {
+        let prompt = format!(
+            r#"Write a pull request title and description for the following changes made during a coding session.
+
+Title: short imperative summary, at most 70 characters, no trailing period.
+
+Body (Markdown):
+- Open with 1-2 sentences on what the change does and why
+- A `## Changes` section with a short bullet list of the notable changes
+- A `## Testing` section only if the diff adds or changes tests; say what they cover
+- Keep it under 250 words and do not restate the file list
+
+The user's original request for the session (for intent only - describe what the diff actually does):
+{}
+
+Diff:
+{}
+
+Respond with ONLY a JSON object in this exact format:
+{{"title": "PR title", "body": "Markdown body"}}"#,
+            truncate_text(user_prompt, 1000),
+            truncate_text(diff, 12000)
+        );
+
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "title": {
+                    "type": "string",
+                    "description": "Pull request title, at most 70 characters"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Pull request description in Markdown"
+                }
+            },
+            "required": ["title", "body"]
+        });
+
+        self.generate_structured_with_usage(&prompt, Some(schema)).await
+    }
+
+    // ============================================================================
+    // Prompt enhancement - dictated prose to a structured task spec
+    // ============================================================================
+
+    /// Rewrite a (transcribed) prompt into a structured task specification.
+    /// `repo_context` carries the target repo's description and vocabulary so symbols
+    /// are spelled the way the codebase spells them.
+    pub async fn enhance_prompt_with_usage(
+        &self,
+        user_prompt: &str,
+        repo_context: Option<&str>,
+    ) -> Result<GenerationResult<TaskSpec>, String> {
+        let context_section = repo_context
+            .map(|ctx| format!("\nRepository context:\n{}\n", truncate_text(ctx, 2000)))
+            .unwrap_or_default();
+
+        let prompt = format!(
+            r#"Turn this dictated request for a coding assistant into a structured task specification.
+{}
+Request:
+{}
+
+Extract:
+- goal: one or two sentences stating what should be achieved
+- constraints: requirements and limitations the user stated (technologies, things not to change, style)
+- files_and_symbols: files, modules, types and functions mentioned - spelled exactly as in the repository vocabulary when they match (e.g. "SDK session" -> "SdkSession")
+- acceptance_criteria: concrete, checkable conditions for the task to be done, derived from what the user said
+- open_questions: ambiguities the assistant would need to resolve; leave empty if the request is clear
+
+Only include what the user said or clearly implied - do not invent requirements. Use empty lists when nothing applies.
+
+Respond with ONLY a JSON object in this exact format:
+{{"goal": "...", "constraints": ["..."], "files_and_symbols": ["..."], "acceptance_criteria": ["..."], "open_questions": ["..."]}}"#,
+            context_section,
+            truncate_text(user_prompt, 4000)
+        );
+
+        let string_list = serde_json::json!({
+            "type": "array",
+            "items": { "type": "string" }
+        });
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "goal": {
+                    "type": "string",
+                    "description": "What should be achieved (1-2 sentences)"
+                },
+                "constraints": string_list,
+                "files_and_symbols": string_list,
+                "acceptance_criteria": string_list,
+                "open_questions": string_list
+            },
+            "required": ["goal", "constraints", "files_and_symbols", "acceptance_criteria", "open_questions"]
+        });
+
+        self.generate_structured_with_usage(&prompt, Some(schema)).await
+    }
+
+    // ============================================================================
+    // Conversation compaction - summaries of older turns for restored sessions
+    // ============================================================================
+
+    /// Summarize the older part of a conversation so a restored session can replay the
+    /// summary plus its most recent turns instead of the full history
+    pub async fn summarize_conversation_with_usage(
+        &self,
+        transcript: &str,
+    ) -> Result<GenerationResult<ConversationSummary>, String> {
+        let prompt = format!(
+            r#"Summarize the earlier part of this conversation between a developer and a coding assistant. The summary replaces these messages when the session is resumed, so keep everything needed to continue the work.
+
+Conversation:
+{}
+
+Extract:
+- summary: what the developer asked for and what the assistant did, in a short paragraph (3-6 sentences)
+- files_touched: files that were read, created or modified (paths as written in the conversation)
+- decisions: design decisions, agreed approaches and user preferences
+- open_todos: work that was planned, promised or left unfinished
+
+Leave out tool output details unless they matter for the remaining work. Use empty lists when nothing applies.
+
+Respond with ONLY a JSON object in this exact format:
+{{"summary": "...", "files_touched": ["..."], "decisions": ["..."], "open_todos": ["..."]}}"#,
+            truncate_text(transcript, 30000)
+        );
+
+        let string_list = serde_json::json!({
+            "type": "array",
+            "items": { "type": "string" }
+        });
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "What was asked and done so far (3-6 sentences)"
+                },
+                "files_touched": string_list,
+                "decisions": string_list,
+                "open_todos": string_list
+            },
+            "required": ["summary", "files_touched", "decisions", "open_todos"]
+        });
+
+        self.generate_structured_with_usage(&prompt, Some(schema)).await
+    }
+
+    // ============================================================================
+    // Voice intent routing - app-control instructions vs. coding prompts
+    // ============================================================================
+
+    /// Classify a spoken instruction as an app-control intent or a coding prompt.
+    /// Only called when the local rules in `intent_router` can't decide.
+    pub async fn classify_voice_intent_with_usage(
+        &self,
+        transcript: &str,
+        repos: &[RepoEntry],
+    ) -> Result<GenerationResult<IntentClassification>, String> {
+        let repos_list = repos
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _, _, _, _))| format!("{}: {}", i, name))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let prompt = format!(
+            r#"A user of a voice-controlled coding assistant said the following. Decide whether it is an instruction to control the app, or a prompt meant for the coding assistant.
+
+Transcript:
+{}
+
+Control intents:
+- switch_repo: make another repository active (set repo_index)
+- change_model: use a different Claude model (set model to haiku, sonnet or opus)
+- set_thinking: turn extended thinking on or off (set thinking_enabled)
+- stop_session: stop the currently running task
+- close_session: close the current session
+- queue_prompt: send a prompt to another session (set prompt to the text to send, or an empty string if they say "that" or "this"; set target to "last")
+
+Anything that asks for code to be written, explained, changed or investigated is "prompt" - even if it mentions models, repos or sessions as a topic (e.g. "add a stop button to the session list" is a prompt).
+
+Repositories:
+{}
+
+Use "high" confidence only when the transcript is clearly an app instruction.
+
+Respond with ONLY a JSON object in this exact format:
+{{"intent": "prompt|switch_repo|change_model|set_thinking|stop_session|close_session|queue_prompt", "repo_index": null, "model": null, "thinking_enabled": null, "prompt": null, "target": null, "confidence": "low|medium|high"}}"#,
+            truncate_text(transcript, 500),
+            if repos_list.is_empty() { "(none)".to_string() } else { repos_list }
+        );
+
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "intent": {
+                    "type": "string",
+                    "enum": ["prompt", "switch_repo", "change_model", "set_thinking", "stop_session", "close_session", "queue_prompt"]
+                },
+                "repo_index": {
+                    "type": "integer",
+                    "description": "Index of the repository for switch_repo"
+                },
+                "model": {
+                    "type": "string",
+                    "description": "haiku, sonnet or opus for change_model"
+                },
+                "thinking_enabled": {
+                    "type": "boolean",
+                    "description": "Whether thinking should be on for set_thinking"
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "Text to send for queue_prompt"
+                },
+                "target": {
+                    "type": "string",
+                    "description": "Target session for queue_prompt (last or active)"
+                },
+                "confidence": {
+                    "type": "string",
+                    "enum": ["low", "medium", "high"]
+                }
+            },
+            "required": ["intent", "confidence"]
+        });
+
         self.generate_structured_with_usage(&prompt, Some(schema)).await
     }
+
+    // ============================================================================
+    // Combined prompt intake - one request instead of one per feature
+    // ============================================================================
+
+    /// Run the enabled prompt intake features (session naming, transcription cleanup,
+    /// model and repo recommendation) in a single request.
+    /// Returns the combined result plus the usage attributed to each included feature.
+    pub async fn run_prompt_intake_with_usage(
+        &self,
+        user_prompt: &str,
+        vosk_transcription: Option<&str>,
+        repo_context: Option<&str>,
+        repos: &[RepoEntry],
+        is_transcribed: bool,
+        options: &PromptIntakeOptions,
+    ) -> Result<(GenerationResult<PromptIntakeResult>, Vec<IntakeFeatureUsage>), String> {
+        // (stats feature, response key, instructions, example, schema)
+        let mut tasks: Vec<(LlmFeature, &'static str, String, &'static str, serde_json::Value)> = Vec::new();
+
+        if options.clean_transcription {
+            let context_section = repo_context
+                .map(|context| {
+                    format!(
+                        "\nProject context (use this to better recognize project-specific terms):\n{}\n",
+                        context
+                    )
+                })
+                .unwrap_or_default();
+
+            tasks.push((
+                LlmFeature::TranscriptionCleanup,
+                "transcription_cleanup",
+                format!(
+                    r#"**transcription_cleanup**: Clean up the voice transcription. Fix:
+1. Common homophones (there/their/they're, your/you're, its/it's, etc.)
+2. Technical terms that may have been misheard (e.g., "react" vs "re-act", "typescript" vs "type script")
+3. Missing or incorrect punctuation
+4. Code-related terms (function names, file extensions, programming concepts)
+5. Common speech-to-text errors
+{}
+Keep the original meaning and intent. Only fix clear errors, don't rewrite the content.
+Base all other tasks on the cleaned text."#,
+                    context_section
+                ),
+                r#""transcription_cleanup": {"cleaned_text": "the corrected text", "corrections_made": ["correction 1"]}"#,
+                transcription_cleanup_schema(),
+            ));
+        }
+
+        if options.name_session {
+            tasks.push((
+                LlmFeature::SessionNaming,
+                "session_name",
+                "**session_name**: Generate a concise name (3-6 words) for this coding session and categorize the task.".to_string(),
+                r#""session_name": {"name": "3-6 word concise name describing the task", "category": "feature|bugfix|refactor|research|question|plan|other"}"#,
+                session_name_schema(),
+            ));
+        }
+
+        if options.recommend_model {
+            tasks.push((
+                LlmFeature::ModelRecommendation,
+                "model_recommendation",
+                r#"**model_recommendation**: Recommend the best Claude model for this task.
+- **Haiku**: Fast, cheap. Best for simple questions, quick lookups, straightforward code edits, syntax questions, documentation searches.
+- **Sonnet**: Balanced. Good for typical coding tasks, debugging, feature implementation, code review, refactoring.
+- **Opus**: Most capable, expensive. Best for complex architecture, multi-file refactoring, difficult debugging, system design, novel problem-solving.
+Extended thinking: "null" for most tasks, "on" (31999 tokens) for complex architecture, debugging, system design.
+Choose the most cost-effective model that can handle this task well. Prefer cheaper models when the task is simple."#
+                    .to_string(),
+                r#""model_recommendation": {"recommended_model": "haiku|sonnet|opus", "reasoning": "brief explanation", "confidence": "low|medium|high", "suggested_thinking": "null|on"}"#,
+                model_recommendation_schema(),
+            ));
+        }
+
+        if options.recommend_repo && !repos.is_empty() {
+            tasks.push((
+                LlmFeature::RepoRecommendation,
+                "repo_recommendation",
+                format!(
+                    r#"**repo_recommendation**: Recommend which repository the user should work in.
+
+Available repositories:
+{}
+
+Consider keywords (categorical terms matching the user's intent), vocabulary (project-specific lingo - a strong match signal, especially for words that sound similar in voice-transcribed prompts), project names, technologies and domain areas mentioned.
+List up to 5 plausible candidates, best first, with relevance scores from 0.0 to 1.0, using the repository numbers shown above as indices.
+If the prompt doesn't contain enough information to make a meaningful recommendation, return -1 for recommended_index, an empty string for recommended_name and no candidates."#,
+                    format_repos_list(repos, &shortlist_repos(user_prompt, repos, options.repo_shortlist_size))
+                ),
+                r#""repo_recommendation": {"recommended_index": 0, "recommended_name": "repo name", "confidence": "low|medium|high", "reasoning": "brief explanation", "candidates": [{"index": 0, "name": "repo name", "score": 0.9}]}"#,
+                repo_recommendation_schema(),
+            ));
+        }
+
+        if tasks.is_empty() {
+            return Ok((
+                GenerationResult {
+                    data: PromptIntakeResult::default(),
+                    usage: LlmUsage::default(),
+                    cached: false,
+                },
+                Vec::new(),
+            ));
+        }
+
+        let prompt_section = match vosk_transcription {
+            Some(vosk) if options.clean_transcription => format!(
+                r#"The user's prompt was dictated and transcribed by two speech-to-text engines:
+
+**Whisper transcription** (more accurate, but may miss quick words):
+{}
+
+**Vosk transcription** (real-time, may capture words Whisper missed but less accurate overall):
+{}
+
+Use Whisper as the primary source but incorporate any clearly correct words from Vosk that Whisper may have missed."#,
+                truncate_text(user_prompt, 1500),
+                truncate_text(vosk, 1500)
+            ),
+            _ if is_transcribed => format!(
+                "User's prompt (recorded via voice and transcribed with speech-to-text, so it may contain homophones, missing punctuation or misheard words):\n{}",
+                truncate_text(user_prompt, 1500)
+            ),
+            _ => format!("User's prompt:\n{}", truncate_text(user_prompt, 1500)),
+        };
+
+        let instructions = tasks
+            .iter()
+            .map(|(_, _, text, _, _)| text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let examples = tasks
+            .iter()
+            .map(|(_, _, _, example, _)| *example)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let prompt = format!(
+            r#"Prepare this prompt for a Claude coding session. Complete every task below in a single pass.
+
+{}
+
+Tasks:
+
+{}
+
+Respond with ONLY a JSON object containing one key per task, in this exact format:
+{{{}}}"#,
+            prompt_section, instructions, examples
+        );
+
+        let mut properties = serde_json::Map::new();
+        for (_, key, _, _, schema) in &tasks {
+            properties.insert(key.to_string(), schema.clone());
+        }
+        let required: Vec<&str> = tasks.iter().map(|(_, key, _, _, _)| *key).collect();
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required
+        });
+
+        let mut result: GenerationResult<PromptIntakeResult> =
+            self.generate_structured_with_usage(&prompt, Some(schema)).await?;
+        result.data.repo_recommendation = result
+            .data
+            .repo_recommendation
+            .take()
+            .map(|rec| normalize_repo_recommendation(rec, repos));
+
+        // Attribute the single request's tokens to each feature: the shared prompt is split
+        // evenly and each task's own instructions/output are weighted by their length
+        let shared_len = prompt_section.len() / tasks.len();
+        let input_weights: Vec<usize> = tasks
+            .iter()
+            .map(|(_, _, text, _, _)| shared_len + text.len())
+            .collect();
+        let output_weights: Vec<usize> = tasks
+            .iter()
+            .map(|(_, key, _, _, _)| result.data.output_len(key))
+            .collect();
+
+        let input_split = split_tokens(result.usage.input_tokens, &input_weights);
+        let output_split = split_tokens(result.usage.output_tokens, &output_weights);
+
+        let feature_usage = tasks
+            .iter()
+            .zip(input_split.into_iter().zip(output_split))
+            .map(|((feature, _, _, _, _), (input_tokens, output_tokens))| IntakeFeatureUsage {
+                feature: *feature,
+                input_tokens,
+                output_tokens,
+            })
+            .collect();
+
+        Ok((result, feature_usage))
+    }
+}
+
+/// Split a token count proportionally to the given weights.
+/// Falls back to an even split when all weights are zero; the rounding remainder goes to the last entry.
+fn split_tokens(total: u64, weights: &[usize]) -> Vec<u64> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+
+    let weight_sum: usize = weights.iter().sum();
+    let mut shares: Vec<u64> = if weight_sum == 0 {
+        vec![total / weights.len() as u64; weights.len()]
+    } else {
+        weights
+            .iter()
+            .map(|w| total * *w as u64 / weight_sum as u64)
+            .collect()
+    };
+
+    let assigned: u64 = shares.iter().sum();
+    if let Some(last) = shares.last_mut() {
+        *last += total - assigned;
+    }
+    shares
+}
+
+/// Format the selected repositories (by index) for inclusion in a repo recommendation prompt.
+/// Repos keep their configured index so the LLM's answer maps back to the full list.
+fn format_repos_list(
+    repos: &[RepoEntry],
+    indices: &[usize],
+) -> String {
+    indices
+        .iter()
+        .filter_map(|i| repos.get(*i).map(|repo| (*i, repo)))
+        .map(|(i, (name, path, desc, keywords, vocabulary))| {
+            let desc_text = desc.as_deref().unwrap_or("No description");
+            let keywords_text = keywords
+                .as_ref()
+                .map(|kw| kw.join(", "))
+                .unwrap_or_else(|| "None".to_string());
+            let vocab_text = vocabulary
+                .as_ref()
+                .map(|v| v.join(", "))
+                .unwrap_or_else(|| "None".to_string());
+            format!(
+                "{}. {} ({})\n   Description: {}\n   Keywords: {}\n   Vocabulary: {}",
+                i, name, path, desc_text, keywords_text, vocab_text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Clean up an LLM repo recommendation: drop candidates that don't map to a configured repo,
+/// use the configured names, rank by score and make sure the recommended repo is listed first
+fn normalize_repo_recommendation(
+    mut rec: RepoRecommendation,
+    repos: &[RepoEntry],
+) -> RepoRecommendation {
+    let repo_name = |index: i64| -> Option<String> {
+        usize::try_from(index).ok().and_then(|i| repos.get(i)).map(|r| r.0.clone())
+    };
+
+    match repo_name(rec.recommended_index) {
+        Some(name) => rec.recommended_name = name,
+        None => {
+            rec.recommended_index = -1;
+            rec.recommended_name = String::new();
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    rec.candidates = std::mem::take(&mut rec.candidates)
+        .into_iter()
+        .filter_map(|c| {
+            let name = repo_name(c.index)?;
+            seen.insert(c.index).then(|| RepoCandidate {
+                index: c.index,
+                name,
+                score: c.score.clamp(0.0, 1.0),
+            })
+        })
+        .collect();
+    rec.candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    if rec.recommended_index >= 0 {
+        match rec.candidates.iter().position(|c| c.index == rec.recommended_index) {
+            Some(pos) => {
+                let recommended = rec.candidates.remove(pos);
+                rec.candidates.insert(0, recommended);
+            }
+            None => {
+                let score = match rec.confidence.as_str() {
+                    "high" => 0.9,
+                    "medium" => 0.6,
+                    _ => 0.3,
+                };
+                rec.candidates.insert(
+                    0,
+                    RepoCandidate {
+                        index: rec.recommended_index,
+                        name: rec.recommended_name.clone(),
+                        score,
+                    },
+                );
+            }
+        }
+    }
+
+    rec.candidates.truncate(MAX_CANDIDATES);
+    rec.source = "llm".to_string();
+    rec
+}
+
+// ============================================================================
+// Response schemas (shared by the single-feature and combined intake requests)
+// ============================================================================
+
+fn session_name_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "name": {
+                "type": "string",
+                "description": "A concise session name (3-6 words, no special characters)"
+            },
+            "category": {
+                "type": "string",
+                "enum": ["feature", "bugfix", "refactor", "research", "question", "plan", "other"],
+                "description": "The type of task"
+            }
+        },
+        "required": ["name", "category"]
+    })
+}
+
+fn transcription_cleanup_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "cleaned_text": {
+                "type": "string",
+                "description": "The corrected transcription with proper punctuation and fixed errors"
+            },
+            "corrections_made": {
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "List of corrections made"
+            }
+        },
+        "required": ["cleaned_text", "corrections_made"]
+    })
+}
+
+fn model_recommendation_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "recommended_model": {
+                "type": "string",
+                "enum": ["haiku", "sonnet", "opus"],
+                "description": "The recommended Claude model"
+            },
+            "reasoning": {
+                "type": "string",
+                "description": "Brief explanation of why this model was chosen"
+            },
+            "confidence": {
+                "type": "string",
+                "enum": ["low", "medium", "high"],
+                "description": "Confidence level in this recommendation"
+            },
+            "suggested_thinking": {
+                "type": "string",
+                "enum": ["null", "on"],
+                "description": "Suggested extended thinking: null (off) or on (31999 tokens)"
+            }
+        },
+        "required": ["recommended_model", "reasoning", "confidence", "suggested_thinking"]
+    })
+}
+
+fn repo_recommendation_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "recommended_index": {
+                "type": "integer",
+                "description": "The index of the recommended repository (0-based), or -1 if no clear match"
+            },
+            "recommended_name": {
+                "type": "string",
+                "description": "The name of the recommended repository, or empty string if no clear match"
+            },
+            "confidence": {
+                "type": "string",
+                "enum": ["low", "medium", "high"],
+                "description": "Confidence level in this recommendation"
+            },
+            "reasoning": {
+                "type": "string",
+                "description": "Brief explanation of why this repo was chosen or why no recommendation could be made"
+            },
+            "candidates": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "index": {
+                            "type": "integer",
+                            "description": "The index of the candidate repository (0-based)"
+                        },
+                        "name": {
+                            "type": "string",
+                            "description": "The name of the candidate repository"
+                        },
+                        "score": {
+                            "type": "number",
+                            "description": "Relevance score from 0.0 to 1.0"
+                        }
+                    },
+                    "required": ["index", "name", "score"]
+                },
+                "maxItems": 5,
+                "description": "Up to 5 ranked candidate repositories, best first"
+            }
+        },
+        "required": ["recommended_index", "recommended_name", "confidence", "reasoning", "candidates"]
+    })
 }
@@ -0,0 +1,452 @@
+//! Local fallbacks for session naming and interaction detection
+//!
+//! Used when the LLM is disabled, rate-limited or failing. Results use the same types as the
+//! LLM features so the frontend does not need to know which path produced them.
+
+use super::types::{InteractionAnalysis, SessionNameResult};
+
+/// Maximum number of words in a generated session name
+const MAX_NAME_WORDS: usize = 6;
+
+/// Phrases stripped from the start of a prompt before naming ("can you please fix...")
+const LEADING_PHRASES: &[&str] = &[
+    "hey claude", "claude", "ok so", "okay so", "so", "please", "can you", "could you",
+    "would you", "will you", "i want you to", "i'd like you to", "i would like you to",
+    "i need you to", "i want to", "i need to", "i'd like to", "help me", "let's", "lets",
+    "we need to", "we should", "go ahead and", "try to",
+];
+
+/// Words skipped when building a name
+const NAME_STOPWORDS: &[&str] = &[
+    "a", "an", "the", "some", "my", "our", "your", "this", "that", "these", "those", "it", "its",
+    "please", "just", "really", "also", "currently", "actually", "basically", "so", "and", "to",
+    "of", "for", "in", "on", "with", "is", "are", "be", "which", "there", "here", "me", "us", "i",
+    "we", "you", "can", "could", "would", "should", "will", "do", "does", "all", "any",
+];
+
+const BUGFIX_WORDS: &[&str] = &[
+    "fix", "bug", "bugs", "error", "errors", "crash", "crashes", "crashing", "broken", "fails",
+    "failing", "failure", "issue", "exception", "panic", "panics", "wrong", "regression", "debug",
+    "incorrect", "doesn't", "isn't", "not working",
+];
+const REFACTOR_WORDS: &[&str] = &[
+    "refactor", "rename", "reorganize", "restructure", "simplify", "extract", "cleanup",
+    "clean up", "deduplicate", "split", "consolidate", "move", "tidy",
+];
+const RESEARCH_WORDS: &[&str] = &[
+    "investigate", "research", "explore", "look into", "find out", "analyze", "analyse",
+    "compare", "evaluate", "review", "audit", "figure out",
+];
+const FEATURE_WORDS: &[&str] = &[
+    "add", "implement", "create", "build", "support", "introduce", "make", "new", "enable",
+    "allow", "generate", "write",
+];
+const QUESTION_STARTERS: &[&str] = &[
+    "what", "why", "how", "where", "when", "which", "who", "does", "is", "are", "do", "explain",
+];
+
+/// Phrases that offer further help without blocking progress
+const POLITE_OFFERS: &[&str] = &[
+    "would you like me to", "let me know if", "anything else", "feel free to",
+    "happy to help", "if you'd like", "if you want", "if you need", "want me to also",
+    "should you need", "hope this helps",
+];
+
+/// Phrases that ask the user to choose between approaches
+const DECISION_PHRASES: &[&str] = &[
+    "should i", "shall i", "would you prefer", "which option",
+    "which approach", "which one", "which do you", "do you prefer", "how would you like",
+    "which would you", "or should i", "let me know which",
+];
+
+/// Phrases that ask for approval before proceeding
+const APPROVAL_PHRASES: &[&str] = &[
+    "please confirm", "can you confirm", "could you confirm", "is it ok", "is it okay",
+    "is that ok", "is that okay", "okay to proceed", "ok to proceed", "before i proceed",
+    "before proceeding", "may i", "do i have your permission", "shall i proceed",
+    "should i proceed", "should i go ahead",
+];
+
+/// Phrases that ask for missing information
+const INPUT_PHRASES: &[&str] = &[
+    "please provide", "could you provide", "can you provide", "could you share",
+    "can you share", "please share", "i need you to", "i'll need", "what is your",
+    "what's your", "could you clarify", "can you clarify", "please clarify", "what do you mean",
+];
+
+/// Words that make an approval request urgent
+const DESTRUCTIVE_WORDS: &[&str] = &[
+    "delete", "remove", "drop", "force push", "force-push", "overwrite", "rm -rf", "reset --hard",
+    "wipe", "truncate", "destroy", "irreversible",
+];
+
+fn contains_any(text: &str, phrases: &[&str]) -> bool {
+    phrases.iter().any(|p| contains_phrase(text, p))
+}
+
+/// Whole-word phrase match on lowercase text
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + phrase.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// First sentence or line of the prompt with leading filler removed
+fn prompt_head(prompt: &str) -> String {
+    let first_line = prompt.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let end = first_line
+        .char_indices()
+        .find(|(i, c)| matches!(c, '.' | '!' | '?') && first_line[i + 1..].starts_with(' '))
+        .map(|(i, _)| i)
+        .unwrap_or(first_line.len());
+
+    let mut head = first_line[..end].trim().to_string();
+    loop {
+        let stripped = LEADING_PHRASES.iter().find_map(|p| {
+            let prefix = head.get(..p.len()).filter(|h| h.eq_ignore_ascii_case(p))?;
+            let rest = &head[prefix.len()..];
+            (rest.is_empty() || rest.starts_with([' ', ',']))
+                .then(|| rest.trim_start_matches([' ', ',']).to_string())
+        });
+        match stripped {
+            Some(rest) if !rest.is_empty() => head = rest,
+            _ => break,
+        }
+    }
+    head
+}
+
+fn title_case(word: &str) -> String {
+    // Keep identifiers (camelCase, snake_case, paths) as typed
+    if word.chars().skip(1).any(|c| c.is_uppercase()) || word.contains(['_', '/', '.', '(']) {
+        return word.to_string();
+    }
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Categorise a prompt by keyword (feature, bugfix, refactor, research, question, other)
+fn categorize(prompt: &str) -> &'static str {
+    let lower = prompt.to_lowercase();
+    let head = prompt_head(prompt).to_lowercase();
+    let first_word = head.split_whitespace().next().unwrap_or("");
+
+    if contains_any(&lower, BUGFIX_WORDS) {
+        "bugfix"
+    } else if contains_any(&head, REFACTOR_WORDS) {
+        "refactor"
+    } else if contains_any(&head, RESEARCH_WORDS) {
+        "research"
+    } else if QUESTION_STARTERS.contains(&first_word) {
+        "question"
+    } else if contains_any(&head, FEATURE_WORDS) {
+        "feature"
+    } else if head.ends_with('?') {
+        "question"
+    } else {
+        "other"
+    }
+}
+
+/// Name and categorise a session from its first prompt without calling an LLM
+pub fn heuristic_session_name(user_prompt: &str) -> SessionNameResult {
+    let head = prompt_head(user_prompt);
+
+    let words: Vec<String> = head
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && !matches!(c, '_' | '/' | '.')))
+        .map(|w| w.trim_end_matches('.'))
+        .filter(|w| !w.is_empty() && !NAME_STOPWORDS.contains(&w.to_lowercase().as_str()))
+        .take(MAX_NAME_WORDS)
+        .map(title_case)
+        .collect();
+
+    let name = if words.is_empty() {
+        "New session".to_string()
+    } else {
+        words.join(" ")
+    };
+
+    SessionNameResult {
+        name,
+        category: categorize(user_prompt).to_string(),
+    }
+}
+
+/// The closing part of a message, where blocking questions usually are
+fn message_tail(message: &str) -> String {
+    let paragraphs: Vec<&str> = message
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    let start = paragraphs.len().saturating_sub(2);
+    paragraphs[start..].join("\n\n")
+}
+
+/// Whether the tail contains a list of at least two options (numbered, lettered or bulleted)
+fn has_option_list(tail: &str) -> bool {
+    let options = tail
+        .lines()
+        .map(str::trim_start)
+        .filter(|l| {
+            let lower = l.to_lowercase();
+            let numbered = l.split_once(['.', ')']).is_some_and(|(n, rest)| {
+                !n.is_empty()
+                    && n.len() <= 2
+                    && n.chars().all(|c| c.is_ascii_alphanumeric())
+                    && rest.starts_with(' ')
+            });
+            numbered || l.starts_with("- ") || l.starts_with("* ") || lower.starts_with("option ")
+        })
+        .count();
+    options >= 2
+}
+
+/// Last sentence that ends with a question mark, if the message ends on a question
+fn trailing_question(tail: &str) -> Option<String> {
+    let trimmed = tail.trim_end_matches(['*', '_', ' ', '\n']);
+    if !trimmed.ends_with('?') {
+        return None;
+    }
+    let start = trimmed[..trimmed.len() - 1]
+        .rfind(['.', '!', '?', '\n'])
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    Some(trimmed[start..].trim().to_string())
+}
+
+/// Detect whether an assistant message is blocked on the user, without calling an LLM
+pub fn heuristic_interaction_analysis(last_message: &str) -> InteractionAnalysis {
+    let tail = message_tail(last_message);
+    let lower = tail.to_lowercase();
+    let question = trailing_question(&tail);
+    let question_lower = question.as_deref().unwrap_or("").to_lowercase();
+
+    let destructive = contains_any(&lower, DESTRUCTIVE_WORDS);
+    let polite_only =
+        contains_any(&question_lower, POLITE_OFFERS) && !contains_any(&question_lower, DECISION_PHRASES);
+
+    let (waiting_for, reason) = if contains_any(&lower, APPROVAL_PHRASES) {
+        (Some("approval"), "Asks for confirmation before proceeding")
+    } else if question.is_some() && has_option_list(&tail) && !polite_only {
+        (Some("decision"), "Asks the user to choose between options")
+    } else if contains_any(&lower, INPUT_PHRASES) {
+        (Some("input"), "Asks for missing information")
+    } else if question.is_some() && contains_any(&question_lower, DECISION_PHRASES) && !polite_only {
+        (Some("decision"), "Asks how to proceed")
+    } else if question.is_some()
+        && !polite_only
+        && (contains_phrase(&question_lower, "you")
+            || contains_phrase(&question_lower, "your")
+            || question_lower
+                .split_whitespace()
+                .next()
+                .is_some_and(|w| QUESTION_STARTERS.contains(&w)))
+    {
+        (Some("clarification"), "Ends with a question for the user")
+    } else {
+        (None, "")
+    };
+
+    match waiting_for {
+        Some(waiting_for) => InteractionAnalysis {
+            needs_interaction: true,
+            reason: Some(reason.to_string()),
+            urgency: if destructive && waiting_for == "approval" {
+                "high"
+            } else if waiting_for == "clarification" {
+                "low"
+            } else {
+                "medium"
+            }
+            .to_string(),
+            waiting_for: Some(waiting_for.to_string()),
+        },
+        None => InteractionAnalysis {
+            needs_interaction: false,
+            reason: None,
+            urgency: "low".to_string(),
+            waiting_for: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_names_and_categories() {
+        let cases = [
+            (
+                "Can you please fix the login crash on Safari",
+                "Fix Login Crash Safari",
+                "bugfix",
+            ),
+            (
+                "Add dark mode support to the settings page.",
+                "Add Dark Mode Support Settings Page",
+                "feature",
+            ),
+            (
+                "Refactor parse_config in src/config.rs",
+                "Refactor parse_config src/config.rs",
+                "refactor",
+            ),
+            (
+                "rename getUserName to fetchUserName",
+                "Rename getUserName fetchUserName",
+                "refactor",
+            ),
+            (
+                "Investigate why the build is slow",
+                "Investigate Why Build Slow",
+                "research",
+            ),
+            (
+                "Why does the cache miss on every request?",
+                "Why Cache Miss Every Request",
+                "question",
+            ),
+            (
+                "Hey Claude, okay so we should implement OAuth login",
+                "Implement OAuth Login",
+                "feature",
+            ),
+            // Only the first sentence names and categorises, except that bug words count anywhere
+            (
+                "Update the README. Then add a changelog entry and publish.",
+                "Update README",
+                "other",
+            ),
+            (
+                "Update the docs. The build is broken on main.",
+                "Update Docs",
+                "bugfix",
+            ),
+            (
+                "implement one two three four five six seven",
+                "Implement One Two Three Four Five",
+                "feature",
+            ),
+        ];
+        for (prompt, name, category) in cases {
+            let result = heuristic_session_name(prompt);
+            assert_eq!(
+                (result.name.as_str(), result.category.as_str()),
+                (name, category),
+                "{}",
+                prompt
+            );
+        }
+    }
+
+    #[test]
+    fn session_names_without_keywords() {
+        let cases = [
+            ("", "New session", "other"),
+            ("   \n\n", "New session", "other"),
+            ("ok so, please, the", "New session", "other"),
+            // Keywords only count as whole words
+            (
+                "Fixture loading for tests",
+                "Fixture Loading Tests",
+                "other",
+            ),
+            ("Bump the version", "Bump Version", "other"),
+            (
+                "\n  Release notes for v2\nmore detail",
+                "Release Notes V2",
+                "other",
+            ),
+        ];
+        for (prompt, name, category) in cases {
+            let result = heuristic_session_name(prompt);
+            assert_eq!(
+                (result.name.as_str(), result.category.as_str()),
+                (name, category),
+                "{:?}",
+                prompt
+            );
+        }
+    }
+
+    #[test]
+    fn blocking_questions_are_detected() {
+        let cases = [
+            (
+                "I can delete the old migrations folder. Should I proceed?",
+                "approval",
+                "high",
+            ),
+            ("Is it okay to update the lockfile?", "approval", "medium"),
+            (
+                "There are two ways to store this.\n\n1. Use SQLite\n2. Use flat files\n\nWhich do you prefer?",
+                "decision",
+                "medium",
+            ),
+            ("Should I use a trait or an enum here?", "decision", "medium"),
+            ("Please provide the API endpoint for staging.", "input", "medium"),
+            (
+                "The config has two formats. What naming convention do you want for the new files?",
+                "clarification",
+                "low",
+            ),
+            ("Would you like me to also add docs, or should I stop here?", "decision", "medium"),
+        ];
+        for (message, waiting_for, urgency) in cases {
+            let analysis = heuristic_interaction_analysis(message);
+            assert!(analysis.needs_interaction, "{}", message);
+            assert_eq!(
+                analysis.waiting_for.as_deref(),
+                Some(waiting_for),
+                "{}",
+                message
+            );
+            assert_eq!(analysis.urgency, urgency, "{}", message);
+            assert!(analysis.reason.is_some(), "{}", message);
+        }
+    }
+
+    #[test]
+    fn finished_messages_do_not_need_interaction() {
+        let cases = [
+            "I've updated the tests and they all pass.",
+            "",
+            "Done. Would you like me to also add docs?",
+            "Let me know if you want any changes?",
+            "Should I use X? I went with X since it matches the rest. All tests pass.",
+            "Here is the summary:\n\n- Added tests\n- Fixed lint\n\nLet me know if you have questions.",
+            "Options I considered:\n\n1. A cache\n2. A queue\n\nI went with the queue.",
+        ];
+        for message in cases {
+            let analysis = heuristic_interaction_analysis(message);
+            assert!(!analysis.needs_interaction, "{}", message);
+            assert_eq!(analysis.waiting_for, None, "{}", message);
+            assert_eq!(analysis.reason, None, "{}", message);
+            assert_eq!(analysis.urgency, "low");
+        }
+    }
+
+    #[test]
+    fn only_the_end_of_a_message_counts() {
+        let message = "Should I proceed with the migration?\n\nI checked: it is safe.\n\nMigrated all tables.\n\nEverything is done.";
+        assert!(!heuristic_interaction_analysis(message).needs_interaction);
+    }
+
+    #[test]
+    fn phrase_matching_uses_whole_words() {
+        assert!(contains_phrase("please fix it", "fix"));
+        assert!(contains_phrase("fix", "fix"));
+        assert!(contains_phrase("(fix)", "fix"));
+        assert!(!contains_phrase("prefix it", "fix"));
+        assert!(!contains_phrase("fixture", "fix"));
+        assert!(contains_phrase("can you look into it", "look into"));
+    }
+}
@@ -0,0 +1,510 @@
+//! Voice intent router - rule-based fast path for app-control instructions
+//!
+//! Short spoken commands like "stop", "use opus for this" or "switch to the billing repo"
+//! are recognized locally. Transcripts without control words are passed through as prompts
+//! without an LLM call; only ambiguous ones are left for the LLM classifier.
+
+use super::repo_matcher::score_repos;
+use super::types::{IntentClassification, RepoEntry, VoiceIntent, VoiceIntentAction};
+
+/// Utterances longer than this are always treated as coding prompts
+const MAX_COMMAND_WORDS: usize = 12;
+
+/// Filler words dropped from the start of an utterance ("hey claude, please stop")
+const LEADING_FILLERS: &[&str] = &[
+    "hey", "hi", "ok", "okay", "so", "um", "uh", "claude", "please", "can", "could", "would",
+    "you", "now", "just", "and",
+];
+
+/// Filler words dropped from the end of an utterance ("stop it please")
+const TRAILING_FILLERS: &[&str] = &["please", "now", "thanks", "thank", "right", "away"];
+
+/// Words that suggest an app-control instruction - without any of them the transcript is a prompt
+const CONTROL_WORDS: &[&str] = &[
+    "stop",
+    "cancel",
+    "abort",
+    "halt",
+    "interrupt",
+    "close",
+    "end",
+    "kill",
+    "session",
+    "model",
+    "opus",
+    "sonnet",
+    "haiku",
+    "thinking",
+    "think",
+    "switch",
+    "repo",
+    "repository",
+    "project",
+    "send",
+    "queue",
+    "tell",
+];
+
+const STOP_PHRASES: &[&str] = &[
+    "stop",
+    "stop it",
+    "stop that",
+    "stop this",
+    "stop working",
+    "stop the query",
+    "stop the session",
+    "stop this session",
+    "stop the current session",
+    "cancel",
+    "cancel that",
+    "cancel it",
+    "abort",
+    "abort that",
+    "halt",
+    "interrupt",
+];
+
+const MODEL_FAMILIES: &[&str] = &["opus", "sonnet", "haiku"];
+const MODEL_VERBS: &[&str] = &["use", "switch", "change", "set", "try", "go", "move"];
+const REPO_VERBS: &[&str] = &[
+    "switch", "go", "change", "open", "use", "select", "move", "jump",
+];
+const REPO_NOUNS: &[&str] = &["repo", "repository", "project", "codebase"];
+const SESSION_TARGETS: &[&str] = &["last", "previous", "latest", "other", "recent"];
+const NEGATIONS: &[&str] = &["don't", "dont", "not", "never"];
+const THINKING_OFF: &[&str] = &["off", "disable", "without", "no", "stop"];
+const THINKING_ON: &[&str] = &["on", "enable", "with", "use", "start", "harder"];
+
+/// Words a thinking instruction is made of ("please don't use extended thinking for this")
+const THINKING_COMMAND_WORDS: &[&str] = &[
+    "thinking", "think", "harder", "hard", "more", "deeper", "extended", "mode", "turn", "switch",
+    "set", "do", "be", "the", "a", "for", "this", "that", "it", "again", "anymore",
+];
+/// Words a model instruction is made of ("switch the model to opus for this one")
+const MODEL_COMMAND_WORDS: &[&str] = &[
+    "model", "to", "the", "a", "for", "this", "that", "it", "one", "instead", "now", "again",
+];
+
+/// True when the utterance is (nearly) only the instruction: at most one word outside the
+/// command vocabulary, so "think harder about the auth bug" stays a prompt
+fn is_mostly_command(words: &[String], vocabulary: &[&[&str]]) -> bool {
+    words
+        .iter()
+        .filter(|w| !vocabulary.iter().any(|v| v.contains(&w.as_str())))
+        .count()
+        <= 1
+}
+
+/// Lowercase, strip punctuation and surrounding filler words
+fn normalize(transcript: &str) -> Vec<String> {
+    let cleaned: String = transcript
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '\'' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect();
+
+    let mut words: Vec<String> = cleaned.split_whitespace().map(String::from).collect();
+    while words.len() > 1 && LEADING_FILLERS.contains(&words[0].as_str()) {
+        words.remove(0);
+    }
+    while words.len() > 1 && TRAILING_FILLERS.contains(&words[words.len() - 1].as_str()) {
+        words.pop();
+    }
+    words
+}
+
+fn intent(action: VoiceIntentAction, confidence: &str) -> VoiceIntent {
+    VoiceIntent {
+        action,
+        confidence: confidence.to_string(),
+        source: "rules".to_string(),
+    }
+}
+
+/// Map a model family (haiku, sonnet, opus) to the first enabled model of that family
+fn resolve_model(family: &str, enabled_models: &[String]) -> String {
+    enabled_models
+        .iter()
+        .find(|m| m.contains(family) && !m.contains('['))
+        .or_else(|| enabled_models.iter().find(|m| m.contains(family)))
+        .cloned()
+        .unwrap_or_else(|| family.to_string())
+}
+
+/// Classify a transcript with local rules.
+/// Returns `None` when the transcript looks like a control instruction but no rule matched,
+/// in which case the LLM classifier should decide.
+pub fn match_intent_rules(
+    transcript: &str,
+    repos: &[RepoEntry],
+    enabled_models: &[String],
+) -> Option<VoiceIntent> {
+    let words = normalize(transcript);
+    if words.is_empty() {
+        return Some(intent(VoiceIntentAction::Prompt, "high"));
+    }
+
+    let first = words[0].as_str();
+    let looks_like_command = words.iter().any(|w| CONTROL_WORDS.contains(&w.as_str()))
+        || (REPO_VERBS.contains(&first) && words.len() <= 4);
+    if words.len() > MAX_COMMAND_WORDS || !looks_like_command {
+        return Some(intent(VoiceIntentAction::Prompt, "high"));
+    }
+
+    let phrase = words.join(" ");
+    let has = |word: &str| words.iter().any(|w| w == word);
+
+    // Stop the running query
+    if STOP_PHRASES.contains(&phrase.as_str()) {
+        return Some(intent(VoiceIntentAction::StopSession, "high"));
+    }
+
+    // "close the session", "end this session"
+    let ends_with_session = words.last().is_some_and(|w| w == "session");
+    if matches!(first, "close" | "end" | "kill") && ends_with_session && words.len() <= 4 {
+        return Some(intent(VoiceIntentAction::CloseSession, "high"));
+    }
+
+    // "send that to the last session", "tell the previous session to run the tests"
+    if let Some(queue) = match_queue_prompt(&words) {
+        return Some(queue);
+    }
+
+    // "turn on thinking", "disable thinking", "don't use thinking"
+    let thinking_vocabulary = [THINKING_COMMAND_WORDS, THINKING_ON, THINKING_OFF, NEGATIONS];
+    if (has("thinking") || has("think"))
+        && words.len() <= 8
+        && is_mostly_command(&words, &thinking_vocabulary)
+    {
+        let negated = NEGATIONS.iter().any(|w| has(w));
+        let off = THINKING_OFF.iter().any(|w| has(w));
+        let on = THINKING_ON.iter().any(|w| has(w));
+        let enabled = match (negated, on, off) {
+            (false, true, false) => Some(true),
+            (false, false, true) => Some(false),
+            // "don't think", "never use thinking"
+            (true, _, false) => Some(false),
+            // "don't turn off thinking"
+            (true, false, true) => Some(true),
+            _ => None,
+        };
+        if let Some(enabled) = enabled {
+            return Some(intent(VoiceIntentAction::SetThinking { enabled }, "high"));
+        }
+    }
+
+    // "use opus for this", "switch to sonnet", "change the model to haiku"
+    if MODEL_VERBS.contains(&first)
+        && words.len() <= 8
+        && !REPO_NOUNS.iter().any(|n| has(n))
+        && is_mostly_command(&words, &[MODEL_VERBS, MODEL_FAMILIES, MODEL_COMMAND_WORDS])
+    {
+        let families: Vec<&str> = MODEL_FAMILIES.iter().copied().filter(|f| has(f)).collect();
+        if families.len() == 1 {
+            return Some(intent(
+                VoiceIntentAction::ChangeModel {
+                    model: resolve_model(families[0], enabled_models),
+                },
+                "high",
+            ));
+        }
+    }
+
+    // "switch to the billing repo", "open the website project"
+    if REPO_VERBS.contains(&first) && words.len() <= 8 {
+        if let Some(switch) = match_switch_repo(&words, repos) {
+            return Some(switch);
+        }
+    }
+
+    None
+}
+
+fn match_queue_prompt(words: &[String]) -> Option<VoiceIntent> {
+    let first = words[0].as_str();
+    if !matches!(first, "send" | "queue" | "tell" | "forward") {
+        return None;
+    }
+
+    let session_pos = words.iter().position(|w| w == "session")?;
+    let target = words[..session_pos]
+        .iter()
+        .rev()
+        .find(|w| SESSION_TARGETS.contains(&w.as_str()))?;
+
+    // "tell the last session to <prompt>"
+    if first == "tell" {
+        let rest = &words[session_pos + 1..];
+        let prompt = match rest.first().map(|w| w.as_str()) {
+            Some("to") => rest[1..].join(" "),
+            _ => rest.join(" "),
+        };
+        return Some(intent(
+            VoiceIntentAction::QueuePrompt {
+                prompt,
+                target: "last".to_string(),
+            },
+            if target == "other" { "medium" } else { "high" },
+        ));
+    }
+
+    // "send <prompt> to the last session" - "that"/"this"/"it" refers to the previous transcript
+    let to_pos = words[..session_pos].iter().rposition(|w| w == "to")?;
+    let prompt = words[1..to_pos].join(" ");
+    let prompt = if matches!(prompt.as_str(), "that" | "this" | "it" | "") {
+        String::new()
+    } else {
+        prompt
+    };
+
+    Some(intent(
+        VoiceIntentAction::QueuePrompt {
+            prompt,
+            target: "last".to_string(),
+        },
+        if target == "other" { "medium" } else { "high" },
+    ))
+}
+
+fn match_switch_repo(words: &[String], repos: &[RepoEntry]) -> Option<VoiceIntent> {
+    if repos.is_empty() {
+        return None;
+    }
+
+    let mentions_repo = REPO_NOUNS.iter().any(|n| words.iter().any(|w| w == n));
+    let target: Vec<&str> = words[1..]
+        .iter()
+        .map(|w| w.as_str())
+        .filter(|w| !matches!(*w, "to" | "the" | "over" | "my" | "into") && !REPO_NOUNS.contains(w))
+        .collect();
+    if target.is_empty() {
+        return None;
+    }
+    let target = target.join(" ");
+
+    // Only score against repo names here - descriptions would match ordinary prompts
+    let names: Vec<RepoEntry> = repos
+        .iter()
+        .map(|(name, path, _, _, _)| (name.clone(), path.clone(), None, None, None))
+        .collect();
+    let scores = score_repos(&target, &names);
+
+    let mut ranked: Vec<(usize, f64)> = scores
+        .into_iter()
+        .enumerate()
+        .filter(|(_, s)| *s > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let &(best, best_score) = ranked.first()?;
+    let runner_up = ranked.get(1).map(|(_, s)| *s).unwrap_or(0.0);
+    if best_score < runner_up * 1.3 {
+        return None;
+    }
+
+    Some(intent(
+        VoiceIntentAction::SwitchRepo {
+            repo_index: best,
+            repo_name: repos[best].0.clone(),
+        },
+        if mentions_repo { "high" } else { "medium" },
+    ))
+}
+
+/// Convert the LLM's flat classification into an action. Invalid or low-confidence
+/// control intents fall back to a plain prompt so real prompts are never swallowed.
+pub fn resolve_classification(
+    classification: IntentClassification,
+    repos: &[RepoEntry],
+    enabled_models: &[String],
+) -> VoiceIntent {
+    let confidence = classification.confidence.to_lowercase();
+
+    let action = match classification.intent.as_str() {
+        "switch_repo" => classification
+            .repo_index
+            .filter(|i| *i >= 0 && (*i as usize) < repos.len())
+            .map(|i| VoiceIntentAction::SwitchRepo {
+                repo_index: i as usize,
+                repo_name: repos[i as usize].0.clone(),
+            }),
+        "change_model" => classification
+            .model
+            .map(|m| m.to_lowercase())
+            .filter(|m| MODEL_FAMILIES.contains(&m.as_str()))
+            .map(|m| VoiceIntentAction::ChangeModel {
+                model: resolve_model(&m, enabled_models),
+            }),
+        "set_thinking" => classification
+            .thinking_enabled
+            .map(|enabled| VoiceIntentAction::SetThinking { enabled }),
+        "stop_session" => Some(VoiceIntentAction::StopSession),
+        "close_session" => Some(VoiceIntentAction::CloseSession),
+        "queue_prompt" => Some(VoiceIntentAction::QueuePrompt {
+            prompt: classification.prompt.unwrap_or_default(),
+            target: classification.target.unwrap_or_else(|| "last".to_string()),
+        }),
+        _ => None,
+    };
+
+    let action = match action {
+        Some(action) if confidence != "low" => action,
+        _ => VoiceIntentAction::Prompt,
+    };
+
+    VoiceIntent {
+        action,
+        confidence,
+        source: "llm".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repos() -> Vec<RepoEntry> {
+        ["web-frontend", "billing", "infra"]
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    format!("/repos/{}", name),
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn models() -> Vec<String> {
+        vec![
+            "claude-sonnet-4-5-20250929".to_string(),
+            "claude-opus-4-1-20250805".to_string(),
+            "claude-opus-4-1-20250805[1m]".to_string(),
+        ]
+    }
+
+    fn action(transcript: &str) -> Option<VoiceIntentAction> {
+        match_intent_rules(transcript, &repos(), &models()).map(|i| i.action)
+    }
+
+    fn thinking(enabled: bool) -> Option<VoiceIntentAction> {
+        Some(VoiceIntentAction::SetThinking { enabled })
+    }
+
+    #[test]
+    fn prompts_without_control_words_pass_through() {
+        assert_eq!(
+            action("fix the login bug in the header"),
+            Some(VoiceIntentAction::Prompt)
+        );
+        assert_eq!(action(""), Some(VoiceIntentAction::Prompt));
+        assert_eq!(
+            action(
+                "stop rendering the sidebar when the user has no repositories configured at all"
+            ),
+            Some(VoiceIntentAction::Prompt)
+        );
+    }
+
+    #[test]
+    fn stop_and_close_ignore_fillers() {
+        assert_eq!(
+            action("Hey Claude, please stop!"),
+            Some(VoiceIntentAction::StopSession)
+        );
+        assert_eq!(
+            action("cancel that now"),
+            Some(VoiceIntentAction::StopSession)
+        );
+        assert_eq!(
+            action("close the session"),
+            Some(VoiceIntentAction::CloseSession)
+        );
+    }
+
+    #[test]
+    fn thinking_toggles() {
+        assert_eq!(action("turn on thinking"), thinking(true));
+        assert_eq!(action("use extended thinking for this"), thinking(true));
+        assert_eq!(action("disable thinking"), thinking(false));
+        assert_eq!(action("turn thinking off please"), thinking(false));
+    }
+
+    #[test]
+    fn negated_thinking_disables_it() {
+        assert_eq!(action("don't use thinking"), thinking(false));
+        assert_eq!(action("do not think"), thinking(false));
+        assert_eq!(action("never use extended thinking"), thinking(false));
+        assert_eq!(action("don't turn off thinking"), thinking(true));
+    }
+
+    #[test]
+    fn prompts_mentioning_thinking_are_not_swallowed() {
+        assert_eq!(action("think harder about the auth bug"), None);
+        assert_eq!(action("think about it"), None);
+    }
+
+    #[test]
+    fn model_changes_resolve_to_enabled_models() {
+        let opus = Some(VoiceIntentAction::ChangeModel {
+            model: "claude-opus-4-1-20250805".to_string(),
+        });
+        assert_eq!(action("use opus for this"), opus);
+        assert_eq!(action("change the model to opus"), opus);
+        assert_eq!(
+            action("switch to haiku"),
+            Some(VoiceIntentAction::ChangeModel {
+                model: "haiku".to_string()
+            })
+        );
+        // Two families or a task alongside the model is left to the classifier
+        assert_eq!(action("use opus or sonnet"), None);
+        assert_eq!(action("use opus to review the auth module"), None);
+    }
+
+    #[test]
+    fn repo_switch_matches_repo_names() {
+        assert_eq!(
+            action("switch to the billing repo"),
+            Some(VoiceIntentAction::SwitchRepo {
+                repo_index: 1,
+                repo_name: "billing".to_string(),
+            })
+        );
+        let intent = match_intent_rules("open web frontend", &repos(), &models()).unwrap();
+        assert_eq!(
+            intent.action,
+            VoiceIntentAction::SwitchRepo {
+                repo_index: 0,
+                repo_name: "web-frontend".to_string(),
+            }
+        );
+        assert_eq!(intent.confidence, "medium");
+    }
+
+    #[test]
+    fn queue_prompts_to_other_sessions() {
+        assert_eq!(
+            action("tell the last session to run the tests"),
+            Some(VoiceIntentAction::QueuePrompt {
+                prompt: "run the tests".to_string(),
+                target: "last".to_string(),
+            })
+        );
+        assert_eq!(
+            action("send that to the previous session"),
+            Some(VoiceIntentAction::QueuePrompt {
+                prompt: String::new(),
+                target: "last".to_string(),
+            })
+        );
+    }
+}
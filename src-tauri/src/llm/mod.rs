@@ -1,16 +1,27 @@
 //! Unified LLM client supporting multiple providers (Gemini, OpenAI, Groq, Local)
 
 mod api_types;
+mod cache;
 mod features;
+mod heuristics;
+mod intent_router;
 mod providers;
+mod repo_matcher;
 mod types;
 mod utils;
 
+#[cfg(test)]
+mod tests;
+
 pub use types::*;
 pub use api_types::LlmUsage;
+pub use cache::clear_response_cache;
 pub use providers::GenerationResult;
+pub use heuristics::{heuristic_interaction_analysis, heuristic_session_name};
+pub use intent_router::{match_intent_rules, resolve_classification};
+pub use repo_matcher::lexical_recommendation;
 
-use crate::config::{LlmModelPriority, LlmProvider};
+use crate::config::{LlmCacheConfig, LlmModelPriority, LlmProvider};
 
 /// Model fallback chains for Gemini provider
 /// Note: As of Dec 2025, free tier is severely limited to 20 RPD for both 2.5 Flash and 2.5 Flash-Lite
@@ -24,6 +35,11 @@ const GEMINI_MODELS_ACCURACY: &[&str] = &[
     "gemini-2.5-flash-lite",
 ];
 
+/// Hosted provider API origins
+const GEMINI_API_ORIGIN: &str = "https://generativelanguage.googleapis.com";
+const OPENAI_API_ORIGIN: &str = "https://api.openai.com";
+const GROQ_API_ORIGIN: &str = "https://api.groq.com";
+
 /// Unified LLM client that supports multiple providers (Gemini, OpenAI, Groq, Local)
 pub struct LlmClient {
     client: reqwest::Client,
@@ -35,6 +51,10 @@ pub struct LlmClient {
     auto_model: bool,
     /// Model priority when auto_model is enabled
     model_priority: LlmModelPriority,
+    /// Response cache settings (None disables caching)
+    cache_config: Option<LlmCacheConfig>,
+    /// Replaces the scheme and host of the hosted providers' APIs (test harness mock server)
+    base_url: Option<String>,
 }
 
 // Type alias for backwards compatibility
@@ -57,9 +77,28 @@ impl LlmClient {
             endpoint,
             auto_model,
             model_priority,
+            cache_config: None,
+            base_url: None,
         }
     }
 
+    /// Enable the on-disk response cache for repeatable features
+    pub fn with_cache(mut self, cache_config: LlmCacheConfig) -> Self {
+        self.cache_config = Some(cache_config);
+        self
+    }
+
+    /// Send hosted provider requests to another server, e.g. `http://127.0.0.1:8080`
+    #[cfg(test)]
+    pub(crate) fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    fn api_origin<'a>(&'a self, default: &'a str) -> &'a str {
+        self.base_url.as_deref().unwrap_or(default)
+    }
+
     /// Get the fallback chain of models based on priority
     fn get_model_fallback_chain(&self) -> Vec<&str> {
         if !self.auto_model || !matches!(self.provider, LlmProvider::Gemini) {
@@ -77,15 +116,17 @@ impl LlmClient {
         match &self.provider {
             LlmProvider::Gemini => {
                 format!(
-                    "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-                    model, self.api_key
+                    "{}/v1beta/models/{}:generateContent?key={}",
+                    self.api_origin(GEMINI_API_ORIGIN),
+                    model,
+                    self.api_key
                 )
             }
             LlmProvider::OpenAI => {
-                "https://api.openai.com/v1/chat/completions".to_string()
+                format!("{}/v1/chat/completions", self.api_origin(OPENAI_API_ORIGIN))
             }
             LlmProvider::Groq => {
-                "https://api.groq.com/openai/v1/chat/completions".to_string()
+                format!("{}/openai/v1/chat/completions", self.api_origin(GROQ_API_ORIGIN))
             }
             LlmProvider::Local | LlmProvider::Custom => {
                 self.endpoint
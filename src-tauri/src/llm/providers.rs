@@ -13,6 +13,15 @@ use super::LlmClient;
 pub struct GenerationResult<T> {
     pub data: T,
     pub usage: LlmUsage,
+    /// Answered from the response cache; `usage` holds the tokens of the original request
+    pub cached: bool,
+}
+
+/// Parse a structured JSON response (handles markdown code blocks)
+pub(super) fn parse_structured<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    let json_text = extract_json(text);
+    serde_json::from_str(&json_text)
+        .map_err(|e| format!("Failed to parse JSON response: {}. Raw text: {}", e, text))
 }
 
 impl LlmClient {
@@ -155,19 +164,27 @@ impl LlmClient {
         prompt: &str,
         schema: Option<serde_json::Value>,
     ) -> Result<GenerationResult<T>, String> {
-        let (text, usage) = if self.is_openai_compatible() {
-            self.generate_openai_with_usage(prompt).await?
-        } else {
-            self.generate_gemini_with_usage(prompt, schema).await?
-        };
-
-        // Try to extract JSON from the response (handle markdown code blocks)
-        let json_text = extract_json(&text);
-
-        let data: T = serde_json::from_str(&json_text)
-            .map_err(|e| format!("Failed to parse JSON response: {}. Raw text: {}", e, text))?;
+        let (text, usage) = self.generate_text_with_usage(prompt, schema).await?;
+        let data: T = parse_structured(&text)?;
+
+        Ok(GenerationResult {
+            data,
+            usage,
+            cached: false,
+        })
+    }
 
-        Ok(GenerationResult { data, usage })
+    /// Raw response text from the configured provider, with usage
+    pub(super) async fn generate_text_with_usage(
+        &self,
+        prompt: &str,
+        schema: Option<serde_json::Value>,
+    ) -> Result<(String, LlmUsage), String> {
+        if self.is_openai_compatible() {
+            self.generate_openai_with_usage(prompt).await
+        } else {
+            self.generate_gemini_with_usage(prompt, schema).await
+        }
     }
 
     /// Try a single Gemini model request, returns (text, usage)
@@ -216,7 +233,11 @@ impl LlmClient {
             input_tokens: u.prompt_token_count.unwrap_or(0),
             output_tokens: u.candidates_token_count.unwrap_or(0),
             total_tokens: u.total_token_count.unwrap_or(0),
-        }).unwrap_or_default();
+            model: model.to_string(),
+        }).unwrap_or_else(|| LlmUsage {
+            model: model.to_string(),
+            ..Default::default()
+        });
 
         let text = gemini_response
             .candidates
@@ -326,7 +347,11 @@ impl LlmClient {
             input_tokens: u.prompt_tokens.unwrap_or(0),
             output_tokens: u.completion_tokens.unwrap_or(0),
             total_tokens: u.total_tokens.unwrap_or(0),
-        }).unwrap_or_default();
+            model: self.model.clone(),
+        }).unwrap_or_else(|| LlmUsage {
+            model: self.model.clone(),
+            ..Default::default()
+        });
 
         let text = openai_response
             .choices
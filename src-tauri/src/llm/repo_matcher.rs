@@ -0,0 +1,431 @@
+//! Offline lexical repository matcher (BM25 over name/keywords/vocabulary/description)
+//!
+//! Used to shortlist repositories before the LLM recommendation call, and as a complete
+//! fallback when the LLM is disabled or unavailable.
+
+use std::collections::{HashMap, HashSet};
+
+use super::types::{RepoCandidate, RepoEntry, RepoRecommendation};
+
+/// BM25 term frequency saturation
+const BM25_K1: f64 = 1.2;
+/// BM25 document length normalization
+const BM25_B: f64 = 0.75;
+
+/// Field weights - a term in the repo name counts more than one in the description
+const NAME_WEIGHT: f64 = 3.0;
+const VOCABULARY_WEIGHT: f64 = 2.0;
+const KEYWORDS_WEIGHT: f64 = 1.5;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+
+/// Minimum similarity for a fuzzy (misheard/misspelled) term match
+const FUZZY_MIN_SIMILARITY: f64 = 0.8;
+/// Fuzzy matching is only attempted for terms at least this long
+const FUZZY_MIN_LEN: usize = 4;
+
+/// Maximum number of ranked candidates returned
+pub const MAX_CANDIDATES: usize = 5;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "can", "could", "do", "does", "for",
+    "from", "get", "has", "have", "how", "i", "if", "in", "into", "is", "it", "its", "let", "like",
+    "make", "me", "my", "need", "of", "on", "or", "our", "please", "should", "so", "some", "that",
+    "the", "then", "there", "this", "to", "up", "us", "want", "we", "what", "when", "where",
+    "which", "why", "will", "with", "would", "you", "your",
+];
+
+/// Weighted bag of terms for one repository
+struct RepoDocument {
+    terms: HashMap<String, f64>,
+    length: f64,
+}
+
+impl RepoDocument {
+    fn new(repo: &RepoEntry) -> Self {
+        let (name, _path, description, keywords, vocabulary) = repo;
+        let mut doc = Self {
+            terms: HashMap::new(),
+            length: 0.0,
+        };
+
+        doc.add(name, NAME_WEIGHT);
+        for term in vocabulary.iter().flatten() {
+            doc.add(term, VOCABULARY_WEIGHT);
+        }
+        for term in keywords.iter().flatten() {
+            doc.add(term, KEYWORDS_WEIGHT);
+        }
+        if let Some(description) = description {
+            doc.add(description, DESCRIPTION_WEIGHT);
+        }
+
+        doc
+    }
+
+    fn add(&mut self, text: &str, weight: f64) {
+        for term in tokenize(text) {
+            *self.terms.entry(term).or_insert(0.0) += weight;
+            self.length += weight;
+        }
+    }
+
+    /// Weighted frequency of a term, falling back to the closest fuzzy match
+    fn frequency(&self, term: &str) -> f64 {
+        if let Some(tf) = self.terms.get(term) {
+            return *tf;
+        }
+
+        if term.len() < FUZZY_MIN_LEN {
+            return 0.0;
+        }
+
+        self.terms
+            .iter()
+            .filter(|(candidate, _)| candidate.len() >= FUZZY_MIN_LEN)
+            .map(|(candidate, tf)| (similarity(term, candidate), *tf))
+            .filter(|(sim, _)| *sim >= FUZZY_MIN_SIMILARITY)
+            .map(|(sim, tf)| sim * tf)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Split text into lowercase terms. CamelCase and snake_case identifiers are split into
+/// their parts and also kept whole, so "SdkSession" matches both "sdk session" and "sdksession".
+fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-') {
+        if word.is_empty() {
+            continue;
+        }
+
+        let parts = split_identifier(word);
+        if parts.len() > 1 {
+            let joined: String = parts.concat();
+            if is_term(&joined) {
+                terms.push(joined);
+            }
+        }
+        terms.extend(parts.into_iter().filter(|p| is_term(p)));
+    }
+
+    terms
+}
+
+/// Split an identifier on case changes, underscores and hyphens
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in word.chars() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+fn is_term(term: &str) -> bool {
+    term.chars().count() >= 2 && !STOPWORDS.contains(&term)
+}
+
+/// Query terms: prompt tokens plus adjacent pairs joined together, so a transcribed
+/// "sdk session" can match the vocabulary term "SdkSession"
+fn query_terms(prompt: &str) -> Vec<String> {
+    let tokens = tokenize(prompt);
+    let mut seen = HashSet::new();
+    let mut terms = Vec::new();
+
+    for token in &tokens {
+        if seen.insert(token.clone()) {
+            terms.push(token.clone());
+        }
+    }
+    for pair in tokens.windows(2) {
+        let joined = format!("{}{}", pair[0], pair[1]);
+        if seen.insert(joined.clone()) {
+            terms.push(joined);
+        }
+    }
+
+    terms
+}
+
+/// Normalized Levenshtein similarity (1.0 = identical)
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+/// Score every repository against the prompt. Returns raw BM25 scores in repo order.
+pub fn score_repos(prompt: &str, repos: &[RepoEntry]) -> Vec<f64> {
+    if repos.is_empty() {
+        return Vec::new();
+    }
+
+    let docs: Vec<RepoDocument> = repos.iter().map(RepoDocument::new).collect();
+    let avg_length = (docs.iter().map(|d| d.length).sum::<f64>() / docs.len() as f64).max(1.0);
+    let doc_count = docs.len() as f64;
+
+    let mut scores = vec![0.0; docs.len()];
+    for term in query_terms(prompt) {
+        let frequencies: Vec<f64> = docs.iter().map(|d| d.frequency(&term)).collect();
+        let doc_freq = frequencies.iter().filter(|tf| **tf > 0.0).count() as f64;
+        if doc_freq == 0.0 {
+            continue;
+        }
+        let idf = (1.0 + (doc_count - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+
+        for (i, tf) in frequencies.into_iter().enumerate() {
+            if tf == 0.0 {
+                continue;
+            }
+            let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * docs[i].length / avg_length);
+            scores[i] += idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+        }
+    }
+
+    scores
+}
+
+/// Indices of the `limit` best lexical matches (ties keep configuration order)
+pub fn shortlist_repos(prompt: &str, repos: &[RepoEntry], limit: usize) -> Vec<usize> {
+    if limit == 0 || repos.len() <= limit {
+        return (0..repos.len()).collect();
+    }
+
+    let scores = score_repos(prompt, repos);
+    let mut ranked: Vec<usize> = (0..repos.len()).collect();
+    ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]).then(a.cmp(b)));
+    ranked.truncate(limit);
+    // Keep configuration order in the prompt so the LLM isn't biased by our ranking
+    ranked.sort_unstable();
+    ranked
+}
+
+/// Map a raw BM25 score to 0.0-1.0
+fn normalize_score(raw: f64) -> f64 {
+    raw / (raw + 2.0)
+}
+
+/// Recommend a repository using only the lexical scorer
+pub fn lexical_recommendation(prompt: &str, repos: &[RepoEntry]) -> RepoRecommendation {
+    let scores = score_repos(prompt, repos);
+
+    let mut ranked: Vec<(usize, f64)> = scores
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let Some(&(best_index, best_score)) = ranked.first() else {
+        return RepoRecommendation::none("No repository terms found in the prompt");
+    };
+    let runner_up = ranked.get(1).map(|(_, score)| *score).unwrap_or(0.0);
+
+    let confidence = if best_score >= 3.0 && best_score >= runner_up * 2.0 {
+        "high"
+    } else if best_score >= 1.5 && best_score >= runner_up * 1.3 {
+        "medium"
+    } else {
+        "low"
+    };
+
+    let candidates = ranked
+        .iter()
+        .take(MAX_CANDIDATES)
+        .map(|(index, score)| RepoCandidate {
+            index: *index as i64,
+            name: repos[*index].0.clone(),
+            score: normalize_score(*score),
+        })
+        .collect();
+
+    RepoRecommendation {
+        recommended_index: best_index as i64,
+        recommended_name: repos[best_index].0.clone(),
+        confidence: confidence.to_string(),
+        reasoning: format!(
+            "Prompt terms best match the name, keywords and vocabulary of {}",
+            repos[best_index].0
+        ),
+        candidates,
+        source: "lexical".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(name: &str, description: &str, keywords: &[&str], vocabulary: &[&str]) -> RepoEntry {
+        let list = |terms: &[&str]| {
+            (!terms.is_empty()).then(|| terms.iter().map(|t| t.to_string()).collect())
+        };
+        (
+            name.to_string(),
+            format!("/repos/{}", name),
+            (!description.is_empty()).then(|| description.to_string()),
+            list(keywords),
+            list(vocabulary),
+        )
+    }
+
+    fn repos() -> Vec<RepoEntry> {
+        vec![
+            repo(
+                "web-frontend",
+                "Svelte dashboard for billing customers",
+                &["svelte", "ui"],
+                &["InvoiceTable"],
+            ),
+            repo(
+                "payments-api",
+                "Rust service that charges customers",
+                &["stripe", "billing"],
+                &["SdkSession"],
+            ),
+            repo(
+                "infra",
+                "Terraform modules for the cloud setup",
+                &["terraform", "kubernetes"],
+                &[],
+            ),
+        ]
+    }
+
+    #[test]
+    fn tokenize_splits_identifiers_and_keeps_them_whole() {
+        let terms = tokenize("Fix the SdkSession in snake_case_name");
+        assert!(terms.contains(&"sdksession".to_string()));
+        assert!(terms.contains(&"sdk".to_string()));
+        assert!(terms.contains(&"session".to_string()));
+        assert!(terms.contains(&"snakecasename".to_string()));
+        assert!(!terms.contains(&"the".to_string()));
+        assert!(!terms.contains(&"in".to_string()));
+    }
+
+    #[test]
+    fn name_match_outranks_description_match() {
+        let repos = vec![
+            repo("billing", "", &[], &[]),
+            repo("other", "Handles billing emails", &[], &[]),
+        ];
+        let scores = score_repos("billing retry logic", &repos);
+        assert!(scores[0] > scores[1], "{:?}", scores);
+    }
+
+    #[test]
+    fn rare_terms_weigh_more_than_common_ones() {
+        let repos = repos();
+        // "customers" appears in two descriptions, "terraform" in only one repo
+        let scores = score_repos("customers terraform", &repos);
+        assert!(scores[2] > scores[0], "{:?}", scores);
+        assert!(scores[2] > scores[1], "{:?}", scores);
+    }
+
+    #[test]
+    fn split_query_words_match_joined_vocabulary() {
+        let scores = score_repos("the sdk session leaks", &repos());
+        assert!(scores[1] > scores[0], "{:?}", scores);
+        assert!(scores[1] > scores[2], "{:?}", scores);
+    }
+
+    #[test]
+    fn unrelated_prompt_scores_zero() {
+        let scores = score_repos("hello there", &repos());
+        assert!(scores.iter().all(|s| *s == 0.0), "{:?}", scores);
+    }
+
+    #[test]
+    fn similarity_is_normalized_levenshtein() {
+        assert_eq!(similarity("stripe", "stripe"), 1.0);
+        assert!((similarity("kitten", "sitten") - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", ""), 0.0);
+    }
+
+    #[test]
+    fn misheard_terms_match_fuzzily() {
+        // "kubernetis" is one edit away from the keyword "kubernetes"
+        let scores = score_repos("deploy to kubernetis", &repos());
+        assert!(scores[2] > 0.0, "{:?}", scores);
+        assert_eq!(scores[0], 0.0);
+        assert_eq!(scores[1], 0.0);
+    }
+
+    #[test]
+    fn short_terms_are_not_matched_fuzzily() {
+        let doc = RepoDocument::new(&repo("api", "", &["ui"], &[]));
+        assert_eq!(doc.frequency("ux"), 0.0);
+        assert_eq!(doc.frequency("apj"), 0.0);
+        assert!(doc.frequency("api") > 0.0);
+    }
+
+    #[test]
+    fn shortlist_keeps_best_matches_in_configuration_order() {
+        let repos = repos();
+        assert_eq!(
+            shortlist_repos("stripe billing in svelte", &repos, 2),
+            vec![0, 1]
+        );
+        assert_eq!(shortlist_repos("terraform", &repos, 1), vec![2]);
+        // A limit of 0 or one covering every repo disables the shortlist
+        assert_eq!(shortlist_repos("terraform", &repos, 0), vec![0, 1, 2]);
+        assert_eq!(shortlist_repos("terraform", &repos, 3), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn lexical_recommendation_ranks_candidates() {
+        let rec = lexical_recommendation("charge customers through stripe billing", &repos());
+        assert_eq!(rec.recommended_index, 1);
+        assert_eq!(rec.recommended_name, "payments-api");
+        assert_eq!(rec.source, "lexical");
+        assert_eq!(rec.candidates[0].index, 1);
+        assert!(rec.candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(rec
+            .candidates
+            .iter()
+            .all(|c| c.score > 0.0 && c.score < 1.0));
+    }
+
+    #[test]
+    fn lexical_recommendation_without_matches_is_none() {
+        let rec = lexical_recommendation("hello there", &repos());
+        assert_eq!(rec.get_index(), None);
+        assert!(rec.candidates.is_empty());
+    }
+}
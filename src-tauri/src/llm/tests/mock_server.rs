@@ -0,0 +1,259 @@
+//! Minimal HTTP server that stands in for the Gemini and OpenAI-compatible APIs
+//!
+//! In replay mode it answers each request with the next scripted response. In record mode it
+//! forwards requests to the real API and writes the responses back to the fixture file.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::Fixture;
+
+/// A response returned by the mock server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl MockResponse {
+    pub fn ok(body: serde_json::Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub fn error(status: u16, body: serde_json::Value) -> Self {
+        Self { status, body }
+    }
+}
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    /// Path including the query string
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: serde_json::Value,
+}
+
+impl ReceivedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+enum Mode {
+    Replay(Vec<MockResponse>),
+    Record {
+        upstream: String,
+        fixture_path: PathBuf,
+        fixture: Fixture,
+    },
+}
+
+struct State {
+    mode: Mode,
+    requests: Vec<ReceivedRequest>,
+}
+
+pub struct MockServer {
+    pub url: String,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Answer requests with `responses` in order
+    pub async fn replay(responses: Vec<MockResponse>) -> Self {
+        Self::start(Mode::Replay(responses)).await
+    }
+
+    /// Forward requests to `upstream` and save the responses into the fixture at `fixture_path`
+    pub async fn record(upstream: &str, fixture_path: PathBuf, mut fixture: Fixture) -> Self {
+        fixture.responses.clear();
+        Self::start(Mode::Record {
+            upstream: upstream.to_string(),
+            fixture_path,
+            fixture,
+        })
+        .await
+    }
+
+    async fn start(mode: Mode) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().expect("mock server address"));
+        let state = Arc::new(Mutex::new(State {
+            mode,
+            requests: Vec::new(),
+        }));
+
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        eprintln!("[mock-llm] {}", e);
+                    }
+                });
+            }
+        });
+
+        Self { url, state, handle }
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<State>>) -> Result<(), String> {
+    let request = read_request(&mut stream).await?;
+    state.lock().requests.push(request.clone());
+
+    let upstream = match &mut state.lock().mode {
+        Mode::Replay(responses) => {
+            let response = if responses.is_empty() {
+                MockResponse::error(
+                    500,
+                    serde_json::json!({ "error": { "message": "mock server has no more responses" } }),
+                )
+            } else {
+                responses.remove(0)
+            };
+            Err(response)
+        }
+        Mode::Record { upstream, .. } => Ok(upstream.clone()),
+    };
+
+    let response = match upstream {
+        Err(response) => response,
+        Ok(upstream) => {
+            let response = forward(&upstream, &request).await?;
+            let mut state = state.lock();
+            if let Mode::Record {
+                fixture_path,
+                fixture,
+                ..
+            } = &mut state.mode
+            {
+                fixture.responses.push(response.clone());
+                fixture.save(fixture_path)?;
+            }
+            response
+        }
+    };
+
+    write_response(&mut stream, &response).await
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<ReceivedRequest, String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if n == 0 {
+            return Err("Connection closed before headers were complete".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let path = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .ok_or("Malformed request line")?
+        .to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .iter()
+        .find(|(n, _)| n == "content-length")
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + content_length {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request body: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let body_bytes = &buf[header_end..(header_end + content_length).min(buf.len())];
+    let body = serde_json::from_slice(body_bytes).unwrap_or(serde_json::Value::Null);
+
+    Ok(ReceivedRequest {
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn write_response(stream: &mut TcpStream, response: &MockResponse) -> Result<(), String> {
+    let body = response.body.to_string();
+    let raw = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        body.len(),
+        body
+    );
+    stream
+        .write_all(raw.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))?;
+    stream
+        .shutdown()
+        .await
+        .map_err(|e| format!("Failed to close connection: {}", e))
+}
+
+/// Send a received request to the real API (record mode)
+async fn forward(upstream: &str, request: &ReceivedRequest) -> Result<MockResponse, String> {
+    let mut req = reqwest::Client::new()
+        .post(format!("{}{}", upstream, request.path))
+        .json(&request.body);
+    if let Some(auth) = request.header("authorization") {
+        req = req.header("Authorization", auth);
+    }
+
+    let response = req
+        .send()
+        .await
+        .map_err(|e| format!("Upstream request failed: {}", e))?;
+    let status = response.status().as_u16();
+    let body = response
+        .json()
+        .await
+        .map_err(|e| format!("Upstream returned invalid JSON: {}", e))?;
+    Ok(MockResponse { status, body })
+}
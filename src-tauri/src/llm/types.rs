@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config::LlmFeature;
+
 /// Result for generating a session name from the initial prompt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionNameResult {
@@ -50,6 +52,9 @@ pub struct RepoDescriptionResult {
     pub vocabulary: Vec<String>,
 }
 
+/// A configured repository as the LLM features see it: (name, path, description, keywords, vocabulary)
+pub type RepoEntry = (String, String, Option<String>, Option<Vec<String>>, Option<Vec<String>>);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoRecommendation {
     /// The index of the recommended repository (0-based), or -1 if no clear match
@@ -58,9 +63,37 @@ pub struct RepoRecommendation {
     pub recommended_name: String,
     pub confidence: String, // low, medium, high
     pub reasoning: String,
+    /// Ranked candidates (best first), including the recommended repository
+    #[serde(default)]
+    pub candidates: Vec<RepoCandidate>,
+    /// Where the recommendation came from: "llm" or "lexical"
+    #[serde(default)]
+    pub source: String,
+}
+
+/// A ranked repository candidate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoCandidate {
+    /// Index into the configured repositories (0-based)
+    pub index: i64,
+    pub name: String,
+    /// Relevance score from 0.0 to 1.0
+    pub score: f64,
 }
 
 impl RepoRecommendation {
+    /// A recommendation with no clear match
+    pub fn none(reasoning: &str) -> Self {
+        Self {
+            recommended_index: -1,
+            recommended_name: String::new(),
+            confidence: "low".to_string(),
+            reasoning: reasoning.to_string(),
+            candidates: Vec::new(),
+            source: String::new(),
+        }
+    }
+
     /// Returns the recommended index as Option<usize>, converting -1 to None
     pub fn get_index(&self) -> Option<usize> {
         if self.recommended_index >= 0 {
@@ -92,3 +125,204 @@ pub struct QuickAction {
 pub struct QuickActionsResult {
     pub actions: Vec<QuickAction>,
 }
+
+/// Result for generating a commit message from the session diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitMessageResult {
+    pub subject: String, // Conventional commit subject, e.g. "fix(sidecar): restart on crash"
+    pub body: String,    // Wrapped body explaining what and why (may be empty)
+}
+
+/// Result for generating a pull request title and body from the session diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrDescriptionResult {
+    pub title: String,
+    pub body: String, // Markdown
+}
+
+/// Structured task derived from a rambling dictated prompt
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskSpec {
+    pub goal: String,
+    #[serde(default)]
+    pub constraints: Vec<String>,
+    /// Files, modules, types and functions mentioned (spelled as in the codebase)
+    #[serde(default)]
+    pub files_and_symbols: Vec<String>,
+    #[serde(default)]
+    pub acceptance_criteria: Vec<String>,
+    #[serde(default)]
+    pub open_questions: Vec<String>,
+}
+
+impl TaskSpec {
+    /// Render the spec as Markdown for use as a session system prompt
+    pub fn to_system_prompt(&self) -> String {
+        let mut parts = vec![format!(
+            "The user's request has been summarized into this task specification. Use it to guide the work; the user's own words take precedence if they disagree.\n\n## Goal\n{}",
+            self.goal
+        )];
+
+        let sections = [
+            ("Constraints", &self.constraints),
+            ("Files and symbols mentioned", &self.files_and_symbols),
+            ("Acceptance criteria", &self.acceptance_criteria),
+            ("Open questions (ask the user if they block the work)", &self.open_questions),
+        ];
+        for (title, items) in sections {
+            if !items.is_empty() {
+                let list: Vec<String> = items.iter().map(|item| format!("- {}", item)).collect();
+                parts.push(format!("## {}\n{}", title, list.join("\n")));
+            }
+        }
+
+        parts.join("\n\n")
+    }
+}
+
+/// Result of enhancing a prompt: the structured spec plus its system prompt rendering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptEnhancementResult {
+    pub spec: TaskSpec,
+    pub system_prompt: String,
+}
+
+/// Compacted summary of the older part of a conversation (used when restoring sessions)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub summary: String,
+    #[serde(default)]
+    pub files_touched: Vec<String>,
+    #[serde(default)]
+    pub decisions: Vec<String>,
+    #[serde(default)]
+    pub open_todos: Vec<String>,
+}
+
+impl ConversationSummary {
+    /// Render the summary as the content of a history summary message
+    pub fn to_history_text(&self) -> String {
+        let mut parts = vec![self.summary.clone()];
+
+        let sections = [
+            ("Files touched", &self.files_touched),
+            ("Decisions", &self.decisions),
+            ("Open TODOs", &self.open_todos),
+        ];
+        for (title, items) in sections {
+            if !items.is_empty() {
+                let list: Vec<String> = items.iter().map(|item| format!("- {}", item)).collect();
+                parts.push(format!("{}:\n{}", title, list.join("\n")));
+            }
+        }
+
+        parts.join("\n\n")
+    }
+}
+
+/// App-control action recognized in a spoken instruction. Each action maps onto an
+/// existing command (`set_active_repo`, `update_sdk_model`, `update_sdk_thinking`,
+/// `stop_sdk_query`, `close_sdk_session`, `send_sdk_prompt`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum VoiceIntentAction {
+    /// Not a control instruction - send the transcript to Claude as a prompt
+    Prompt,
+    SwitchRepo { repo_index: usize, repo_name: String },
+    /// Model ID resolved from the enabled models
+    ChangeModel { model: String },
+    SetThinking { enabled: bool },
+    StopSession,
+    CloseSession,
+    /// Send a prompt to another session. An empty prompt refers to the previous transcript
+    QueuePrompt { prompt: String, target: String }, // target: last, active
+}
+
+/// Result of routing a voice transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceIntent {
+    #[serde(flatten)]
+    pub action: VoiceIntentAction,
+    pub confidence: String, // low, medium, high
+    pub source: String,     // rules, llm
+}
+
+/// Flat intent classification returned by the LLM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentClassification {
+    pub intent: String, // prompt, switch_repo, change_model, set_thinking, stop_session, close_session, queue_prompt
+    #[serde(default)]
+    pub repo_index: Option<i64>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub thinking_enabled: Option<bool>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub target: Option<String>,
+    pub confidence: String,
+}
+
+/// Features to include in a combined prompt intake request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptIntakeOptions {
+    pub name_session: bool,
+    pub clean_transcription: bool,
+    pub recommend_model: bool,
+    pub recommend_repo: bool,
+    /// Maximum number of repos (best lexical matches) included in the prompt
+    pub repo_shortlist_size: usize,
+}
+
+/// Recommendations a caller of the combined prompt intake wants (both by default)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntakeNeeds {
+    pub model: bool,
+    pub repo: bool,
+}
+
+impl Default for IntakeNeeds {
+    fn default() -> Self {
+        Self {
+            model: true,
+            repo: true,
+        }
+    }
+}
+
+/// Result of a combined prompt intake request (only the requested features are populated)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptIntakeResult {
+    #[serde(default)]
+    pub session_name: Option<SessionNameResult>,
+    #[serde(default)]
+    pub transcription_cleanup: Option<TranscriptionCleanupResult>,
+    #[serde(default)]
+    pub model_recommendation: Option<ModelRecommendation>,
+    #[serde(default)]
+    pub repo_recommendation: Option<RepoRecommendation>,
+}
+
+impl PromptIntakeResult {
+    /// Serialized length of a single feature's output, used to attribute output tokens
+    pub fn output_len(&self, key: &str) -> usize {
+        let value = match key {
+            "session_name" => serde_json::to_string(&self.session_name),
+            "transcription_cleanup" => serde_json::to_string(&self.transcription_cleanup),
+            "model_recommendation" => serde_json::to_string(&self.model_recommendation),
+            "repo_recommendation" => serde_json::to_string(&self.repo_recommendation),
+            _ => return 0,
+        };
+        value.map(|s| s.len()).unwrap_or(0)
+    }
+}
+
+/// Token usage attributed to one feature of a combined prompt intake request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntakeFeatureUsage {
+    pub feature: LlmFeature,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
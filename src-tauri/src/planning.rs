@@ -0,0 +1,253 @@
+//! Answers to plan-mode planning questions
+//!
+//! `ask_planning_questions` shows a wizard in the UI. The user's choices come back as option
+//! labels per question plus optional free text, are checked against the questions the session
+//! was asked and are sent to the sidecar as a `planning_answers` message, which turns them into
+//! the next user turn.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sidecar::PlanningQuestion;
+
+/// The user's answer to one planning question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanningAnswer {
+    pub question_index: usize,
+    /// Labels of the chosen options; at most one unless the question is multi-select
+    #[serde(default)]
+    pub selected: Vec<String>,
+    /// Free-text "other" answer
+    #[serde(default)]
+    pub other: Option<String>,
+}
+
+/// A planning question together with the user's answer, as sent to the sidecar
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnsweredPlanningQuestion {
+    pub header: String,
+    pub question: String,
+    pub multi_select: bool,
+    pub selected: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other: Option<String>,
+}
+
+/// Check answers against the questions they belong to. Answers without a selection or text
+/// are dropped (the question was skipped).
+pub fn answer_questions(
+    questions: &[PlanningQuestion],
+    answers: Vec<PlanningAnswer>,
+) -> Result<Vec<AnsweredPlanningQuestion>, String> {
+    let mut answered: Vec<(usize, AnsweredPlanningQuestion)> = Vec::new();
+    // Includes skipped questions, which are not in `answered`
+    let mut seen: Vec<usize> = Vec::new();
+
+    for answer in answers {
+        let question = questions.get(answer.question_index).ok_or_else(|| {
+            format!(
+                "Answer for question {} but only {} questions were asked",
+                answer.question_index + 1,
+                questions.len()
+            )
+        })?;
+        if seen.contains(&answer.question_index) {
+            return Err(format!(
+                "Question {} (\"{}\") was answered twice",
+                answer.question_index + 1,
+                question.header
+            ));
+        }
+        seen.push(answer.question_index);
+        for label in &answer.selected {
+            if !question.options.iter().any(|option| &option.label == label) {
+                return Err(format!(
+                    "\"{}\" is not an option of question \"{}\"",
+                    label, question.header
+                ));
+            }
+        }
+        if !question.multi_select && answer.selected.len() > 1 {
+            return Err(format!(
+                "Question \"{}\" allows one option but {} were selected",
+                question.header,
+                answer.selected.len()
+            ));
+        }
+
+        let other = answer
+            .other
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty());
+        if answer.selected.is_empty() && other.is_none() {
+            continue;
+        }
+        answered.push((
+            answer.question_index,
+            AnsweredPlanningQuestion {
+                header: question.header.clone(),
+                question: question.question.clone(),
+                multi_select: question.multi_select,
+                selected: answer.selected,
+                other,
+            },
+        ));
+    }
+
+    answered.sort_by_key(|(index, _)| *index);
+    Ok(answered.into_iter().map(|(_, answer)| answer).collect())
+}
+
+/// The user turn the sidecar sends for these answers (matches `formatPlanningAnswers` in the
+/// sidecar); used for the session history and the message shown in the UI
+pub fn answers_text(answers: &[AnsweredPlanningQuestion]) -> String {
+    let mut text = String::from("Here are my answers to the planning questions:\n");
+    for answer in answers {
+        text.push_str(&format!("\n**{}**: {}\n", answer.header, answer.question));
+        if !answer.selected.is_empty() {
+            text.push_str(&format!("- Selected: {}\n", answer.selected.join(", ")));
+        }
+        if let Some(other) = &answer.other {
+            text.push_str(&format!("- Other: {}\n", other));
+        }
+    }
+    text.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::PlanningQuestionOption;
+
+    fn question(header: &str, options: &[&str], multi_select: bool) -> PlanningQuestion {
+        PlanningQuestion {
+            question: format!("Which {}?", header.to_lowercase()),
+            header: header.to_string(),
+            options: options
+                .iter()
+                .map(|label| PlanningQuestionOption {
+                    label: label.to_string(),
+                    description: String::new(),
+                })
+                .collect(),
+            multi_select,
+        }
+    }
+
+    fn questions() -> Vec<PlanningQuestion> {
+        vec![
+            question("Scope", &["Small", "Large"], false),
+            question("Storage", &["SQLite", "Files"], true),
+            question("Tests", &["Unit", "End to end"], false),
+        ]
+    }
+
+    fn answer(question_index: usize, selected: &[&str], other: Option<&str>) -> PlanningAnswer {
+        PlanningAnswer {
+            question_index,
+            selected: selected.iter().map(|s| s.to_string()).collect(),
+            other: other.map(String::from),
+        }
+    }
+
+    #[test]
+    fn answers_are_ordered_by_question() {
+        let answered = answer_questions(
+            &questions(),
+            vec![
+                answer(1, &["SQLite", "Files"], Some("  and S3 ")),
+                answer(0, &["Small"], None),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            answered,
+            vec![
+                AnsweredPlanningQuestion {
+                    header: "Scope".to_string(),
+                    question: "Which scope?".to_string(),
+                    multi_select: false,
+                    selected: vec!["Small".to_string()],
+                    other: None,
+                },
+                AnsweredPlanningQuestion {
+                    header: "Storage".to_string(),
+                    question: "Which storage?".to_string(),
+                    multi_select: true,
+                    selected: vec!["SQLite".to_string(), "Files".to_string()],
+                    other: Some("and S3".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            answers_text(&answered),
+            "Here are my answers to the planning questions:\n\n\
+             **Scope**: Which scope?\n\
+             - Selected: Small\n\n\
+             **Storage**: Which storage?\n\
+             - Selected: SQLite, Files\n\
+             - Other: and S3"
+        );
+    }
+
+    #[test]
+    fn unknown_question_index_is_rejected() {
+        let err = answer_questions(&questions(), vec![answer(3, &[], Some("extra"))]).unwrap_err();
+        assert_eq!(err, "Answer for question 4 but only 3 questions were asked");
+
+        let err = answer_questions(&[], vec![answer(0, &["Small"], None)]).unwrap_err();
+        assert_eq!(err, "Answer for question 1 but only 0 questions were asked");
+    }
+
+    #[test]
+    fn missing_and_empty_answers_skip_the_question() {
+        let answered = answer_questions(
+            &questions(),
+            vec![
+                answer(0, &[], Some("   ")),
+                answer(2, &[], Some("Only unit")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(answered.len(), 1);
+        assert_eq!(answered[0].header, "Tests");
+        assert_eq!(answered[0].other.as_deref(), Some("Only unit"));
+
+        assert!(answer_questions(&questions(), Vec::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn answering_twice_is_rejected() {
+        let err = answer_questions(
+            &questions(),
+            vec![answer(1, &["SQLite"], None), answer(1, &["Files"], None)],
+        )
+        .unwrap_err();
+        assert_eq!(err, "Question 2 (\"Storage\") was answered twice");
+
+        // Even when the first answer was empty and got dropped
+        let err = answer_questions(
+            &questions(),
+            vec![answer(0, &[], None), answer(0, &["Large"], None)],
+        )
+        .unwrap_err();
+        assert!(err.contains("answered twice"), "{}", err);
+    }
+
+    #[test]
+    fn selections_must_match_the_options() {
+        let err = answer_questions(&questions(), vec![answer(0, &["Medium"], None)]).unwrap_err();
+        assert_eq!(err, "\"Medium\" is not an option of question \"Scope\"");
+
+        let err =
+            answer_questions(&questions(), vec![answer(0, &["Small", "Large"], None)]).unwrap_err();
+        assert_eq!(
+            err,
+            "Question \"Scope\" allows one option but 2 were selected"
+        );
+    }
+}
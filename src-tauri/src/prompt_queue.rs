@@ -0,0 +1,211 @@
+//! Prompts waiting for a busy SDK session
+//!
+//! A prompt sent while its session is still running a query is queued instead of being handed
+//! to the sidecar, which would otherwise start a second query on the same conversation. The
+//! next queued prompt is sent once the running query finishes with `done`. Stopping a query or
+//! a query that ends with an error pauses the queue until the next prompt is sent or the user
+//! runs the queue again.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sidecar::ImageData;
+
+/// A prompt waiting for its session to finish the running query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedPrompt {
+    /// Identifies the item for cancel and reorder
+    pub id: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub images: Option<Vec<ImageData>>,
+    /// When the prompt was queued (Unix ms)
+    pub queued_at: u64,
+}
+
+impl QueuedPrompt {
+    pub fn new(prompt: String, images: Option<Vec<ImageData>>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            prompt,
+            images,
+            queued_at: chrono::Utc::now().timestamp_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptQueue {
+    items: VecDeque<QueuedPrompt>,
+    paused: bool,
+}
+
+impl PromptQueue {
+    pub fn items(&self) -> Vec<QueuedPrompt> {
+        self.items.iter().cloned().collect()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// A query started from a prompt the user sent: they are driving the session again
+    pub fn query_started(&mut self) {
+        self.paused = false;
+    }
+
+    /// A query ended. After an error the queue waits for the user instead of running on.
+    pub fn query_ended(&mut self, failed: bool) {
+        if failed {
+            self.paused = true;
+        }
+    }
+
+    /// Next prompt to send, unless the session is still running a query or the queue is paused
+    pub fn take_next(&mut self, querying: bool) -> Option<QueuedPrompt> {
+        if querying || self.paused {
+            return None;
+        }
+        self.items.pop_front()
+    }
+
+    pub fn push(&mut self, item: QueuedPrompt) {
+        self.items.push_back(item);
+    }
+
+    /// Put back an item that could not be sent
+    pub fn push_front(&mut self, item: QueuedPrompt) {
+        self.items.push_front(item);
+    }
+
+    /// Replace the queue, e.g. with the one saved for a restored session
+    pub fn replace(&mut self, items: Vec<QueuedPrompt>) {
+        self.items = items.into();
+    }
+
+    pub fn cancel(&mut self, item_id: &str) -> Result<QueuedPrompt, String> {
+        let index = self.position(item_id)?;
+        Ok(self.items.remove(index).expect("index from position"))
+    }
+
+    /// Move an item to `index` (clamped to the end of the queue)
+    pub fn move_to(&mut self, item_id: &str, index: usize) -> Result<(), String> {
+        let item = self.cancel(item_id)?;
+        let index = index.min(self.items.len());
+        self.items.insert(index, item);
+        Ok(())
+    }
+
+    fn position(&self, item_id: &str) -> Result<usize, String> {
+        self.items
+            .iter()
+            .position(|item| item.id == item_id)
+            .ok_or_else(|| format!("Queued prompt {} not found", item_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(prompts: &[&str]) -> PromptQueue {
+        let mut queue = PromptQueue::default();
+        for prompt in prompts {
+            queue.push(QueuedPrompt {
+                id: prompt.to_string(),
+                prompt: prompt.to_string(),
+                images: None,
+                queued_at: 0,
+            });
+        }
+        queue
+    }
+
+    fn ids(queue: &PromptQueue) -> Vec<String> {
+        queue.items().into_iter().map(|item| item.id).collect()
+    }
+
+    #[test]
+    fn move_to_reorders() {
+        let mut q = queue(&["a", "b", "c"]);
+        q.move_to("c", 0).unwrap();
+        assert_eq!(ids(&q), ["c", "a", "b"]);
+        q.move_to("c", 1).unwrap();
+        assert_eq!(ids(&q), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn move_to_past_the_end_appends() {
+        let mut q = queue(&["a", "b", "c"]);
+        q.move_to("a", 99).unwrap();
+        assert_eq!(ids(&q), ["b", "c", "a"]);
+        assert!(q.move_to("missing", 0).is_err());
+        assert_eq!(ids(&q), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn cancel_removes_the_item() {
+        let mut q = queue(&["a", "b", "c"]);
+        assert_eq!(q.cancel("b").unwrap().prompt, "b");
+        assert_eq!(ids(&q), ["a", "c"]);
+        assert!(q.cancel("b").is_err());
+    }
+
+    #[test]
+    fn push_front_puts_an_unsent_item_back_first() {
+        let mut q = queue(&["a", "b"]);
+        let item = q.take_next(false).unwrap();
+        q.push_front(item);
+        assert_eq!(ids(&q), ["a", "b"]);
+    }
+
+    #[test]
+    fn nothing_is_taken_while_querying() {
+        let mut q = queue(&["a"]);
+        assert!(q.take_next(true).is_none());
+        assert_eq!(q.take_next(false).unwrap().id, "a");
+        assert!(q.take_next(false).is_none());
+    }
+
+    #[test]
+    fn errors_pause_until_the_next_query() {
+        let mut q = queue(&["a", "b"]);
+
+        // A successful query runs the queue on
+        q.query_ended(false);
+        assert_eq!(q.take_next(false).unwrap().id, "a");
+
+        // A failed one pauses it until the next prompt starts a query
+        q.query_ended(true);
+        assert!(q.take_next(false).is_none());
+        q.query_started();
+        assert_eq!(q.take_next(false).unwrap().id, "b");
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let mut q = queue(&["a"]);
+        q.pause();
+        assert!(q.take_next(false).is_none());
+        // A success does not undo an explicit pause
+        q.query_ended(false);
+        assert!(q.take_next(false).is_none());
+        q.resume();
+        assert_eq!(q.take_next(false).unwrap().id, "a");
+    }
+
+    #[test]
+    fn replace_keeps_the_pause_state() {
+        let mut q = queue(&["a"]);
+        q.pause();
+        q.replace(queue(&["x", "y"]).items());
+        assert_eq!(ids(&q), ["x", "y"]);
+        assert!(q.take_next(false).is_none());
+    }
+}
@@ -0,0 +1,775 @@
+//! Offline repository profiling - derives a description, keywords and vocabulary from
+//! docs, manifests and source identifiers without calling an LLM.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Maximum number of source files scanned for identifiers
+const MAX_SOURCE_FILES: usize = 1500;
+/// Source files larger than this are skipped (generated/minified code)
+const MAX_SOURCE_FILE_BYTES: u64 = 256 * 1024;
+const MAX_KEYWORDS: usize = 20;
+const MAX_VOCABULARY: usize = 40;
+
+/// Directories that are never scanned when the repo isn't a git repository
+const SKIPPED_DIRS: &[&str] = &[
+    ".git", "node_modules", "target", "dist", "build", "out", "vendor", ".venv", "venv",
+    "__pycache__", ".next", ".svelte-kit", ".idea", ".vscode", "coverage", "bin", "obj",
+];
+
+/// Source file extensions and the language they indicate
+const SOURCE_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("mjs", "javascript"),
+    ("svelte", "svelte"),
+    ("vue", "vue"),
+    ("py", "python"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("cs", "csharp"),
+    ("cpp", "cpp"),
+    ("cc", "cpp"),
+    ("c", "c"),
+    ("h", "c"),
+    ("hpp", "cpp"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("swift", "swift"),
+    ("dart", "dart"),
+];
+
+/// Well-known dependencies that make good keywords
+const KNOWN_FRAMEWORKS: &[&str] = &[
+    "tauri", "tokio", "axum", "actix-web", "rocket", "serde", "diesel", "sqlx", "bevy", "clap",
+    "react", "svelte", "@sveltejs/kit", "vue", "next", "nuxt", "angular", "express", "fastify",
+    "nestjs", "electron", "tailwindcss", "vite", "prisma", "graphql", "redux", "three",
+    "django", "flask", "fastapi", "pandas", "numpy", "pytorch", "torch", "tensorflow",
+    "sqlalchemy", "pydantic", "celery",
+];
+
+/// Identifiers that are too generic to be useful vocabulary
+const COMMON_IDENTIFIERS: &[&str] = &[
+    "self", "this", "true", "false", "null", "none", "some", "string", "number", "boolean",
+    "return", "const", "function", "async", "await", "import", "export", "default", "struct",
+    "enum", "impl", "trait", "class", "interface", "type", "public", "private", "static",
+    "value", "result", "error", "option", "println", "eprintln", "format", "unwrap", "clone",
+    "into", "from", "length", "console", "window", "document", "event", "props",
+    "state", "index", "items", "data", "item", "args", "params", "options", "config", "undefined",
+    "let", "mut", "match", "else", "while", "loop", "break", "continue", "where", "with", "def",
+    "elif", "lambda", "pass", "yield", "super", "extends", "implements", "throws", "catch",
+    "finally", "throw", "new", "delete", "typeof", "instanceof", "void", "usize", "isize",
+    "to_string", "to_owned", "as_str", "map_err", "unwrap_or", "unwrap_or_default", "and_then",
+    "ok_or", "serde_json", "viewBox", "currentColor", "toString", "forEach", "innerHTML",
+    "addEventListener", "preventDefault", "stopPropagation", "setTimeout", "querySelector",
+];
+
+/// Keywords that introduce a definition - the following identifier gets extra weight
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "struct", "enum", "trait", "fn", "mod", "class", "interface", "type", "function", "def",
+    "const", "let",
+];
+
+/// Result of scanning a repository
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RepoProfile {
+    pub description: String,
+    pub keywords: Vec<String>,
+    pub vocabulary: Vec<String>,
+    /// Compact text summary of the scan - a cheaper LLM input than the full docs
+    pub digest: String,
+}
+
+/// Metadata collected from package manifests
+#[derive(Debug, Default)]
+struct ManifestInfo {
+    name: Option<String>,
+    description: Option<String>,
+    keywords: Vec<String>,
+    dependencies: Vec<String>,
+}
+
+/// Scan a repository and build its profile
+pub fn profile_repo(repo_path: &str, repo_name: &str) -> Result<RepoProfile, String> {
+    let root = Path::new(repo_path);
+    if !root.is_dir() {
+        return Err(format!("Repository path not found: {}", repo_path));
+    }
+
+    let doc = read_first(root, &["README.md", "readme.md", "Readme.md", "README", "CLAUDE.md"]);
+    let manifest = read_manifests(root);
+    let files = list_files(root);
+
+    let mut language_counts: HashMap<&'static str, usize> = HashMap::new();
+    let mut identifier_scores: HashMap<String, f64> = HashMap::new();
+    let mut dir_counts: HashMap<String, usize> = HashMap::new();
+
+    for rel in files.iter() {
+        if let Some(parent) = rel.parent().and_then(|p| p.file_name()) {
+            *dir_counts.entry(parent.to_string_lossy().to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let source_files = files
+        .iter()
+        .filter_map(|rel| source_language(rel).map(|lang| (rel, lang)))
+        .take(MAX_SOURCE_FILES);
+    for (rel, lang) in source_files {
+        *language_counts.entry(lang).or_insert(0) += 1;
+
+        let path = root.join(rel);
+        let too_large = fs::metadata(&path)
+            .map(|m| m.len() > MAX_SOURCE_FILE_BYTES)
+            .unwrap_or(true);
+        if too_large {
+            continue;
+        }
+        if let Ok(content) = fs::read_to_string(&path) {
+            collect_identifiers(&content, &mut identifier_scores);
+        }
+    }
+
+    let mut languages: Vec<(&'static str, usize)> = language_counts.into_iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let languages: Vec<&'static str> = languages.into_iter().take(4).map(|(lang, _)| lang).collect();
+
+    let frameworks: Vec<String> = manifest
+        .dependencies
+        .iter()
+        .filter(|dep| KNOWN_FRAMEWORKS.contains(&dep.as_str()))
+        .map(|dep| match dep.as_str() {
+            "@sveltejs/kit" => "sveltekit".to_string(),
+            _ => dep.clone(),
+        })
+        .collect();
+    let mut seen_frameworks = HashSet::new();
+    let frameworks: Vec<String> = frameworks
+        .into_iter()
+        .filter(|f| !languages.contains(&f.as_str()) && seen_frameworks.insert(f.clone()))
+        .collect();
+
+    let description = build_description(repo_name, &manifest, doc.as_deref(), &languages, &frameworks);
+    let keywords = build_keywords(&manifest, &languages, &frameworks, &dir_counts);
+    let vocabulary = build_vocabulary(identifier_scores, &dir_counts);
+
+    let digest = build_digest(
+        repo_name,
+        &manifest,
+        doc.as_deref(),
+        &languages,
+        &frameworks,
+        &keywords,
+        &vocabulary,
+    );
+
+    Ok(RepoProfile {
+        description,
+        keywords,
+        vocabulary,
+        digest,
+    })
+}
+
+/// Read the first of the given files that exists in the repo root
+fn read_first(root: &Path, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok())
+}
+
+fn read_manifests(root: &Path) -> ManifestInfo {
+    let mut info = ManifestInfo::default();
+
+    if let Ok(content) = fs::read_to_string(root.join("Cargo.toml")) {
+        parse_toml_manifest(&content, "package", &["dependencies"], &mut info);
+    }
+
+    if let Ok(content) = fs::read_to_string(root.join("pyproject.toml")) {
+        parse_toml_manifest(&content, "project", &[], &mut info);
+        parse_toml_manifest(&content, "tool.poetry", &["tool.poetry.dependencies"], &mut info);
+    }
+
+    if let Ok(content) = fs::read_to_string(root.join("package.json")) {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
+            if info.name.is_none() {
+                info.name = json["name"].as_str().map(String::from);
+            }
+            if info.description.is_none() {
+                info.description = json["description"]
+                    .as_str()
+                    .filter(|d| !d.is_empty())
+                    .map(String::from);
+            }
+            if let Some(keywords) = json["keywords"].as_array() {
+                info.keywords
+                    .extend(keywords.iter().filter_map(|k| k.as_str()).map(String::from));
+            }
+            for section in ["dependencies", "devDependencies"] {
+                if let Some(deps) = json[section].as_object() {
+                    info.dependencies.extend(deps.keys().cloned());
+                }
+            }
+        }
+    }
+
+    // Tauri apps keep their Cargo.toml one level down
+    if let Ok(content) = fs::read_to_string(root.join("src-tauri").join("Cargo.toml")) {
+        parse_toml_manifest(&content, "package", &["dependencies"], &mut info);
+    }
+
+    info
+}
+
+/// Minimal line-based TOML reader for `name`, `description`, `keywords` and dependency names
+fn parse_toml_manifest(content: &str, package_table: &str, dep_tables: &[&str], info: &mut ManifestInfo) {
+    let mut table = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            table = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        let value = value.trim();
+
+        if table == package_table {
+            match key {
+                "name" if info.name.is_none() => info.name = Some(unquote(value)),
+                "description" if info.description.is_none() => info.description = Some(unquote(value)),
+                "keywords" => info.keywords.extend(
+                    value
+                        .trim_matches(|c| c == '[' || c == ']')
+                        .split(',')
+                        .map(unquote)
+                        .filter(|k| !k.is_empty()),
+                ),
+                "dependencies" if value.starts_with('[') => info.dependencies.extend(
+                    value
+                        .trim_matches(|c| c == '[' || c == ']')
+                        .split(',')
+                        .map(|d| {
+                            unquote(d)
+                                .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                                .next()
+                                .unwrap_or_default()
+                                .to_string()
+                        })
+                        .filter(|d| !d.is_empty()),
+                ),
+                _ => {}
+            }
+        } else if dep_tables.contains(&table.as_str()) {
+            info.dependencies.push(key.to_string());
+        }
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// List repository files relative to the root. Uses git when available so .gitignore
+/// is respected; otherwise walks the tree skipping common build/dependency directories.
+fn list_files(root: &Path) -> Vec<PathBuf> {
+    let mut cmd = Command::new("git");
+    cmd.args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root);
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    if let Ok(output) = cmd.output() {
+        if output.status.success() {
+            return String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(PathBuf::from)
+                .collect();
+        }
+    }
+
+    let ignored = read_gitignore(root);
+    let mut files = Vec::new();
+    walk(root, root, &ignored, &mut files);
+    files
+}
+
+/// Simple .gitignore support for the non-git fallback: plain names and `*.ext` patterns
+fn read_gitignore(root: &Path) -> Vec<String> {
+    fs::read_to_string(root.join(".gitignore"))
+        .map(|content| {
+            content
+                .lines()
+                .map(|l| l.trim().trim_matches('/').to_string())
+                .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('!'))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_ignored(name: &str, ignored: &[String]) -> bool {
+    ignored.iter().any(|pattern| match pattern.strip_prefix('*') {
+        Some(suffix) => name.ends_with(suffix),
+        None => pattern == name,
+    })
+}
+
+fn walk(root: &Path, dir: &Path, ignored: &[String], files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if files.len() >= MAX_SOURCE_FILES * 4 {
+            return;
+        }
+
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_ignored(&name, ignored) {
+            continue;
+        }
+
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) && !name.starts_with('.') {
+                walk(root, &path, ignored, files);
+            }
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            files.push(rel.to_path_buf());
+        }
+    }
+}
+
+fn source_language(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    // Skip type declaration and minified bundles
+    let name = path.file_name()?.to_str()?;
+    if name.ends_with(".d.ts") || name.ends_with(".min.js") {
+        return None;
+    }
+    SOURCE_EXTENSIONS
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|(_, lang)| *lang)
+}
+
+/// Count project-specific identifiers (CamelCase, camelCase, snake_case).
+/// Identifiers that follow a definition keyword count extra.
+fn collect_identifiers(content: &str, scores: &mut HashMap<String, f64>) {
+    let mut prev_word = String::new();
+    let mut current = String::new();
+
+    let mut flush = |word: &mut String, prev: &mut String| {
+        if word.is_empty() {
+            return;
+        }
+        if is_interesting_identifier(word) {
+            let weight = if DEFINITION_KEYWORDS.contains(&prev.as_str()) { 3.0 } else { 1.0 };
+            *scores.entry(word.clone()).or_insert(0.0) += weight;
+        }
+        *prev = std::mem::take(word);
+    };
+
+    for c in content.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            current.push(c);
+        } else {
+            flush(&mut current, &mut prev_word);
+        }
+    }
+    flush(&mut current, &mut prev_word);
+}
+
+fn is_interesting_identifier(word: &str) -> bool {
+    let len = word.chars().count();
+    if !(4..=40).contains(&len) {
+        return false;
+    }
+    let first = word.chars().next().unwrap_or('0');
+    if !first.is_alphabetic() {
+        return false;
+    }
+    if COMMON_IDENTIFIERS.iter().any(|common| common.eq_ignore_ascii_case(word)) {
+        return false;
+    }
+    // SCREAMING_CASE constants are rarely spoken
+    if word.chars().all(|c| c.is_uppercase() || c == '_' || c.is_numeric()) {
+        return false;
+    }
+
+    let has_inner_upper = word.chars().skip(1).any(|c| c.is_uppercase());
+    let has_underscore = word.trim_matches('_').contains('_');
+    has_inner_upper || has_underscore
+}
+
+fn build_description(
+    repo_name: &str,
+    manifest: &ManifestInfo,
+    doc: Option<&str>,
+    languages: &[&str],
+    frameworks: &[String],
+) -> String {
+    let summary = manifest
+        .description
+        .clone()
+        .or_else(|| doc.and_then(first_paragraph))
+        .unwrap_or_else(|| format!("The {} project", repo_name));
+
+    let mut tech: Vec<String> = languages.iter().map(|l| capitalize(l)).collect();
+    tech.extend(frameworks.iter().take(4).map(|f| capitalize(f)));
+
+    let summary = first_sentences(&summary, 2);
+    let summary = summary.trim_end_matches('.');
+    if tech.is_empty() {
+        format!("{}.", summary)
+    } else {
+        format!("{}. Built with {}.", summary, tech.join(", "))
+    }
+}
+
+/// First prose paragraph of a Markdown document (skips headings, badges, HTML and code)
+fn first_paragraph(doc: &str) -> Option<String> {
+    let mut paragraph = Vec::new();
+    let mut in_code = false;
+
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+
+        let is_prose = !trimmed.is_empty()
+            && !trimmed.starts_with('#')
+            && !trimmed.starts_with('<')
+            && !trimmed.starts_with("![")
+            && !trimmed.starts_with("[![")
+            && !trimmed.starts_with('|')
+            && !trimmed.starts_with("---");
+
+        if is_prose {
+            paragraph.push(trimmed.trim_start_matches("> ").to_string());
+        } else if !paragraph.is_empty() {
+            break;
+        }
+    }
+
+    if paragraph.is_empty() {
+        None
+    } else {
+        Some(paragraph.join(" "))
+    }
+}
+
+fn first_sentences(text: &str, count: usize) -> String {
+    let mut end = text.len();
+    let mut seen = 0;
+    for (i, c) in text.char_indices() {
+        if c == '.' || c == '!' || c == '?' {
+            let next = text[i + c.len_utf8()..].chars().next();
+            if next.is_none_or(|n| n.is_whitespace()) {
+                seen += 1;
+                if seen == count {
+                    end = i + c.len_utf8();
+                    break;
+                }
+            }
+        }
+    }
+    text[..end].trim().to_string()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+fn build_keywords(
+    manifest: &ManifestInfo,
+    languages: &[&str],
+    frameworks: &[String],
+    dir_counts: &HashMap<String, usize>,
+) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut push = |keyword: String, keywords: &mut Vec<String>| {
+        let keyword = keyword.to_lowercase();
+        if keyword.len() >= 2 && seen.insert(keyword.clone()) {
+            keywords.push(keyword);
+        }
+    };
+
+    for keyword in &manifest.keywords {
+        push(keyword.clone(), &mut keywords);
+    }
+    for language in languages {
+        push(language.to_string(), &mut keywords);
+    }
+    for framework in frameworks {
+        push(framework.clone(), &mut keywords);
+    }
+
+    // Frequently used directory names describe the project's areas (components, commands, api, ...)
+    let mut dirs: Vec<(&String, &usize)> = dir_counts
+        .iter()
+        .filter(|(name, _)| !SKIPPED_DIRS.contains(&name.as_str()) && !matches!(name.as_str(), "src" | "lib" | "tests" | "test"))
+        .filter(|(name, _)| name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_'))
+        .collect();
+    dirs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (dir, _) in dirs {
+        if keywords.len() >= MAX_KEYWORDS {
+            break;
+        }
+        push(dir.replace('_', "-"), &mut keywords);
+    }
+
+    keywords.truncate(MAX_KEYWORDS);
+    keywords
+}
+
+fn build_vocabulary(identifier_scores: HashMap<String, f64>, dir_counts: &HashMap<String, usize>) -> Vec<String> {
+    let mut ranked: Vec<(String, f64)> = identifier_scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut vocabulary: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for (word, _) in ranked {
+        if vocabulary.len() >= MAX_VOCABULARY {
+            break;
+        }
+        if seen.insert(word.to_lowercase()) {
+            vocabulary.push(word);
+        }
+    }
+
+    // Distinctive directory names are often spoken too (e.g. "sidecar")
+    let mut dirs: Vec<&String> = dir_counts
+        .keys()
+        .filter(|name| name.len() >= 4 && !SKIPPED_DIRS.contains(&name.as_str()))
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        if vocabulary.len() >= MAX_VOCABULARY + 10 {
+            break;
+        }
+        if seen.insert(dir.to_lowercase()) {
+            vocabulary.push(dir.clone());
+        }
+    }
+
+    vocabulary
+}
+
+fn build_digest(
+    repo_name: &str,
+    manifest: &ManifestInfo,
+    doc: Option<&str>,
+    languages: &[&str],
+    frameworks: &[String],
+    keywords: &[String],
+    vocabulary: &[String],
+) -> String {
+    let mut parts = vec![format!("Repository: {}", repo_name)];
+
+    if let Some(name) = &manifest.name {
+        parts.push(format!("Package name: {}", name));
+    }
+    if let Some(description) = &manifest.description {
+        parts.push(format!("Package description: {}", description));
+    }
+    if !languages.is_empty() {
+        parts.push(format!("Languages: {}", languages.join(", ")));
+    }
+    if !frameworks.is_empty() {
+        parts.push(format!("Frameworks: {}", frameworks.join(", ")));
+    }
+    if !manifest.dependencies.is_empty() {
+        let deps: Vec<&str> = manifest.dependencies.iter().take(30).map(|d| d.as_str()).collect();
+        parts.push(format!("Dependencies: {}", deps.join(", ")));
+    }
+    if let Some(intro) = doc.and_then(first_paragraph) {
+        parts.push(format!("README intro: {}", first_sentences(&intro, 4)));
+    }
+    if !keywords.is_empty() {
+        parts.push(format!("Candidate keywords: {}", keywords.join(", ")));
+    }
+    if !vocabulary.is_empty() {
+        parts.push(format!("Frequent identifiers: {}", vocabulary.join(", ")));
+    }
+
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_manifest_fields_and_dependencies() {
+        let content = r#"
+[package]
+name = "voice-coder"
+description = "Voice driven coding sessions"
+keywords = ["voice", "claude", ""]
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+"tauri" = "2"
+
+[dev-dependencies]
+tempfile = "3"
+"#;
+        let mut info = ManifestInfo::default();
+        parse_toml_manifest(content, "package", &["dependencies"], &mut info);
+
+        assert_eq!(info.name.as_deref(), Some("voice-coder"));
+        assert_eq!(info.description.as_deref(), Some("Voice driven coding sessions"));
+        assert_eq!(info.keywords, vec!["voice", "claude"]);
+        assert_eq!(info.dependencies, vec!["serde", "tauri"]);
+    }
+
+    #[test]
+    fn pyproject_dependency_arrays_keep_only_names() {
+        let content = r#"
+[project]
+name = 'scraper'
+dependencies = ["requests>=2.31", "beautifulsoup4[lxml]", "click"]
+
+[tool.poetry]
+name = "ignored"
+"#;
+        let mut info = ManifestInfo::default();
+        parse_toml_manifest(content, "project", &[], &mut info);
+        parse_toml_manifest(content, "tool.poetry", &["tool.poetry.dependencies"], &mut info);
+
+        // The first manifest to set a name wins
+        assert_eq!(info.name.as_deref(), Some("scraper"));
+        assert_eq!(info.description, None);
+        assert_eq!(info.dependencies, vec!["requests", "beautifulsoup4", "click"]);
+    }
+
+    #[test]
+    fn first_paragraph_skips_markdown_chrome() {
+        let doc = "\
+# Voice Coder
+
+[![CI](https://example.com/badge.svg)](https://example.com)
+<p align=\"center\"><img src=\"logo.png\"></p>
+
+```sh
+npm install
+```
+
+> Talk to Claude
+while you code.
+
+Second paragraph.
+";
+        assert_eq!(
+            first_paragraph(doc).as_deref(),
+            Some("Talk to Claude while you code.")
+        );
+        assert_eq!(first_paragraph("# Title\n\n| a | b |\n---\n"), None);
+        assert_eq!(first_paragraph(""), None);
+    }
+
+    #[test]
+    fn interesting_identifiers() {
+        let cases = [
+            ("SidecarManager", true),
+            ("parse_toml_manifest", true),
+            ("private_field", true),
+            ("_private_field", false),
+            ("getUserName", true),
+            ("Manager", false),
+            ("lowercase", false),
+            ("MAX_KEYWORDS", false),
+            ("addEventListener", false),
+            ("to_string", false),
+            ("fooBar", true),
+            ("aB", false),
+            ("trailing_", false),
+            ("9lives_here", false),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(is_interesting_identifier(word), expected, "{}", word);
+        }
+        assert!(!is_interesting_identifier(&format!("long_{}", "x".repeat(40))));
+    }
+
+    #[test]
+    fn keywords_come_from_manifest_then_stack_then_directories() {
+        let manifest = ManifestInfo {
+            keywords: vec!["Voice".to_string(), "rust".to_string(), "x".to_string()],
+            ..Default::default()
+        };
+        let dir_counts: HashMap<String, usize> = [
+            ("components", 12),
+            ("commands", 12),
+            ("stores", 3),
+            ("src", 50),
+            ("node_modules", 90),
+            ("sidecar_runtime", 2),
+            ("has space", 40),
+        ]
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+
+        let keywords = build_keywords(
+            &manifest,
+            &["rust", "typescript"],
+            &["svelte".to_string(), "tauri".to_string()],
+            &dir_counts,
+        );
+        assert_eq!(
+            keywords,
+            vec![
+                "voice",
+                "rust",
+                "typescript",
+                "svelte",
+                "tauri",
+                "commands",
+                "components",
+                "stores",
+                "sidecar-runtime",
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_are_capped() {
+        let dir_counts: HashMap<String, usize> = (0..50).map(|i| (format!("area{:02}", i), 1)).collect();
+        let keywords = build_keywords(&ManifestInfo::default(), &["rust"], &[], &dir_counts);
+
+        assert_eq!(keywords.len(), MAX_KEYWORDS);
+        assert_eq!(keywords[0], "rust");
+        assert_eq!(keywords[1], "area00");
+    }
+}
@@ -0,0 +1,312 @@
+//! Conversation history helpers for restoring persisted SDK sessions
+//!
+//! Restored sessions replay their history to the sidecar as a prompt prefix. These helpers
+//! split the history into turns, estimate its token cost and format older turns for
+//! summarisation.
+
+use serde::Serialize;
+
+use crate::llm::ConversationSummary;
+use crate::session_persistence::PersistedSdkMessage;
+use crate::sidecar::HistoryMessage;
+
+/// The sidecar truncates replayed tool results to this many characters
+const SIDECAR_TOOL_OUTPUT_LIMIT: usize = 500;
+
+/// Tool results are cut harder when sent for summarisation
+const SUMMARY_TOOL_OUTPUT_LIMIT: usize = 200;
+
+/// Expected size of a compacted summary message
+const SUMMARY_TOKEN_ESTIMATE: usize = 400;
+
+/// Token estimate shown before a restored session is resumed
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryTokenEstimate {
+    /// Tokens when the full history is replayed
+    pub full_tokens: usize,
+    /// Tokens when older turns are replaced with a summary
+    pub compacted_tokens: usize,
+    /// Number of turns (user prompts) in the history
+    pub total_turns: usize,
+    /// Turns that would be summarised
+    pub older_turns: usize,
+}
+
+/// History to replay for a restored session, with the summary that replaced older turns
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactedHistory {
+    pub messages: Vec<HistoryMessage>,
+    pub summary: Option<ConversationSummary>,
+    pub estimate: HistoryTokenEstimate,
+}
+
+/// Rough token estimate (~4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}...[truncated]", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+/// One history message as text, with tool output cut to `max_tool_output` characters
+fn format_message(msg: &HistoryMessage, max_tool_output: usize) -> String {
+    match msg {
+        HistoryMessage::User { content } => format!("[User]: {}", content),
+        HistoryMessage::Assistant { content } => format!("[Assistant]: {}", content),
+        HistoryMessage::ToolUse { tool, input } => {
+            format!("[Assistant used tool \"{}\"]: {}", tool, input)
+        }
+        HistoryMessage::ToolResult { tool, output } => format!(
+            "[Tool \"{}\" result]: {}",
+            tool,
+            truncate_chars(output, max_tool_output)
+        ),
+        HistoryMessage::Summary { content } => {
+            format!("[Summary of earlier conversation]: {}", content)
+        }
+    }
+}
+
+/// History to replay for persisted session messages. Mirrors `convertToHistoryMessages` in
+/// the frontend: prompts, assistant text and tool calls are kept, UI-only messages dropped.
+pub fn history_from_persisted(messages: &[PersistedSdkMessage]) -> Vec<HistoryMessage> {
+    let non_empty = |s: &Option<String>| s.as_ref().filter(|s| !s.is_empty()).cloned();
+    messages
+        .iter()
+        .filter_map(|msg| match msg.msg_type.as_str() {
+            "user" => non_empty(&msg.content).map(|content| HistoryMessage::User { content }),
+            "text" => non_empty(&msg.content).map(|content| HistoryMessage::Assistant { content }),
+            "tool_start" => match (&msg.tool, &msg.input) {
+                (Some(tool), Some(input)) if !input.is_null() => Some(HistoryMessage::ToolUse {
+                    tool: tool.clone(),
+                    input: input.clone(),
+                }),
+                _ => None,
+            },
+            "tool_result" => match (&msg.tool, non_empty(&msg.output)) {
+                (Some(tool), Some(output)) => Some(HistoryMessage::ToolResult {
+                    tool: tool.clone(),
+                    output,
+                }),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Estimated tokens the sidecar sends when replaying these messages
+pub fn estimate_history_tokens(messages: &[HistoryMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| estimate_tokens(&format_message(m, SIDECAR_TOOL_OUTPUT_LIMIT)) + 1)
+        .sum()
+}
+
+/// Index of the message that starts the `recent_turns`-th last turn (a turn starts at a
+/// user message). Everything before it is considered older history.
+fn recent_turns_start(messages: &[HistoryMessage], recent_turns: usize) -> usize {
+    if recent_turns == 0 {
+        return messages.len();
+    }
+
+    let turn_starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| matches!(m, HistoryMessage::User { .. }))
+        .map(|(i, _)| i)
+        .collect();
+    if turn_starts.len() <= recent_turns {
+        return 0;
+    }
+    turn_starts[turn_starts.len() - recent_turns]
+}
+
+/// Split history into (older, recent) where `recent` holds the last `recent_turns` turns
+pub fn split_recent_turns(
+    messages: &[HistoryMessage],
+    recent_turns: usize,
+) -> (&[HistoryMessage], &[HistoryMessage]) {
+    messages.split_at(recent_turns_start(messages, recent_turns))
+}
+
+/// Transcript of older messages for the summarisation prompt
+pub fn format_for_summary(messages: &[HistoryMessage]) -> String {
+    messages
+        .iter()
+        .map(|m| format_message(m, SUMMARY_TOOL_OUTPUT_LIMIT))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Compare replaying the full history with replaying a summary plus the recent turns
+pub fn estimate_restore(messages: &[HistoryMessage], recent_turns: usize) -> HistoryTokenEstimate {
+    let (older, recent) = split_recent_turns(messages, recent_turns);
+    let count_turns = |msgs: &[HistoryMessage]| {
+        msgs.iter()
+            .filter(|m| matches!(m, HistoryMessage::User { .. }))
+            .count()
+    };
+
+    let full_tokens = estimate_history_tokens(messages);
+    let compacted_tokens = if older.is_empty() {
+        full_tokens
+    } else {
+        estimate_history_tokens(recent) + SUMMARY_TOKEN_ESTIMATE
+    };
+
+    HistoryTokenEstimate {
+        full_tokens,
+        compacted_tokens,
+        total_turns: count_turns(messages),
+        older_turns: count_turns(older),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(content: &str) -> HistoryMessage {
+        HistoryMessage::User {
+            content: content.to_string(),
+        }
+    }
+
+    fn assistant(content: &str) -> HistoryMessage {
+        HistoryMessage::Assistant {
+            content: content.to_string(),
+        }
+    }
+
+    fn tool_result(output: &str) -> HistoryMessage {
+        HistoryMessage::ToolResult {
+            tool: "Bash".to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    /// Three turns: prompt, answer and (for the second turn) a tool call
+    fn three_turns() -> Vec<HistoryMessage> {
+        vec![
+            user("first"),
+            assistant("one"),
+            user("second"),
+            HistoryMessage::ToolUse {
+                tool: "Bash".to_string(),
+                input: json!({ "command": "ls" }),
+            },
+            tool_result("src"),
+            assistant("two"),
+            user("third"),
+            assistant("three"),
+        ]
+    }
+
+    fn persisted(value: serde_json::Value) -> PersistedSdkMessage {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn recent_turns_split_at_a_user_message() {
+        let history = three_turns();
+
+        let (older, recent) = split_recent_turns(&history, 2);
+        assert_eq!((older.len(), recent.len()), (2, 6));
+        assert!(matches!(&recent[0], HistoryMessage::User { content } if content == "second"));
+
+        let (older, recent) = split_recent_turns(&history, 1);
+        assert_eq!((older.len(), recent.len()), (6, 2));
+        assert!(matches!(&recent[0], HistoryMessage::User { content } if content == "third"));
+
+        let (older, recent) = split_recent_turns(&history, 0);
+        assert_eq!((older.len(), recent.len()), (8, 0));
+    }
+
+    #[test]
+    fn more_recent_turns_than_history_keeps_everything() {
+        let history = three_turns();
+        for recent_turns in [3, 10] {
+            let (older, recent) = split_recent_turns(&history, recent_turns);
+            assert!(older.is_empty());
+            assert_eq!(recent.len(), history.len());
+        }
+
+        let estimate = estimate_restore(&history, 10);
+        assert_eq!(estimate.total_turns, 3);
+        assert_eq!(estimate.older_turns, 0);
+        assert_eq!(estimate.compacted_tokens, estimate.full_tokens);
+    }
+
+    #[test]
+    fn empty_history() {
+        let (older, recent) = split_recent_turns(&[], 2);
+        assert!(older.is_empty() && recent.is_empty());
+
+        let estimate = estimate_restore(&[], 2);
+        assert_eq!(
+            (
+                estimate.full_tokens,
+                estimate.compacted_tokens,
+                estimate.total_turns,
+                estimate.older_turns
+            ),
+            (0, 0, 0, 0)
+        );
+        assert!(history_from_persisted(&[]).is_empty());
+    }
+
+    #[test]
+    fn compacted_estimate_replaces_older_turns_with_a_summary() {
+        let mut history = three_turns();
+        history.insert(1, tool_result(&"x".repeat(4000)));
+
+        let estimate = estimate_restore(&history, 1);
+        let (_, recent) = split_recent_turns(&history, 1);
+        assert_eq!(estimate.total_turns, 3);
+        assert_eq!(estimate.older_turns, 2);
+        assert_eq!(estimate.full_tokens, estimate_history_tokens(&history));
+        assert_eq!(
+            estimate.compacted_tokens,
+            estimate_history_tokens(recent) + SUMMARY_TOKEN_ESTIMATE
+        );
+        // The replayed tool output is truncated before it's counted
+        assert!(estimate.full_tokens < 4000 / 4);
+    }
+
+    #[test]
+    fn persisted_messages_keep_prompts_text_and_tools() {
+        let messages = [
+            json!({ "type": "user", "content": "fix the build", "timestamp": 1 }),
+            json!({ "type": "user", "content": "", "timestamp": 2 }),
+            json!({ "type": "thinking", "content": "hmm", "timestamp": 3 }),
+            json!({ "type": "tool_start", "tool": "Bash", "input": { "command": "cargo build" }, "timestamp": 4 }),
+            json!({ "type": "tool_start", "tool": "Bash", "input": null, "timestamp": 5 }),
+            json!({ "type": "tool_result", "tool": "Bash", "output": "ok", "timestamp": 6 }),
+            json!({ "type": "tool_result", "tool": "Bash", "output": "", "timestamp": 7 }),
+            json!({ "type": "text", "content": "Fixed.", "timestamp": 8 }),
+            json!({ "type": "done", "timestamp": 9 }),
+        ]
+        .map(persisted);
+
+        let history = history_from_persisted(&messages);
+        let types: Vec<String> = history
+            .iter()
+            .map(|m| {
+                serde_json::to_value(m).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(types, ["user", "tool_use", "tool_result", "assistant"]);
+        assert!(
+            matches!(&history[1], HistoryMessage::ToolUse { input, .. } if input["command"] == "cargo build")
+        );
+    }
+}
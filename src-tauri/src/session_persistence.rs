@@ -3,6 +3,9 @@ use std::fs;
 use std::path::PathBuf;
 
 use crate::config::AppConfig;
+use crate::prompt_queue::QueuedPrompt;
+use crate::sidecar::PlanningQuestion;
+use crate::subagent_transcript::SubagentSummary;
 
 /// Represents a persisted image content block
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +35,9 @@ pub struct PersistedSdkMessage {
     pub agent_type: Option<String>,
     /// Subagent transcript path
     pub transcript_path: Option<String>,
+    /// What the subagent did, read from its transcript (subagent-stop messages)
+    #[serde(default)]
+    pub subagent_summary: Option<SubagentSummary>,
     pub timestamp: u64,
 }
 
@@ -77,6 +83,8 @@ pub struct PersistedPendingTranscriptionInfo {
     pub used_dual_source: bool,
     pub model_recommendation: Option<serde_json::Value>,
     pub repo_recommendation: Option<serde_json::Value>,
+    /// Structured task spec generated from the transcript (prompt enhancement)
+    pub task_spec: Option<serde_json::Value>,
     pub recording_started_at: Option<u64>,
     pub recording_duration_ms: Option<u64>,
     pub audio_visualization_history: Option<Vec<Vec<f64>>>,
@@ -91,6 +99,50 @@ pub struct PersistedPendingRepoSelection {
     pub recommendations: Option<Vec<serde_json::Value>>,
 }
 
+/// An answer in the planning wizard (option indices, as the UI keeps them)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedPlanningAnswer {
+    pub question_index: usize,
+    #[serde(default)]
+    pub selected_options: Vec<usize>,
+    pub text_input: Option<String>,
+}
+
+/// Plan mode state, so a session can be resumed mid-wizard
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedPlanMode {
+    #[serde(default)]
+    pub is_active: bool,
+    /// Questions waiting for answers
+    #[serde(default)]
+    pub questions: Vec<PlanningQuestion>,
+    #[serde(default)]
+    pub answers: Vec<PersistedPlanningAnswer>,
+    #[serde(default)]
+    pub current_question_index: usize,
+    pub plan_file_path: Option<String>,
+    pub feature_name: Option<String>,
+    pub plan_summary: Option<String>,
+    #[serde(default)]
+    pub is_complete: bool,
+}
+
+/// The plan an implementation session works through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedImplementingPlan {
+    pub plan_path: String,
+    pub feature_name: String,
+    /// The plan mode session that wrote the plan
+    pub plan_session_id: Option<String>,
+    #[serde(default)]
+    pub checked_items: usize,
+    #[serde(default)]
+    pub total_items: usize,
+}
+
 /// Represents a persisted SDK session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -126,6 +178,69 @@ pub struct PersistedSdkSession {
     pub pending_prompt: Option<String>,
     /// Pending approval prompt text
     pub pending_approval_prompt: Option<String>,
+    /// HEAD commit when the session was created (base for commit message / PR generation)
+    #[serde(default)]
+    pub base_commit: Option<String>,
+    /// Session this one was forked from
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Index of the last parent message copied into the fork
+    #[serde(default)]
+    pub forked_at_message: Option<usize>,
+    /// Prompts waiting for the running query to finish
+    #[serde(default)]
+    pub queued_prompts: Vec<QueuedPrompt>,
+    /// Plan mode state (plan mode sessions only)
+    #[serde(default)]
+    pub plan_mode: Option<PersistedPlanMode>,
+    /// Plan being implemented (implementation sessions only)
+    #[serde(default)]
+    pub implementing_plan: Option<PersistedImplementingPlan>,
+}
+
+impl PersistedSdkSession {
+    /// New idle session with this session's settings and its messages up to and including
+    /// `message_index`
+    pub fn fork(&self, id: String, message_index: usize, now: u64) -> Result<Self, String> {
+        if message_index >= self.messages.len() {
+            return Err(format!(
+                "Message {} is out of range; session {} has {} messages",
+                message_index,
+                self.id,
+                self.messages.len()
+            ));
+        }
+        Ok(Self {
+            id,
+            cwd: self.cwd.clone(),
+            model: self.model.clone(),
+            auto_model_requested: self.auto_model_requested,
+            thinking_level: self.thinking_level.clone(),
+            messages: self.messages[..=message_index].to_vec(),
+            status: "idle".to_string(),
+            created_at: now,
+            started_at: None,
+            accumulated_duration_ms: 0,
+            usage: None,
+            unread: false,
+            ai_metadata: self.ai_metadata.as_ref().map(|meta| PersistedSessionAiMetadata {
+                name: meta.name.as_ref().map(|name| format!("{} (fork)", name)),
+                summary: None,
+                category: meta.category.clone(),
+                needs_interaction: false,
+            }),
+            pending_transcription: None,
+            pending_repo_selection: None,
+            pending_prompt: None,
+            pending_approval_prompt: None,
+            base_commit: self.base_commit.clone(),
+            parent_id: Some(self.id.clone()),
+            forked_at_message: Some(message_index),
+            queued_prompts: Vec::new(),
+            plan_mode: None,
+            implementing_plan: None,
+        })
+    }
 }
 
 /// Represents a persisted terminal session (PTY)
@@ -211,3 +326,73 @@ impl PersistedSessions {
         self.active_terminal_session_id = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn session(message_count: usize) -> PersistedSdkSession {
+        let messages: Vec<serde_json::Value> = (0..message_count)
+            .map(|i| json!({ "type": "text", "content": format!("message {}", i), "timestamp": i }))
+            .collect();
+        serde_json::from_value(json!({
+            "id": "parent",
+            "cwd": "/repo",
+            "model": "claude-opus-4-1",
+            "thinkingLevel": "on",
+            "messages": messages,
+            "status": "done",
+            "createdAt": 1,
+            "accumulatedDurationMs": 5000,
+            "unread": true,
+            "aiMetadata": { "name": "Fix login", "summary": "Fixed it", "category": "bugfix", "needsInteraction": true },
+            "baseCommit": "abc1234",
+            "queuedPrompts": [{ "id": "q1", "prompt": "next", "queuedAt": 2 }],
+            "forkedAtMessage": 7,
+            "parentId": "grandparent"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fork_copies_messages_up_to_the_index() {
+        let parent = session(4);
+        let fork = parent.fork("child".to_string(), 2, 100).unwrap();
+
+        assert_eq!(fork.id, "child");
+        assert_eq!(fork.parent_id.as_deref(), Some("parent"));
+        assert_eq!(fork.forked_at_message, Some(2));
+        let contents: Vec<_> = fork.messages.iter().map(|m| m.content.as_deref().unwrap()).collect();
+        assert_eq!(contents, ["message 0", "message 1", "message 2"]);
+    }
+
+    #[test]
+    fn fork_keeps_settings_and_resets_state() {
+        let parent = session(2);
+        let fork = parent.fork("child".to_string(), 1, 100).unwrap();
+
+        assert_eq!(fork.cwd, "/repo");
+        assert_eq!(fork.model, "claude-opus-4-1");
+        assert_eq!(fork.thinking_level.as_deref(), Some("on"));
+        assert_eq!(fork.base_commit.as_deref(), Some("abc1234"));
+        assert_eq!(fork.status, "idle");
+        assert_eq!(fork.created_at, 100);
+        assert_eq!(fork.accumulated_duration_ms, 0);
+        assert!(!fork.unread);
+        assert!(fork.queued_prompts.is_empty());
+
+        let meta = fork.ai_metadata.unwrap();
+        assert_eq!(meta.name.as_deref(), Some("Fix login (fork)"));
+        assert_eq!(meta.summary, None);
+        assert!(!meta.needs_interaction);
+    }
+
+    #[test]
+    fn fork_rejects_out_of_range_indices() {
+        let parent = session(3);
+        assert!(parent.fork("child".to_string(), 2, 100).is_ok());
+        assert!(parent.fork("child".to_string(), 3, 100).is_err());
+        assert!(session(0).fork("child".to_string(), 0, 100).is_err());
+    }
+}
@@ -1,15 +1,31 @@
-use crate::config::McpServerConfig;
+use crate::budget::{self, BudgetExceeded, ResponseTokens, SessionSpend};
+use crate::config::{AppConfig, McpServerConfig};
+use crate::permissions::{self, PermissionDecision, RuleVerdict};
+use crate::planning::{self, AnsweredPlanningQuestion};
+use crate::prompt_queue::{PromptQueue, QueuedPrompt};
+use crate::sidecar_journal::{read_journal, Direction, SidecarJournal};
+use crate::sidecar_runtime;
+use crate::sidecar_stream::{StreamCoalescer, DEFAULT_FLUSH_INTERVAL_MS};
+use crate::subagent_transcript;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
 use std::process::{Child, Command, Stdio};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
+#[cfg(test)]
+mod tests;
+
 /// Image data for multimodal prompts
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageData {
@@ -31,6 +47,8 @@ pub enum HistoryMessage {
     Assistant { content: String },
     ToolUse { tool: String, input: serde_json::Value },
     ToolResult { tool: String, output: String },
+    /// Compacted summary of older turns (replaces them when restoring long sessions)
+    Summary { content: String },
 }
 
 #[derive(Debug, Serialize)]
@@ -77,14 +95,84 @@ pub enum OutboundMessage {
         repo_path: String,
         repo_name: String,
     },
+    /// Answer to a `PermissionRequest`
+    PermissionResponse {
+        id: String,
+        #[serde(rename = "toolUseId")]
+        tool_use_id: String,
+        decision: PermissionDecision,
+        /// Replaces the tool input when allowing
+        #[serde(rename = "updatedInput", skip_serializing_if = "Option::is_none")]
+        updated_input: Option<serde_json::Value>,
+        /// Reason shown to Claude when denying
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+    /// Answers to `PlanningQuestions`; the sidecar sends them as the next user turn
+    PlanningAnswers {
+        id: String,
+        answers: Vec<AnsweredPlanningQuestion>,
+    },
+}
+
+impl OutboundMessage {
+    /// Session (or request) id the message targets
+    pub fn session_id(&self) -> &str {
+        match self {
+            OutboundMessage::Create { id, .. }
+            | OutboundMessage::Query { id, .. }
+            | OutboundMessage::Stop { id }
+            | OutboundMessage::UpdateModel { id, .. }
+            | OutboundMessage::UpdateThinking { id, .. }
+            | OutboundMessage::Close { id }
+            | OutboundMessage::GenerateRepoDescription { id, .. }
+            | OutboundMessage::PermissionResponse { id, .. }
+            | OutboundMessage::PlanningAnswers { id, .. } => id,
+        }
+    }
+
+    /// Wire name of the message type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboundMessage::Create { .. } => "create",
+            OutboundMessage::Query { .. } => "query",
+            OutboundMessage::Stop { .. } => "stop",
+            OutboundMessage::UpdateModel { .. } => "update_model",
+            OutboundMessage::UpdateThinking { .. } => "update_thinking",
+            OutboundMessage::Close { .. } => "close",
+            OutboundMessage::GenerateRepoDescription { .. } => "generate_repo_description",
+            OutboundMessage::PermissionResponse { .. } => "permission_response",
+            OutboundMessage::PlanningAnswers { .. } => "planning_answers",
+        }
+    }
+}
+
+/// An outbound message as written to the sidecar, tagged with a request id when a reply is awaited
+#[derive(Serialize)]
+struct OutboundEnvelope<'a> {
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+    #[serde(flatten)]
+    message: &'a OutboundMessage,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum InboundMessage {
-    Ready,
+    /// Handshake sent once at startup. Builds predating the handshake send no payload and
+    /// parse with protocol version 0.
+    Ready {
+        #[serde(rename = "protocolVersion", default)]
+        protocol_version: u32,
+        #[serde(rename = "sdkVersion", default)]
+        sdk_version: Option<String>,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
     Created {
         id: String,
+        #[serde(rename = "requestId", default)]
+        request_id: Option<String>,
     },
     Text {
         id: String,
@@ -149,22 +237,34 @@ pub enum InboundMessage {
         cache_read_tokens: u64,
         #[serde(rename = "cacheCreationTokens")]
         cache_creation_tokens: u64,
+        /// API response the message belongs to; each content block of a response reports it
+        #[serde(rename = "messageId", default)]
+        message_id: Option<String>,
     },
     ModelUpdated {
         id: String,
         model: String,
+        #[serde(rename = "requestId", default)]
+        request_id: Option<String>,
     },
     ThinkingUpdated {
         id: String,
         #[serde(rename = "maxThinkingTokens")]
         max_thinking_tokens: u64,
+        #[serde(rename = "requestId", default)]
+        request_id: Option<String>,
     },
     Closed {
         id: String,
+        #[serde(rename = "requestId", default)]
+        request_id: Option<String>,
     },
     Error {
         id: String,
         message: String,
+        /// Set when the error answers a request sent with `send_request`
+        #[serde(rename = "requestId", default)]
+        request_id: Option<String>,
     },
     Debug {
         id: String,
@@ -208,6 +308,44 @@ pub enum InboundMessage {
         id: String,
         error: String,
     },
+    /// The SDK wants to run a tool that needs approval; answered with `PermissionResponse`
+    PermissionRequest {
+        id: String,
+        tool: String,
+        input: serde_json::Value,
+        #[serde(rename = "toolUseId")]
+        tool_use_id: String,
+    },
+}
+
+impl InboundMessage {
+    /// Session (or request) the message belongs to; `None` for the handshake
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            InboundMessage::Ready { .. } => None,
+            InboundMessage::Created { id, .. }
+            | InboundMessage::Text { id, .. }
+            | InboundMessage::ToolStart { id, .. }
+            | InboundMessage::ToolResult { id, .. }
+            | InboundMessage::ThinkingStart { id, .. }
+            | InboundMessage::ThinkingEnd { id, .. }
+            | InboundMessage::Done { id }
+            | InboundMessage::Usage { id, .. }
+            | InboundMessage::ProgressiveUsage { id, .. }
+            | InboundMessage::ModelUpdated { id, .. }
+            | InboundMessage::ThinkingUpdated { id, .. }
+            | InboundMessage::Closed { id, .. }
+            | InboundMessage::Error { id, .. }
+            | InboundMessage::Debug { id, .. }
+            | InboundMessage::SubagentStart { id, .. }
+            | InboundMessage::SubagentStop { id, .. }
+            | InboundMessage::PlanningQuestions { id, .. }
+            | InboundMessage::PlanningComplete { id, .. }
+            | InboundMessage::RepoDescriptionResult { id, .. }
+            | InboundMessage::RepoDescriptionError { id, .. }
+            | InboundMessage::PermissionRequest { id, .. } => Some(id),
+        }
+    }
 }
 
 /// Planning question option
@@ -227,10 +365,131 @@ pub struct PlanningQuestion {
     pub multi_select: bool,
 }
 
+/// Number of sidecar stderr lines kept for diagnostics
+const STDERR_BUFFER_LINES: usize = 200;
+
+/// Consecutive restarts attempted after a crash before giving up
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+
+/// Delay before the first restart; doubles with each consecutive attempt
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How often running queries are checked against the duration limit
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A sidecar that stayed up this long before crashing resets the restart backoff
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// How long `send_request` waits for the sidecar to acknowledge a request
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Protocol version this build speaks. Bump together with PROTOCOL_VERSION in the sidecar's
+/// index.ts whenever messages change incompatibly.
+pub const SIDECAR_PROTOCOL_VERSION: u32 = 1;
+
+/// Capabilities the app relies on; a sidecar lacking any of them is rejected
+const REQUIRED_CAPABILITIES: &[&str] = &["request_ids", "permission_requests"];
+
+/// How long `start` waits for the sidecar's ready handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Unparseable lines are cut to this many characters in `sidecar-protocol-error` events
+const PROTOCOL_ERROR_LINE_LIMIT: usize = 2000;
+
+/// Longest pause kept between messages when replaying a journal in real time
+const REPLAY_MAX_GAP: Duration = Duration::from_secs(2);
+
+/// What the sidecar reported in its ready handshake
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarInfo {
+    pub protocol_version: u32,
+    pub sdk_version: Option<String>,
+    pub capabilities: Vec<String>,
+}
+
+impl SidecarInfo {
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    fn check_compatible(&self) -> Result<(), String> {
+        if self.protocol_version != SIDECAR_PROTOCOL_VERSION {
+            return Err(format!(
+                "Sidecar speaks protocol version {} but the app expects {}. Rebuild the sidecar (npm run build in src-tauri/sidecar).",
+                self.protocol_version, SIDECAR_PROTOCOL_VERSION
+            ));
+        }
+        let missing: Vec<&str> = REQUIRED_CAPABILITIES
+            .iter()
+            .copied()
+            .filter(|c| !self.supports(c))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Sidecar is missing required capabilities: {}",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Requests awaiting a reply, by request id
+type PendingRequests = Mutex<HashMap<String, oneshot::Sender<Result<(), String>>>>;
+
+/// Everything needed to re-create a live session after the sidecar restarts
+#[derive(Debug, Clone)]
+struct LiveSession {
+    cwd: String,
+    model: Option<String>,
+    system_prompt: Option<String>,
+    plan_mode: Option<bool>,
+    mcp_servers: Option<Vec<McpServerConfig>>,
+    max_thinking_tokens: Option<u32>,
+    /// History sent on creation plus every turn seen since
+    history: Vec<HistoryMessage>,
+    /// Whether a query was running (it is lost if the sidecar crashes)
+    querying: bool,
+    /// Permission requests waiting for the user, by tool use id
+    pending_permissions: HashMap<String, PendingPermission>,
+    /// Cost, turns and query time counted against the budget limits
+    spend: SessionSpend,
+    /// Prompts sent while a query was running
+    queue: PromptQueue,
+    /// Planning questions asked since the last answers
+    planning_questions: Vec<PlanningQuestion>,
+}
+
+/// A tool permission request escalated to the UI
+#[derive(Debug, Clone)]
+pub struct PendingPermission {
+    pub tool: String,
+    pub input: serde_json::Value,
+    /// Working directory of the requesting session
+    pub cwd: String,
+}
+
 pub struct SidecarManager {
     process: Arc<Mutex<Option<Child>>>,
     stdin: Arc<Mutex<Option<std::process::ChildStdin>>>,
     started: Arc<Mutex<bool>>,
+    /// Bumped on every start and shutdown so reader threads of old processes can tell they are stale
+    generation: Arc<Mutex<u64>>,
+    started_at: Arc<Mutex<Option<Instant>>>,
+    restart_attempts: Arc<Mutex<u32>>,
+    sessions: Arc<Mutex<HashMap<String, LiveSession>>>,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    pending: Arc<PendingRequests>,
+    next_request_id: AtomicU64,
+    /// Handshake of the running sidecar
+    info: Mutex<Option<SidecarInfo>>,
+    /// Raw traffic journal, when enabled in the system settings
+    journal: Arc<Mutex<Option<SidecarJournal>>>,
+    /// Batches streaming text and progressive usage events
+    stream: Arc<StreamCoalescer>,
 }
 
 impl Default for SidecarManager {
@@ -245,6 +504,16 @@ impl SidecarManager {
             process: Arc::new(Mutex::new(None)),
             stdin: Arc::new(Mutex::new(None)),
             started: Arc::new(Mutex::new(false)),
+            generation: Arc::new(Mutex::new(0)),
+            started_at: Arc::new(Mutex::new(None)),
+            restart_attempts: Arc::new(Mutex::new(0)),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            stderr_tail: Arc::new(Mutex::new(VecDeque::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_request_id: AtomicU64::new(1),
+            info: Mutex::new(None),
+            journal: Arc::new(Mutex::new(None)),
+            stream: Arc::new(StreamCoalescer::new(DEFAULT_FLUSH_INTERVAL_MS)),
         }
     }
 
@@ -257,51 +526,35 @@ impl SidecarManager {
             }
         }
 
-        // Try multiple possible sidecar locations
-        let exe_dir = std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?
-            .parent()
-            .ok_or("Failed to get exe directory")?
-            .to_path_buf();
-
-        let cwd = std::env::current_dir()
-            .map_err(|e| format!("Failed to get cwd: {}", e))?;
-
-        // Get resource directory for bundled release
-        let resource_dir = app.path().resource_dir()
-            .map_err(|e| format!("Failed to get resource dir: {}", e))?;
-
-        // Possible paths (in order of priority):
-        // 1. <resource_dir>/dist/index.js (bundled release - "sidecar/dist/" becomes "dist/")
-        // 2. <resource_dir>/sidecar/dist/index.js (bundled release alternate)
-        // 3. <cwd>/sidecar/dist/index.js (when cwd is src-tauri during dev)
-        // 4. <cwd>/src-tauri/sidecar/dist/index.js (when cwd is project root)
-        // 5. <exe_dir>/sidecar/dist/index.js (fallback)
-        let possible_paths = [
-            resource_dir.join("dist").join("index.js"),
-            resource_dir.join("sidecar").join("dist").join("index.js"),
-            cwd.join("sidecar").join("dist").join("index.js"),
-            cwd.join("src-tauri").join("sidecar").join("dist").join("index.js"),
-            exe_dir.join("sidecar").join("dist").join("index.js"),
-        ];
-
-        let path = possible_paths
-            .iter()
-            .find(|p| {
-                println!("[sidecar] Checking path: {:?} exists={}", p, p.exists());
-                p.exists()
-            })
-            .cloned()
-            .ok_or_else(|| {
-                format!(
-                    "Sidecar not found. Tried:\n{}",
-                    possible_paths
-                        .iter()
-                        .map(|p| format!("  - {:?}", p))
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                )
-            })?;
+        let configured_node = app
+            .try_state::<Mutex<AppConfig>>()
+            .and_then(|config| config.lock().system.node_path.clone());
+        let (node, node_candidates) = sidecar_runtime::discover_node(
+            configured_node.as_deref(),
+            &sidecar_runtime::NodeSearchDirs::from_env(),
+            false,
+        );
+        let node = node.ok_or_else(|| {
+            format!(
+                "No usable Node.js {}+ found. Set the Node.js path in settings. Tried:\n{}",
+                sidecar_runtime::min_node_version(),
+                sidecar_runtime::describe_failures(&node_candidates)
+            )
+        })?;
+        println!("[sidecar] Using node {} at {:?}", node.version, node.path);
+
+        let resource_dir = app.path().resource_dir().ok();
+        let (path, script_candidates) = sidecar_runtime::find_script(resource_dir.as_deref());
+        let path = path.ok_or_else(|| {
+            format!(
+                "Sidecar not found. Tried:\n{}",
+                script_candidates
+                    .iter()
+                    .map(|c| format!("  - {}", c.path))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        })?;
 
         // Convert to string and strip Windows extended path prefix if present
         let path_str = path.to_string_lossy().to_string();
@@ -318,13 +571,24 @@ impl SidecarManager {
 
         println!("[sidecar] Sidecar base directory: {}", sidecar_base);
 
-        let mut cmd = Command::new("node");
+        let mut cmd = Command::new(&node.path);
         cmd.arg(&path_str)
             .current_dir(&sidecar_base)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
+        // The SDK launches its CLI with `node` from PATH, so put the chosen node first
+        if let Some(node_dir) = node.path.parent() {
+            let mut paths = vec![node_dir.to_path_buf()];
+            if let Some(path_var) = std::env::var_os("PATH") {
+                paths.extend(std::env::split_paths(&path_var));
+            }
+            if let Ok(joined) = std::env::join_paths(paths) {
+                cmd.env("PATH", joined);
+            }
+        }
+
         // On Windows, prevent the CMD window from appearing
         #[cfg(windows)]
         {
@@ -349,14 +613,26 @@ impl SidecarManager {
         *self.stdin.lock() = Some(stdin);
         *self.process.lock() = Some(child);
         *self.started.lock() = true;
+        *self.started_at.lock() = Some(Instant::now());
+        let generation = {
+            let mut generation = self.generation.lock();
+            *generation += 1;
+            *generation
+        };
 
-        // Spawn stderr reader thread
+        // Spawn stderr reader thread, keeping the most recent lines for diagnostics
         if let Some(stderr) = stderr {
+            let stderr_tail = self.stderr_tail.clone();
             thread::spawn(move || {
                 let reader = BufReader::new(stderr);
                 for line in reader.lines() {
                     if let Ok(line) = line {
                         eprintln!("[sidecar stderr] {}", line);
+                        let mut tail = stderr_tail.lock();
+                        if tail.len() >= STDERR_BUFFER_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line);
                     }
                 }
             });
@@ -364,41 +640,548 @@ impl SidecarManager {
 
         // Spawn stdout reader thread
         let app_clone = app.clone();
+        let sessions = self.sessions.clone();
+        let pending = self.pending.clone();
+        let journal = self.journal.clone();
+        let stream = self.stream.clone();
+        let (ready_tx, ready_rx) = mpsc::channel();
         thread::spawn(move || {
+            let mut ready_tx = Some(ready_tx);
             let reader = BufReader::new(stdout);
             for line in reader.lines() {
                 if let Ok(line) = line {
+                    Self::journal_line(&journal, Direction::Inbound, &line);
                     match serde_json::from_str::<InboundMessage>(&line) {
                         Ok(msg) => {
-                            Self::handle_message(&app_clone, msg);
-                        }
-                        Err(e) => {
-                            eprintln!("[sidecar] Failed to parse message: {} - {}", e, line);
+                            if let InboundMessage::Ready {
+                                protocol_version,
+                                sdk_version,
+                                capabilities,
+                            } = &msg
+                            {
+                                if let Some(tx) = ready_tx.take() {
+                                    let _ = tx.send(SidecarInfo {
+                                        protocol_version: *protocol_version,
+                                        sdk_version: sdk_version.clone(),
+                                        capabilities: capabilities.clone(),
+                                    });
+                                }
+                            }
+                            Self::record_inbound(&sessions, &msg);
+                            if let InboundMessage::Usage { id, .. }
+                            | InboundMessage::ProgressiveUsage { id, .. } = &msg
+                            {
+                                if let Some(manager) = app_clone.try_state::<Arc<SidecarManager>>() {
+                                    manager.enforce_budget(&app_clone, id);
+                                }
+                            }
+                            if Self::resolve_request(&pending, &msg) {
+                                continue;
+                            }
+                            let done = match &msg {
+                                InboundMessage::Done { id } => Some(id.clone()),
+                                _ => None,
+                            };
+                            Self::handle_message(&app_clone, &stream, msg);
+                            if let Some(id) = done {
+                                if let Some(manager) = app_clone.try_state::<Arc<SidecarManager>>() {
+                                    manager.run_next_queued(&app_clone, &id);
+                                }
+                            }
                         }
+                        Err(e) => Self::report_parse_error(&app_clone, &line, &e),
                     }
                 }
             }
             eprintln!("[sidecar] Reader thread exited");
+            stream.flush_due(&app_clone, true);
+
+            // stdout closes when the process exits. Before the handshake `start` is still
+            // waiting and reports the failure itself.
+            if ready_tx.is_none() {
+                if let Some(manager) = app_clone.try_state::<Arc<SidecarManager>>() {
+                    manager.handle_exit(&app_clone, generation);
+                }
+            }
         });
 
-        Ok(())
+        // Flush the tail of batched streaming updates until this process is replaced
+        let flush_app = app.clone();
+        let stream = self.stream.clone();
+        let current_generation = self.generation.clone();
+        thread::spawn(move || {
+            while *current_generation.lock() == generation {
+                let interval = stream.interval();
+                let interval = if interval.is_zero() {
+                    Duration::from_millis(DEFAULT_FLUSH_INTERVAL_MS)
+                } else {
+                    interval
+                };
+                thread::sleep(interval);
+                stream.flush_due(&flush_app, false);
+            }
+        });
+
+        // Enforce the duration limit of running queries, which no message would trigger
+        let budget_app = app.clone();
+        let current_generation = self.generation.clone();
+        thread::spawn(move || {
+            while *current_generation.lock() == generation {
+                thread::sleep(BUDGET_CHECK_INTERVAL);
+                if let Some(manager) = budget_app.try_state::<Arc<SidecarManager>>() {
+                    let querying: Vec<String> = manager
+                        .sessions
+                        .lock()
+                        .iter()
+                        .filter(|(_, s)| s.querying)
+                        .map(|(id, _)| id.clone())
+                        .collect();
+                    for id in querying {
+                        manager.enforce_budget(&budget_app, &id);
+                    }
+                }
+            }
+        });
+
+        let handshake = match ready_rx.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(info) => info.check_compatible().map(|_| info),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(format!(
+                "Sidecar did not complete its ready handshake within {}s",
+                HANDSHAKE_TIMEOUT.as_secs()
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(format!(
+                "Sidecar exited before it was ready:\n{}",
+                self.stderr_tail().join("\n")
+            )),
+        };
+        match handshake {
+            Ok(info) => {
+                println!(
+                    "[sidecar] Handshake ok: protocol {}, SDK {}, capabilities: {}",
+                    info.protocol_version,
+                    info.sdk_version.as_deref().unwrap_or("unknown"),
+                    info.capabilities.join(", ")
+                );
+                *self.info.lock() = Some(info);
+                Ok(())
+            }
+            Err(message) => {
+                eprintln!("[sidecar] {}", message);
+                self.stop_process();
+                let _ = app.emit(
+                    "sidecar-protocol-error",
+                    serde_json::json!({ "kind": "handshake", "message": message }),
+                );
+                Err(message)
+            }
+        }
+    }
+
+    /// Report a stdout line that is not a known message, usually a sidecar build that does not
+    /// match this app version
+    fn report_parse_error(app: &AppHandle, line: &str, error: &serde_json::Error) {
+        eprintln!("[sidecar] Failed to parse message: {} - {}", error, line);
+        let message_type = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(str::to_string));
+        let _ = app.emit(
+            "sidecar-protocol-error",
+            serde_json::json!({
+                "kind": "parse",
+                "message": error.to_string(),
+                "messageType": message_type,
+                "line": line.chars().take(PROTOCOL_ERROR_LINE_LIMIT).collect::<String>(),
+            }),
+        );
+    }
+
+    /// Handshake of the running sidecar
+    pub fn info(&self) -> Option<SidecarInfo> {
+        self.info.lock().clone()
+    }
+
+    /// Handle the sidecar process exiting. Unless it was shut down or replaced, this is a crash:
+    /// notify the UI, restart with backoff and re-create the live sessions.
+    fn handle_exit(&self, app: &AppHandle, mut generation: u64) {
+        if *self.generation.lock() != generation {
+            return;
+        }
+
+        let exit_code = self
+            .process
+            .lock()
+            .take()
+            .and_then(|mut child| child.wait().ok())
+            .and_then(|status| status.code());
+        *self.stdin.lock() = None;
+        *self.started.lock() = false;
+        *self.info.lock() = None;
+        // Dropping the senders fails every request still waiting for a reply
+        self.pending.lock().clear();
+
+        let uptime = self.started_at.lock().take().map(|t| t.elapsed()).unwrap_or_default();
+        if uptime >= STABLE_UPTIME {
+            *self.restart_attempts.lock() = 0;
+        }
+
+        let interrupted: Vec<String> = self
+            .sessions
+            .lock()
+            .iter_mut()
+            .filter(|(_, session)| session.querying)
+            .map(|(id, session)| {
+                session.querying = false;
+                session.pending_permissions.clear();
+                session.queue.pause();
+                id.clone()
+            })
+            .collect();
+        let session_ids: Vec<String> = self.sessions.lock().keys().cloned().collect();
+
+        eprintln!(
+            "[sidecar] Process exited unexpectedly (code: {:?}) after {:.1}s with {} live sessions",
+            exit_code,
+            uptime.as_secs_f64(),
+            session_ids.len()
+        );
+        let _ = app.emit(
+            "sidecar-crashed",
+            serde_json::json!({
+                "exitCode": exit_code,
+                "stderr": self.stderr_tail(),
+                "sessions": session_ids,
+                "interrupted": interrupted,
+            }),
+        );
+        for id in &interrupted {
+            let _ = app.emit(
+                &format!("sdk-error-{}", id),
+                "The sidecar crashed while this query was running. The session is being restored; send the prompt again to continue.",
+            );
+        }
+
+        loop {
+            let attempt = {
+                let mut attempts = self.restart_attempts.lock();
+                *attempts += 1;
+                *attempts
+            };
+            if attempt > MAX_RESTART_ATTEMPTS {
+                eprintln!(
+                    "[sidecar] Giving up after {} restart attempts",
+                    MAX_RESTART_ATTEMPTS
+                );
+                self.sessions.lock().clear();
+                let _ = app.emit(
+                    "sidecar-restart-failed",
+                    serde_json::json!({ "attempts": MAX_RESTART_ATTEMPTS, "stderr": self.stderr_tail() }),
+                );
+                return;
+            }
+
+            let delay = RESTART_BASE_DELAY
+                .saturating_mul(1 << (attempt - 1))
+                .min(RESTART_MAX_DELAY);
+            println!(
+                "[sidecar] Restarting in {:.1}s (attempt {}/{})",
+                delay.as_secs_f64(),
+                attempt,
+                MAX_RESTART_ATTEMPTS
+            );
+            thread::sleep(delay);
+
+            // Started again or shut down by someone else while we waited
+            if *self.generation.lock() != generation {
+                if self.is_started() {
+                    self.restore_sessions(app);
+                }
+                return;
+            }
+
+            match self.start(app.clone()) {
+                Ok(()) => {
+                    self.restore_sessions(app);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("[sidecar] Restart attempt {} failed: {}", attempt, e);
+                    // The failed attempt stopped its own process
+                    generation = *self.generation.lock();
+                }
+            }
+        }
     }
 
-    fn handle_message(app: &AppHandle, msg: InboundMessage) {
+    /// Re-create every live session in a freshly started sidecar
+    fn restore_sessions(&self, app: &AppHandle) {
+        let sessions: Vec<(String, LiveSession)> = self
+            .sessions
+            .lock()
+            .iter()
+            .map(|(id, session)| (id.clone(), session.clone()))
+            .collect();
+
+        let mut restored = Vec::new();
+        for (id, session) in sessions {
+            let create = OutboundMessage::Create {
+                id: id.clone(),
+                cwd: session.cwd,
+                model: session.model,
+                system_prompt: session.system_prompt,
+                messages: if session.history.is_empty() {
+                    None
+                } else {
+                    Some(session.history)
+                },
+                plan_mode: session.plan_mode,
+                mcp_servers: session.mcp_servers,
+            };
+            if let Err(e) = self.send(create) {
+                eprintln!("[sidecar] Failed to restore session {}: {}", id, e);
+                continue;
+            }
+            if session.max_thinking_tokens.is_some() {
+                let _ = self.send(OutboundMessage::UpdateThinking {
+                    id: id.clone(),
+                    max_thinking_tokens: session.max_thinking_tokens,
+                });
+            }
+            restored.push(id);
+        }
+
+        println!("[sidecar] Restarted and restored {} sessions", restored.len());
+        let _ = app.emit("sidecar-restarted", serde_json::json!({ "sessions": restored }));
+    }
+
+    /// Keep live session state in sync with what the app sends
+    fn record_outbound(&self, msg: &OutboundMessage) {
+        let mut sessions = self.sessions.lock();
         match msg {
-            InboundMessage::Ready => {
-                println!("[sidecar] Ready");
+            OutboundMessage::Create {
+                id,
+                cwd,
+                model,
+                system_prompt,
+                messages,
+                plan_mode,
+                mcp_servers,
+            } => {
+                let max_thinking_tokens = sessions.get(id).and_then(|s| s.max_thinking_tokens);
+                // A session re-created after a crash keeps what it has spent and its queue
+                let spend = sessions.get(id).map(|s| s.spend.clone()).unwrap_or_default();
+                let queue = sessions.get(id).map(|s| s.queue.clone()).unwrap_or_default();
+                sessions.insert(
+                    id.clone(),
+                    LiveSession {
+                        cwd: cwd.clone(),
+                        model: model.clone(),
+                        system_prompt: system_prompt.clone(),
+                        plan_mode: *plan_mode,
+                        mcp_servers: mcp_servers.clone(),
+                        max_thinking_tokens,
+                        history: messages.clone().unwrap_or_default(),
+                        querying: false,
+                        pending_permissions: HashMap::new(),
+                        spend,
+                        queue,
+                        planning_questions: Vec::new(),
+                    },
+                );
+            }
+            OutboundMessage::Query { id, prompt, .. } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.history.push(HistoryMessage::User {
+                        content: prompt.clone(),
+                    });
+                    session.querying = true;
+                    session.spend.start_query();
+                    session.queue.query_started();
+                }
+            }
+            OutboundMessage::UpdateModel { id, model } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.model = Some(model.clone());
+                }
+            }
+            OutboundMessage::UpdateThinking {
+                id,
+                max_thinking_tokens,
+            } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.max_thinking_tokens = *max_thinking_tokens;
+                }
+            }
+            OutboundMessage::Close { id } => {
+                sessions.remove(id);
             }
-            InboundMessage::Created { id } => {
+            OutboundMessage::PermissionResponse { id, tool_use_id, .. } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.pending_permissions.remove(tool_use_id);
+                }
+            }
+            OutboundMessage::PlanningAnswers { id, answers } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.history.push(HistoryMessage::User {
+                        content: planning::answers_text(answers),
+                    });
+                    session.planning_questions.clear();
+                    session.querying = true;
+                    session.spend.start_query();
+                    session.queue.query_started();
+                }
+            }
+            OutboundMessage::Stop { id } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.queue.pause();
+                }
+            }
+            OutboundMessage::GenerateRepoDescription { .. } => {}
+        }
+    }
+
+    /// Keep live session history in sync with what the sidecar reports
+    fn record_inbound(sessions: &Mutex<HashMap<String, LiveSession>>, msg: &InboundMessage) {
+        let mut sessions = sessions.lock();
+        match msg {
+            InboundMessage::Text { id, content } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.history.push(HistoryMessage::Assistant {
+                        content: content.clone(),
+                    });
+                }
+            }
+            InboundMessage::ToolStart { id, tool, input, .. } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.history.push(HistoryMessage::ToolUse {
+                        tool: tool.clone(),
+                        input: input.clone(),
+                    });
+                }
+            }
+            InboundMessage::ToolResult { id, tool, output, .. } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.history.push(HistoryMessage::ToolResult {
+                        tool: tool.clone(),
+                        output: output.clone(),
+                    });
+                }
+            }
+            InboundMessage::Done { id } | InboundMessage::Error { id, .. } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.querying = false;
+                    session.pending_permissions.clear();
+                    session.spend.end_query();
+                    session.queue.query_ended(matches!(msg, InboundMessage::Error { .. }));
+                }
+            }
+            InboundMessage::ProgressiveUsage {
+                id,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                message_id,
+            } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    let tokens = ResponseTokens {
+                        input: *input_tokens,
+                        output: *output_tokens,
+                        cache_read: *cache_read_tokens,
+                        cache_creation: *cache_creation_tokens,
+                    };
+                    session.spend.record_response(message_id.as_deref(), tokens);
+                }
+            }
+            InboundMessage::Usage {
+                id,
+                total_cost_usd,
+                num_turns,
+                ..
+            } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.spend.record_usage(*total_cost_usd, *num_turns);
+                }
+            }
+            InboundMessage::PlanningQuestions { id, questions } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.planning_questions.extend(questions.iter().cloned());
+                }
+            }
+            InboundMessage::PermissionRequest {
+                id,
+                tool,
+                input,
+                tool_use_id,
+            } => {
+                if let Some(session) = sessions.get_mut(id) {
+                    let pending = PendingPermission {
+                        tool: tool.clone(),
+                        input: input.clone(),
+                        cwd: session.cwd.clone(),
+                    };
+                    session.pending_permissions.insert(tool_use_id.clone(), pending);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Complete the pending request a reply belongs to. Returns true if the reply was an error
+    /// that the requester now reports, so no `sdk-error` event should be emitted for it.
+    fn resolve_request(pending: &PendingRequests, msg: &InboundMessage) -> bool {
+        let (request_id, result) = match msg {
+            InboundMessage::Created { request_id, .. }
+            | InboundMessage::ModelUpdated { request_id, .. }
+            | InboundMessage::ThinkingUpdated { request_id, .. }
+            | InboundMessage::Closed { request_id, .. } => (request_id, Ok(())),
+            InboundMessage::Error {
+                request_id,
+                message,
+                ..
+            } => (request_id, Err(message.clone())),
+            _ => return false,
+        };
+        let Some(sender) = request_id.as_ref().and_then(|id| pending.lock().remove(id)) else {
+            return false;
+        };
+        let is_error = result.is_err();
+        // The requester may have timed out and gone away
+        let _ = sender.send(result);
+        is_error
+    }
+
+    /// Most recent sidecar stderr lines, oldest first
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.lock().iter().cloned().collect()
+    }
+
+    fn handle_message(app: &AppHandle, stream: &StreamCoalescer, msg: InboundMessage) {
+        // Anything other than a streaming update first flushes what is buffered for its
+        // session, so the UI sees events in the order the sidecar sent them
+        if !matches!(msg, InboundMessage::Text { .. } | InboundMessage::ProgressiveUsage { .. }) {
+            if let Some(id) = msg.session_id() {
+                stream.flush(app, id);
+            }
+        }
+
+        match msg {
+            InboundMessage::Ready {
+                protocol_version,
+                sdk_version,
+                ..
+            } => {
+                println!(
+                    "[sidecar] Ready (protocol {}, SDK {})",
+                    protocol_version,
+                    sdk_version.as_deref().unwrap_or("unknown")
+                );
+            }
+            InboundMessage::Created { id, .. } => {
                 println!("[sidecar] Emitting sdk-created-{}", id);
                 let _ = app.emit(&format!("sdk-created-{}", id), ());
             }
-            InboundMessage::Text { id, ref content } => {
-                println!("[sidecar] Emitting sdk-text-{} with {} bytes", id, content.len());
-                let result = app.emit(&format!("sdk-text-{}", id), content);
-                if let Err(e) = result {
-                    eprintln!("[sidecar] Failed to emit text event: {}", e);
-                }
+            InboundMessage::Text { id, content } => {
+                stream.push_text(app, &id, content);
             }
             InboundMessage::ToolStart { id, tool, input, tool_use_id } => {
                 let _ = app.emit(
@@ -468,9 +1251,11 @@ impl SidecarManager {
                 output_tokens,
                 cache_read_tokens,
                 cache_creation_tokens,
+                ..
             } => {
-                let _ = app.emit(
-                    &format!("sdk-progressive-usage-{}", id),
+                stream.push_progressive_usage(
+                    app,
+                    &id,
                     serde_json::json!({
                         "inputTokens": input_tokens,
                         "outputTokens": output_tokens,
@@ -479,14 +1264,14 @@ impl SidecarManager {
                     }),
                 );
             }
-            InboundMessage::ModelUpdated { id, model } => {
+            InboundMessage::ModelUpdated { id, model, .. } => {
                 println!("[sidecar] Model updated for {}: {}", id, model);
                 let _ = app.emit(&format!("sdk-model-updated-{}", id), &model);
             }
-            InboundMessage::Closed { id } => {
+            InboundMessage::Closed { id, .. } => {
                 let _ = app.emit(&format!("sdk-closed-{}", id), ());
             }
-            InboundMessage::Error { id, message } => {
+            InboundMessage::Error { id, message, .. } => {
                 let _ = app.emit(&format!("sdk-error-{}", id), &message);
             }
             InboundMessage::Debug { id, message } => {
@@ -525,10 +1310,33 @@ impl SidecarManager {
                         "transcriptPath": transcript_path,
                     }),
                 );
+
+                // Summarise what the subagent did so it can be shown with the parent session.
+                // Transcripts can be large, so they are read off the reader thread.
+                let app = app.clone();
+                let pricing = app
+                    .try_state::<Mutex<AppConfig>>()
+                    .map(|config| config.lock().claude_pricing.clone())
+                    .unwrap_or_default();
+                thread::spawn(move || {
+                    match subagent_transcript::load_transcript(&transcript_path, &pricing) {
+                        Ok(transcript) => {
+                            let _ = app.emit(
+                                &format!("sdk-subagent-summary-{}", id),
+                                serde_json::json!({
+                                    "agentId": agent_id,
+                                    "summary": transcript.summary,
+                                }),
+                            );
+                        }
+                        Err(e) => eprintln!("[sidecar] Subagent {}: {}", agent_id, e),
+                    }
+                });
             }
             InboundMessage::ThinkingUpdated {
                 id,
                 max_thinking_tokens,
+                ..
             } => {
                 println!(
                     "[sidecar] Thinking updated for {}: {} tokens",
@@ -593,27 +1401,398 @@ impl SidecarManager {
                 eprintln!("[sidecar] Repo description error for {}: {}", id, error);
                 let _ = app.emit(&format!("repo-description-error-{}", id), &error);
             }
+            InboundMessage::PermissionRequest {
+                id,
+                tool,
+                input,
+                tool_use_id,
+            } => match app.try_state::<Arc<SidecarManager>>() {
+                Some(manager) => manager.handle_permission_request(app, id, tool, input, tool_use_id),
+                None => eprintln!("[sidecar] No sidecar manager to answer permission request for {}", id),
+            },
+        }
+    }
+
+    /// Answer a tool permission request from the session's repo rules, or ask the UI
+    fn handle_permission_request(
+        &self,
+        app: &AppHandle,
+        id: String,
+        tool: String,
+        input: serde_json::Value,
+        tool_use_id: String,
+    ) {
+        let cwd = self.sessions.lock().get(&id).map(|s| s.cwd.clone());
+        let verdict = match (app.try_state::<Mutex<AppConfig>>(), cwd) {
+            (Some(config), Some(cwd)) => {
+                let cfg = config.lock();
+                if cfg.skip_permissions {
+                    RuleVerdict::Allow("skip permissions".to_string())
+                } else {
+                    cfg.repo_for_path(&cwd)
+                        .map(|repo| permissions::evaluate(&repo.permissions, &tool, &input))
+                        .unwrap_or(RuleVerdict::Ask)
+                }
+            }
+            _ => RuleVerdict::Ask,
+        };
+
+        let (decision, message) = match verdict {
+            RuleVerdict::Allow(rule) => {
+                println!("[sidecar] Allowing {} for {} (rule: {})", tool, id, rule);
+                (PermissionDecision::Allow, None)
+            }
+            RuleVerdict::Deny(rule) => {
+                println!("[sidecar] Denying {} for {} (rule: {})", tool, id, rule);
+                (
+                    PermissionDecision::Deny,
+                    Some(format!("Denied by permission rule {}", rule)),
+                )
+            }
+            RuleVerdict::Ask => {
+                println!("[sidecar] Asking for permission to run {} in {}", tool, id);
+                let _ = app.emit(
+                    &format!("sdk-permission-request-{}", id),
+                    serde_json::json!({ "tool": tool, "input": input, "toolUseId": tool_use_id }),
+                );
+                return;
+            }
+        };
+
+        let response = OutboundMessage::PermissionResponse {
+            id: id.clone(),
+            tool_use_id,
+            decision,
+            updated_input: None,
+            message,
+        };
+        if let Err(e) = self.send(response) {
+            eprintln!("[sidecar] Failed to answer permission request for {}: {}", id, e);
         }
     }
 
+    /// The budget limit a session has reached, checked against the current config
+    pub fn check_budget(&self, app: &AppHandle, id: &str) -> Option<BudgetExceeded> {
+        let config = app.try_state::<Mutex<AppConfig>>()?;
+        let (cwd, model) = {
+            let sessions = self.sessions.lock();
+            let session = sessions.get(id)?;
+            (session.cwd.clone(), session.model.clone())
+        };
+        let (limits, price) = {
+            let cfg = config.lock();
+            let price = model.as_deref().and_then(|model| cfg.claude_price_for(model)).cloned();
+            (budget::limits_for(&cfg, &cwd), price)
+        };
+        self.sessions.lock().get(id)?.spend.check(&limits, price.as_ref())
+    }
+
+    /// Stop a running query that has reached a budget limit and tell the UI (once per query)
+    fn enforce_budget(&self, app: &AppHandle, id: &str) {
+        let Some(exceeded) = self.check_budget(app, id) else {
+            return;
+        };
+        let first = self
+            .sessions
+            .lock()
+            .get_mut(id)
+            .is_some_and(|s| s.querying && s.spend.mark_stopped());
+        if !first {
+            return;
+        }
+
+        eprintln!("[sidecar] Session {} reached its budget: {}", id, exceeded.describe());
+        if let Err(e) = self.send(OutboundMessage::Stop { id: id.to_string() }) {
+            eprintln!("[sidecar] Failed to stop session {}: {}", id, e);
+        }
+        let _ = app.emit(&format!("sdk-budget-exceeded-{}", id), &exceeded);
+    }
+
+    /// Send a prompt, or queue it while the session is running a query. Returns the queued
+    /// item, or `None` when the prompt was sent right away.
+    pub fn submit_prompt(
+        &self,
+        app: &AppHandle,
+        id: &str,
+        prompt: String,
+        images: Option<Vec<ImageData>>,
+    ) -> Result<Option<QueuedPrompt>, String> {
+        {
+            let mut sessions = self.sessions.lock();
+            if let Some(session) = sessions.get_mut(id).filter(|s| s.querying) {
+                let item = QueuedPrompt::new(prompt, images);
+                session.queue.push(item.clone());
+                let items = session.queue.items();
+                drop(sessions);
+                println!("[sidecar] Queued prompt {} for busy session {}", item.id, id);
+                Self::emit_queue(app, id, &items);
+                return Ok(Some(item));
+            }
+        }
+        self.send(OutboundMessage::Query {
+            id: id.to_string(),
+            prompt,
+            images,
+        })?;
+        Ok(None)
+    }
+
+    /// Send the next queued prompt of an idle session. Returns false if there was none to send.
+    pub fn run_next_queued(&self, app: &AppHandle, id: &str) -> bool {
+        if let Some(exceeded) = self.check_budget(app, id) {
+            println!(
+                "[sidecar] Not running queued prompt for {}: budget reached ({})",
+                id,
+                exceeded.describe()
+            );
+            return false;
+        }
+
+        let (item, items) = {
+            let mut sessions = self.sessions.lock();
+            let Some(session) = sessions.get_mut(id) else {
+                return false;
+            };
+            let Some(item) = session.queue.take_next(session.querying) else {
+                return false;
+            };
+            (item, session.queue.items())
+        };
+
+        let query = OutboundMessage::Query {
+            id: id.to_string(),
+            prompt: item.prompt.clone(),
+            images: item.images.clone(),
+        };
+        if let Err(e) = self.send(query) {
+            eprintln!("[sidecar] Failed to send queued prompt for {}: {}", id, e);
+            if let Some(session) = self.sessions.lock().get_mut(id) {
+                session.queue.push_front(item);
+            }
+            return false;
+        }
+
+        Self::emit_queue(app, id, &items);
+        let _ = app.emit(&format!("sdk-queue-dispatched-{}", id), &item);
+        true
+    }
+
+    /// Prompts queued for a live session
+    pub fn prompt_queue(&self, id: &str) -> Result<Vec<QueuedPrompt>, String> {
+        self.sessions
+            .lock()
+            .get(id)
+            .map(|session| session.queue.items())
+            .ok_or_else(|| format!("Session {} is not live", id))
+    }
+
+    /// Change the queue of a live session and tell the UI
+    pub fn update_prompt_queue(
+        &self,
+        app: &AppHandle,
+        id: &str,
+        change: impl FnOnce(&mut PromptQueue) -> Result<(), String>,
+    ) -> Result<Vec<QueuedPrompt>, String> {
+        let items = {
+            let mut sessions = self.sessions.lock();
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| format!("Session {} is not live", id))?;
+            change(&mut session.queue)?;
+            session.queue.items()
+        };
+        Self::emit_queue(app, id, &items);
+        Ok(items)
+    }
+
+    fn emit_queue(app: &AppHandle, id: &str, items: &[QueuedPrompt]) {
+        let _ = app.emit(&format!("sdk-queue-{}", id), items);
+    }
+
+    /// Planning questions a live session is waiting on; `None` if the session is not live
+    pub fn planning_questions(&self, id: &str) -> Option<Vec<PlanningQuestion>> {
+        self.sessions.lock().get(id).map(|s| s.planning_questions.clone())
+    }
+
+    pub fn is_querying(&self, id: &str) -> bool {
+        self.sessions.lock().get(id).is_some_and(|s| s.querying)
+    }
+
+    /// MCP servers a live session was created with
+    pub fn session_mcp_servers(&self, id: &str) -> Option<Vec<McpServerConfig>> {
+        self.sessions.lock().get(id).and_then(|s| s.mcp_servers.clone())
+    }
+
+    /// A permission request of the session that is waiting for the user
+    pub fn pending_permission(&self, id: &str, tool_use_id: &str) -> Option<PendingPermission> {
+        self.sessions
+            .lock()
+            .get(id)
+            .and_then(|session| session.pending_permissions.get(tool_use_id).cloned())
+    }
+
+    /// Send a message without waiting for the sidecar to act on it
     pub fn send(&self, msg: OutboundMessage) -> Result<(), String> {
+        self.write(&msg, None)
+    }
+
+    /// Send a message and wait until the sidecar acknowledges it (`created`, `model_updated`,
+    /// `thinking_updated` or `closed`) or reports an error for it
+    pub async fn send_request(&self, msg: OutboundMessage, timeout: Duration) -> Result<(), String> {
+        let request_id = format!("req-{}", self.next_request_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().insert(request_id.clone(), sender);
+
+        if let Err(e) = self.write(&msg, Some(&request_id)) {
+            self.pending.lock().remove(&request_id);
+            return Err(e);
+        }
+
+        let result = match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(format!(
+                "Sidecar exited before answering {} for {}",
+                msg.kind(),
+                msg.session_id()
+            )),
+            Err(_) => {
+                self.pending.lock().remove(&request_id);
+                Err(format!(
+                    "Sidecar did not answer {} for {} within {}s",
+                    msg.kind(),
+                    msg.session_id(),
+                    timeout.as_secs()
+                ))
+            }
+        };
+
+        // A session the sidecar failed to create must not be restored after a crash
+        if result.is_err() {
+            if let OutboundMessage::Create { id, .. } = &msg {
+                self.sessions.lock().remove(id);
+            }
+        }
+        result
+    }
+
+    fn write(&self, msg: &OutboundMessage, request_id: Option<&str>) -> Result<(), String> {
         let mut stdin = self.stdin.lock();
         if let Some(ref mut stdin) = *stdin {
-            let json =
-                serde_json::to_string(&msg).map_err(|e| format!("Serialize error: {}", e))?;
+            let envelope = OutboundEnvelope {
+                request_id,
+                message: msg,
+            };
+            let json = serde_json::to_string(&envelope)
+                .map_err(|e| format!("Serialize error: {}", e))?;
+            Self::journal_line(&self.journal, Direction::Outbound, &json);
             writeln!(stdin, "{}", json).map_err(|e| format!("Write error: {}", e))?;
             stdin.flush().map_err(|e| format!("Flush error: {}", e))?;
+            self.record_outbound(msg);
             Ok(())
         } else {
             Err("Sidecar not started".to_string())
         }
     }
 
+    /// Interval streaming text and usage updates are batched over; 0 emits every update
+    pub fn set_stream_interval_ms(&self, interval_ms: u64) {
+        self.stream.set_interval_ms(interval_ms);
+    }
+
+    /// Start or stop recording raw traffic to the journal in the config directory
+    pub fn set_journal_enabled(&self, enabled: bool) {
+        let mut journal = self.journal.lock();
+        if !enabled {
+            *journal = None;
+            return;
+        }
+        if journal.is_none() {
+            match SidecarJournal::open(SidecarJournal::default_dir()) {
+                Ok(opened) => {
+                    println!("[sidecar] Journaling traffic to {:?}", opened.current_path());
+                    *journal = Some(opened);
+                }
+                Err(e) => eprintln!("[sidecar] Failed to open journal: {}", e),
+            }
+        }
+    }
+
+    fn journal_line(journal: &Mutex<Option<SidecarJournal>>, direction: Direction, line: &str) {
+        if let Some(journal) = journal.lock().as_mut() {
+            if let Err(e) = journal.record(direction, line) {
+                eprintln!("[sidecar] {}", e);
+            }
+        }
+    }
+
+    /// Feed the inbound lines of a recorded journal through `handle_message` as if the sidecar
+    /// had sent them, to reproduce UI bugs without running the SDK. `session` limits the replay
+    /// to one recorded session and `target_session` re-addresses its messages to another id, so
+    /// they show up in a session that is open in the UI. With `realtime` the recorded pauses are
+    /// kept (capped at `REPLAY_MAX_GAP`). Returns the number of lines replayed.
+    pub fn replay_journal(
+        app: &AppHandle,
+        path: &Path,
+        session: Option<&str>,
+        target_session: Option<&str>,
+        realtime: bool,
+    ) -> Result<usize, String> {
+        if target_session.is_some() && session.is_none() {
+            return Err("Choose the recorded session to replay into the target session".to_string());
+        }
+
+        let entries: Vec<_> = read_journal(path)?
+            .into_iter()
+            .filter(|e| e.direction == Direction::Inbound)
+            .filter(|e| session.is_none() || e.session.as_deref() == session)
+            .collect();
+        println!("[sidecar] Replaying {} inbound lines from {:?}", entries.len(), path);
+
+        let stream = app
+            .try_state::<Arc<SidecarManager>>()
+            .map(|manager| manager.stream.clone())
+            .unwrap_or_else(|| Arc::new(StreamCoalescer::new(DEFAULT_FLUSH_INTERVAL_MS)));
+
+        let mut previous_ts = None;
+        for entry in &entries {
+            if let (true, Some(previous)) = (realtime, previous_ts) {
+                let gap = Duration::from_millis(entry.ts.saturating_sub(previous).max(0) as u64);
+                thread::sleep(gap.min(REPLAY_MAX_GAP));
+            }
+            previous_ts = Some(entry.ts);
+
+            let line = match target_session {
+                Some(target) => match serde_json::from_str::<serde_json::Value>(&entry.line) {
+                    Ok(mut value) => {
+                        value["id"] = serde_json::Value::String(target.to_string());
+                        value.to_string()
+                    }
+                    Err(_) => entry.line.clone(),
+                },
+                None => entry.line.clone(),
+            };
+            match serde_json::from_str::<InboundMessage>(&line) {
+                Ok(msg) => Self::handle_message(app, &stream, msg),
+                Err(e) => Self::report_parse_error(app, &line, &e),
+            }
+        }
+        stream.flush_due(app, true);
+        Ok(entries.len())
+    }
+
     pub fn is_started(&self) -> bool {
         *self.started.lock()
     }
 
     pub fn shutdown(&self) {
+        self.stop_process();
+        self.sessions.lock().clear();
+    }
+
+    /// Kill the process, keeping the live sessions so a later start can restore them
+    fn stop_process(&self) {
+        // Make the exiting process's reader thread ignore the exit
+        *self.generation.lock() += 1;
         let mut process = self.process.lock();
         if let Some(ref mut child) = *process {
             println!("[sidecar] Shutting down sidecar process");
@@ -623,6 +1802,9 @@ impl SidecarManager {
         *process = None;
         *self.stdin.lock() = None;
         *self.started.lock() = false;
+        *self.started_at.lock() = None;
+        *self.info.lock() = None;
+        self.pending.lock().clear();
     }
 }
 
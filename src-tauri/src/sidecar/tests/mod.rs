@@ -0,0 +1,189 @@
+//! Golden-file tests for sidecar message parsing
+//!
+//! Each fixture in `fixtures/` is a sidecar journal (see `sidecar_journal`). The inbound lines
+//! are parsed as `InboundMessage` and rendered one block per line - the `Debug` output for
+//! parsed messages, the serde error otherwise - then compared with the matching `.golden`
+//! file. New fixtures can be taken from a real journal with the journal enabled in settings.
+//!
+//! To regenerate the golden files after an intended protocol change:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test sidecar::tests
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use super::{InboundMessage, OutboundEnvelope, OutboundMessage};
+use crate::sidecar_journal::{read_journal, Direction, JournalEntry, SidecarJournal};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/sidecar/tests/fixtures")
+}
+
+fn updating() -> bool {
+    std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1")
+}
+
+/// Parse every inbound line of a journal fixture and render the results
+fn render(name: &str) -> String {
+    let path = fixtures_dir().join(format!("{}.jsonl", name));
+    let entries = read_journal(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path.display(), e));
+    assert!(!entries.is_empty(), "Fixture {} has no entries", path.display());
+
+    let mut out = String::new();
+    for entry in entries.iter().filter(|e| e.direction == Direction::Inbound) {
+        out.push_str(&format!("> {}\n", entry.line));
+        match serde_json::from_str::<InboundMessage>(&entry.line) {
+            Ok(msg) => out.push_str(&format!("{:#?}\n", msg)),
+            Err(e) => out.push_str(&format!("error: {}\n", e)),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn check_golden(name: &str) {
+    let actual = render(name);
+    let path = fixtures_dir().join(format!("{}.golden", name));
+    if updating() {
+        fs::write(&path, &actual)
+            .unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {} (run with UPDATE_GOLDEN=1 to create it): {}",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(
+        actual, expected,
+        "Parsed output of {}.jsonl differs from {}.golden",
+        name, name
+    );
+}
+
+#[test]
+fn golden_handshake() {
+    check_golden("handshake");
+}
+
+#[test]
+fn golden_session_turn() {
+    check_golden("session_turn");
+}
+
+#[test]
+fn golden_subagents_planning() {
+    check_golden("subagents_planning");
+}
+
+#[test]
+fn golden_malformed() {
+    check_golden("malformed");
+}
+
+#[test]
+fn outbound_envelope_carries_request_id() {
+    let msg = OutboundMessage::Close { id: "a".to_string() };
+    let line = serde_json::to_string(&OutboundEnvelope {
+        request_id: Some("req-1"),
+        message: &msg,
+    })
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(value, json!({ "requestId": "req-1", "type": "close", "id": "a" }));
+
+    let line = serde_json::to_string(&OutboundEnvelope {
+        request_id: None,
+        message: &msg,
+    })
+    .unwrap();
+    assert!(!line.contains("requestId"));
+}
+
+#[test]
+fn journal_records_and_rotates() {
+    let dir = std::env::temp_dir().join(format!("sidecar-journal-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    // Small enough that every couple of entries forces a rotation
+    let mut journal = SidecarJournal::with_limits(dir.clone(), 200, 2).unwrap();
+    for i in 0..10 {
+        let line = json!({ "type": "text", "id": "s1", "content": format!("chunk {}", i) });
+        journal.record(Direction::Inbound, &line.to_string()).unwrap();
+    }
+    journal
+        .record(Direction::Outbound, r#"{"type":"close","id":"s1"}"#)
+        .unwrap();
+
+    let current = read_journal(&journal.current_path()).unwrap();
+    let last = current.last().unwrap();
+    assert_eq!(last.direction, Direction::Outbound);
+    assert_eq!(last.session.as_deref(), Some("s1"));
+
+    assert!(dir.join("journal.1.jsonl").exists());
+    assert!(dir.join("journal.2.jsonl").exists());
+    assert!(!dir.join("journal.3.jsonl").exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn journaled(message: serde_json::Value) -> serde_json::Value {
+    let entry = JournalEntry::new(Direction::Outbound, &message.to_string());
+    serde_json::from_str(&entry.line).unwrap()
+}
+
+#[test]
+fn journal_redacts_mcp_secrets() {
+    let line = journaled(json!({
+        "type": "create",
+        "id": "s1",
+        "mcpServers": [{
+            "name": "github",
+            "headers": { "Authorization": "Bearer ghp_secret" },
+            "env": { "GITHUB_TOKEN": "ghp_secret" },
+            "oauth": { "client_secret": "shh" },
+            "url": "https://example.com/mcp"
+        }]
+    }));
+    let server = &line["mcpServers"][0];
+    assert_eq!(server["headers"], json!({ "Authorization": "[redacted]" }));
+    assert_eq!(server["env"], json!({ "GITHUB_TOKEN": "[redacted]" }));
+    assert_eq!(server["oauth"], json!({ "client_secret": "[redacted]" }));
+    assert_eq!(server["url"], "https://example.com/mcp");
+    assert!(!line.to_string().contains("ghp_") && !line.to_string().contains("shh"));
+}
+
+#[test]
+fn journal_replaces_image_data_with_its_size() {
+    let line = journaled(json!({
+        "type": "send",
+        "id": "s1",
+        "images": [{ "mediaType": "image/png", "base64Data": "aGVsbG8=" }],
+        "content": [{
+            "type": "image",
+            "source": { "type": "base64", "media_type": "image/png", "data": "aGk=" }
+        }]
+    }));
+    assert_eq!(line["images"][0]["base64Data"], "[8 bytes of base64]");
+    assert_eq!(line["images"][0]["mediaType"], "image/png");
+    assert_eq!(line["content"][0]["source"]["data"], "[4 bytes of base64]");
+}
+
+#[test]
+fn journal_keeps_lines_without_secrets_verbatim() {
+    let raw = r#"{"type":"text","id":"s1","content":"hi","env":null}"#;
+    let entry = JournalEntry::new(Direction::Inbound, raw);
+    assert_eq!(entry.line, raw);
+    assert_eq!(entry.session.as_deref(), Some("s1"));
+
+    let entry = JournalEntry::new(Direction::Inbound, "not json");
+    assert_eq!(entry.line, "not json");
+    assert_eq!(entry.session, None);
+}
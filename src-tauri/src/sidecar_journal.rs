@@ -0,0 +1,234 @@
+//! Journal of raw sidecar traffic for debugging
+//!
+//! Every JSONL line written to or read from the sidecar is appended to a journal file with a
+//! timestamp, direction and session id. Files rotate by size. Recorded inbound streams can be
+//! replayed through the normal message handling to reproduce UI bugs without running the SDK.
+//! Secrets (MCP headers, env and OAuth settings) and image data are redacted before writing.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+
+/// The current journal file rotates once it reaches this size
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept besides the current one (journal.1.jsonl is the newest)
+const MAX_ROTATED_FILES: usize = 4;
+
+const CURRENT_FILE: &str = "journal.jsonl";
+
+/// Keys whose values never reach the journal: MCP server headers carry the Authorization
+/// token, and `env` and `oauth` may hold credentials
+const SECRET_KEYS: &[&str] = &["headers", "env", "oauth"];
+
+const REDACTED: &str = "[redacted]";
+
+/// Which way a line crossed the JSONL boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Sidecar to app
+    Inbound,
+    /// App to sidecar
+    Outbound,
+}
+
+/// One journaled line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix time in milliseconds
+    pub ts: i64,
+    pub direction: Direction,
+    /// `id` of the message - the session (or request) it belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// The raw line as sent or received
+    pub line: String,
+}
+
+impl JournalEntry {
+    /// Entry for a raw line, with secrets and image data redacted
+    pub fn new(direction: Direction, line: &str) -> Self {
+        let mut value = serde_json::from_str::<serde_json::Value>(line).ok();
+        let session = value
+            .as_ref()
+            .and_then(|v| v.get("id").and_then(|id| id.as_str()).map(str::to_string));
+        let redacted = value.as_mut().is_some_and(redact);
+        let line = match value {
+            Some(value) if redacted => value.to_string(),
+            _ => line.to_string(),
+        };
+        Self {
+            ts: chrono::Utc::now().timestamp_millis(),
+            direction,
+            session,
+            line,
+        }
+    }
+}
+
+/// Replace secret values and base64 image data in place. Key names (header and env names)
+/// are kept so the journal still shows which ones were set. Returns whether anything was replaced.
+fn redact(value: &mut serde_json::Value) -> bool {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            // Anthropic image blocks: {"type": "base64", "media_type": ..., "data": ...}
+            let is_image_source = map.get("type").and_then(|t| t.as_str()) == Some("base64");
+            let mut changed = false;
+            for (key, v) in map.iter_mut() {
+                if v.is_null() {
+                    continue;
+                }
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    match v {
+                        Value::Object(entries) => {
+                            for entry in entries.values_mut() {
+                                *entry = Value::from(REDACTED);
+                            }
+                        }
+                        _ => *v = Value::from(REDACTED),
+                    }
+                    changed = true;
+                } else if key == "base64Data" || (is_image_source && key == "data") {
+                    if let Value::String(data) = v {
+                        *v = Value::from(format!("[{} bytes of base64]", data.len()));
+                        changed = true;
+                    }
+                } else {
+                    changed |= redact(v);
+                }
+            }
+            changed
+        }
+        Value::Array(items) => {
+            let mut changed = false;
+            for item in items {
+                changed |= redact(item);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+pub struct SidecarJournal {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_rotated_files: usize,
+    writer: Option<BufWriter<File>>,
+    size: u64,
+}
+
+impl SidecarJournal {
+    pub fn default_dir() -> PathBuf {
+        AppConfig::config_dir().join("sidecar-journal")
+    }
+
+    /// Current journal file in the default directory
+    pub fn default_path() -> PathBuf {
+        Self::default_dir().join(CURRENT_FILE)
+    }
+
+    pub fn open(dir: PathBuf) -> Result<Self, String> {
+        Self::with_limits(dir, MAX_FILE_BYTES, MAX_ROTATED_FILES)
+    }
+
+    pub fn with_limits(
+        dir: PathBuf,
+        max_file_bytes: u64,
+        max_rotated_files: usize,
+    ) -> Result<Self, String> {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create journal directory {:?}: {}", dir, e))?;
+        let mut journal = Self {
+            dir,
+            max_file_bytes,
+            max_rotated_files,
+            writer: None,
+            size: 0,
+        };
+        journal.open_current()?;
+        Ok(journal)
+    }
+
+    /// File currently being written
+    pub fn current_path(&self) -> PathBuf {
+        self.dir.join(CURRENT_FILE)
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("journal.{}.jsonl", n))
+    }
+
+    fn open_current(&mut self) -> Result<(), String> {
+        let path = self.current_path();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open journal {:?}: {}", path, e))?;
+        self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        self.writer = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    /// Append a line. Each entry is flushed so the tail survives a crash.
+    pub fn record(&mut self, direction: Direction, line: &str) -> Result<(), String> {
+        let json = serde_json::to_string(&JournalEntry::new(direction, line))
+            .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+        let len = json.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_file_bytes {
+            self.rotate()?;
+        }
+
+        let writer = self.writer.as_mut().ok_or("Journal is not open")?;
+        writeln!(writer, "{}", json).map_err(|e| format!("Failed to write journal: {}", e))?;
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to flush journal: {}", e))?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift journal.N.jsonl to N+1 (dropping the oldest) and start a new current file
+    fn rotate(&mut self) -> Result<(), String> {
+        self.writer = None;
+        let current = self.current_path();
+        if self.max_rotated_files == 0 {
+            let _ = fs::remove_file(&current);
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.max_rotated_files));
+            for n in (1..self.max_rotated_files).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    let _ = fs::rename(&from, self.rotated_path(n + 1));
+                }
+            }
+            fs::rename(&current, self.rotated_path(1))
+                .map_err(|e| format!("Failed to rotate journal: {}", e))?;
+        }
+        self.open_current()
+    }
+}
+
+/// Read a journal file. Lines that are not journal entries (e.g. a line cut short by a crash)
+/// are skipped.
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>, String> {
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open journal {:?}: {}", path, e))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read journal {:?}: {}", path, e))?;
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("[sidecar-journal] Skipping invalid entry in {:?}: {}", path, e),
+        }
+    }
+    Ok(entries)
+}
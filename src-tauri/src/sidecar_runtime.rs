@@ -0,0 +1,521 @@
+//! Locating the Node runtime and the sidecar script
+//!
+//! Apps started from a desktop launcher often do not inherit the shell's PATH, so a node
+//! installed through a version manager is invisible to a plain `Command::new("node")`. Besides
+//! an explicitly configured binary and PATH, the usual nvm, volta and fnm install locations are
+//! searched. Every candidate is probed with `--version` and checked against the minimum version
+//! the Agent SDK supports.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// Oldest Node release the Agent SDK runs on
+pub const MIN_NODE_VERSION: (u32, u32, u32) = (18, 0, 0);
+
+#[cfg(windows)]
+const NODE_BINARY: &str = "node.exe";
+#[cfg(not(windows))]
+const NODE_BINARY: &str = "node";
+
+/// A node binary that was considered, and why it was or was not usable
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCandidate {
+    pub path: String,
+    /// Where the candidate came from: "config", "PATH", "nvm", "volta", "fnm" or "system"
+    pub source: String,
+    pub version: Option<String>,
+    /// Why the candidate was rejected; `None` for a usable binary
+    pub error: Option<String>,
+}
+
+/// A sidecar script location that was considered
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptCandidate {
+    pub path: String,
+    pub exists: bool,
+}
+
+/// Which node and `index.js` the sidecar would start with, and why other candidates failed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarDiagnosis {
+    pub node: Option<String>,
+    pub node_version: Option<String>,
+    pub min_node_version: String,
+    pub node_candidates: Vec<NodeCandidate>,
+    pub script: Option<String>,
+    pub script_candidates: Vec<ScriptCandidate>,
+}
+
+/// Node binary chosen to run the sidecar
+#[derive(Debug, Clone)]
+pub struct NodeRuntime {
+    pub path: PathBuf,
+    pub version: String,
+}
+
+/// Where to look for node besides the configured binary, searched in field order
+#[derive(Debug, Clone, Default)]
+pub struct NodeSearchDirs {
+    /// PATH entries
+    pub path: Vec<PathBuf>,
+    /// volta homes; their `bin/node` shim resolves the active toolchain
+    pub volta: Vec<PathBuf>,
+    /// nvm roots holding one directory per installed version
+    pub nvm: Vec<PathBuf>,
+    /// fnm roots holding `aliases/default` and `node-versions`
+    pub fnm: Vec<PathBuf>,
+    /// Node binaries at fixed install locations
+    pub system: Vec<PathBuf>,
+}
+
+impl NodeSearchDirs {
+    /// The usual locations for this machine, from the environment and home directory
+    pub fn from_env() -> Self {
+        let home = dirs::home_dir();
+        let env_dir = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+        let path = std::env::var_os("PATH")
+            .map(|path_var| std::env::split_paths(&path_var).collect())
+            .unwrap_or_default();
+
+        let volta = env_dir("VOLTA_HOME")
+            .or_else(|| home.as_ref().map(|h| h.join(".volta")))
+            .into_iter()
+            .collect();
+
+        // nvm-windows keeps its versions directly in NVM_HOME
+        let mut nvm = Vec::new();
+        nvm.extend(env_dir("NVM_DIR").map(|d| d.join("versions").join("node")));
+        nvm.extend(home.as_ref().map(|h| h.join(".nvm").join("versions").join("node")));
+        nvm.extend(env_dir("NVM_HOME"));
+
+        let mut fnm = Vec::new();
+        fnm.extend(env_dir("FNM_DIR"));
+        fnm.extend(dirs::data_dir().map(|d| d.join("fnm")));
+        if let Some(home) = &home {
+            fnm.push(home.join(".fnm"));
+            fnm.push(home.join(".local").join("share").join("fnm"));
+        }
+
+        let system: &[&str] = if cfg!(windows) {
+            &[r"C:\Program Files\nodejs\node.exe", r"C:\Program Files (x86)\nodejs\node.exe"]
+        } else {
+            &["/opt/homebrew/bin/node", "/usr/local/bin/node", "/usr/bin/node", "/snap/bin/node"]
+        };
+
+        Self {
+            path,
+            volta,
+            nvm,
+            fnm,
+            system: system.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+/// Find a usable node binary. With `probe_all` every candidate is probed (for diagnostics);
+/// otherwise the search stops at the first usable one.
+pub fn discover_node(
+    configured: Option<&str>,
+    search: &NodeSearchDirs,
+    probe_all: bool,
+) -> (Option<NodeRuntime>, Vec<NodeCandidate>) {
+    let mut found: Option<NodeRuntime> = None;
+    let mut candidates: Vec<NodeCandidate> = Vec::new();
+
+    for (path, source) in node_candidates(configured, search) {
+        let path_str = path.to_string_lossy().to_string();
+        if candidates.iter().any(|c| c.path == path_str) {
+            continue;
+        }
+        if found.is_some() && !probe_all {
+            break;
+        }
+
+        let (version, error) = match probe_node(&path) {
+            Ok(version) => (Some(version), None),
+            Err((version, e)) => (version, Some(e)),
+        };
+        if found.is_none() && error.is_none() {
+            found = Some(NodeRuntime {
+                path: path.clone(),
+                version: version.clone().unwrap_or_default(),
+            });
+        }
+        candidates.push(NodeCandidate {
+            path: path_str,
+            source: source.to_string(),
+            version,
+            error,
+        });
+    }
+
+    (found, candidates)
+}
+
+/// Candidate node binaries in order of preference
+fn node_candidates(configured: Option<&str>, search: &NodeSearchDirs) -> Vec<(PathBuf, &'static str)> {
+    let mut candidates = Vec::new();
+
+    if let Some(configured) = configured.map(str::trim).filter(|p| !p.is_empty()) {
+        candidates.push((PathBuf::from(configured), "config"));
+    }
+
+    for dir in &search.path {
+        let path = dir.join(NODE_BINARY);
+        if path.is_file() {
+            candidates.push((path, "PATH"));
+        }
+    }
+
+    // volta: a shim that resolves the project's or default toolchain
+    for volta_home in &search.volta {
+        candidates.push((volta_home.join("bin").join(NODE_BINARY), "volta"));
+    }
+
+    // nvm (and nvm-windows): one directory per installed version, newest first
+    for dir in &search.nvm {
+        for version_dir in version_dirs(dir) {
+            let bin = if cfg!(windows) {
+                version_dir.join(NODE_BINARY)
+            } else {
+                version_dir.join("bin").join(NODE_BINARY)
+            };
+            candidates.push((bin, "nvm"));
+        }
+    }
+
+    // fnm: the default alias first, then every installed version
+    for dir in &search.fnm {
+        candidates.push((fnm_binary(&dir.join("aliases").join("default")), "fnm"));
+        for version_dir in version_dirs(&dir.join("node-versions")) {
+            candidates.push((fnm_binary(&version_dir.join("installation")), "fnm"));
+        }
+    }
+
+    for path in &search.system {
+        candidates.push((path.clone(), "system"));
+    }
+
+    candidates
+}
+
+fn fnm_binary(installation: &Path) -> PathBuf {
+    if cfg!(windows) {
+        installation.join(NODE_BINARY)
+    } else {
+        installation.join("bin").join(NODE_BINARY)
+    }
+}
+
+/// Subdirectories named after a node version (`v20.11.1`), newest first
+fn version_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<((u32, u32, u32), PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            parse_version(&name).map(|version| (version, entry.path()))
+        })
+        .collect();
+    versions.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    versions.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Parse `v20.11.1` / `20.11.1` into its numeric parts
+fn parse_version(s: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = s.trim().trim_start_matches('v').splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    // Allow suffixes such as "1-nightly"
+    let patch = parts
+        .next()
+        .unwrap_or("0")
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .unwrap_or("0")
+        .parse()
+        .unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+pub fn min_node_version() -> String {
+    let (major, minor, patch) = MIN_NODE_VERSION;
+    format!("{}.{}.{}", major, minor, patch)
+}
+
+/// Run `<path> --version`. On failure returns the version (if one was read) and the reason.
+fn probe_node(path: &Path) -> Result<String, (Option<String>, String)> {
+    if !path.is_file() {
+        return Err((None, "File does not exist".to_string()));
+    }
+
+    let mut cmd = Command::new(path);
+    cmd.arg("--version");
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| (None, format!("Failed to run: {}", e)))?;
+    if !output.status.success() {
+        return Err((
+            None,
+            format!(
+                "`--version` exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    check_version(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+/// Accept `node --version` output if it is at least `MIN_NODE_VERSION`
+fn check_version(version: &str) -> Result<String, (Option<String>, String)> {
+    let version = version.to_string();
+    match parse_version(&version) {
+        Some(parsed) if parsed >= MIN_NODE_VERSION => Ok(version),
+        Some(_) => Err((
+            Some(version.clone()),
+            format!("Version {} is older than the required {}", version, min_node_version()),
+        )),
+        None => Err((None, format!("Unrecognized version output: {:?}", version))),
+    }
+}
+
+/// Possible sidecar script locations, in order of priority:
+/// 1. `<resource_dir>/dist/index.js` (bundled release - "sidecar/dist/" becomes "dist/")
+/// 2. `<resource_dir>/sidecar/dist/index.js` (bundled release alternate)
+/// 3. `<cwd>/sidecar/dist/index.js` (when cwd is src-tauri during dev)
+/// 4. `<cwd>/src-tauri/sidecar/dist/index.js` (when cwd is project root)
+/// 5. `<exe_dir>/sidecar/dist/index.js` (fallback)
+pub fn script_candidates(resource_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(resource_dir) = resource_dir {
+        candidates.push(resource_dir.join("dist").join("index.js"));
+        candidates.push(resource_dir.join("sidecar").join("dist").join("index.js"));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push(cwd.join("sidecar").join("dist").join("index.js"));
+        candidates.push(cwd.join("src-tauri").join("sidecar").join("dist").join("index.js"));
+    }
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(exe_dir.join("sidecar").join("dist").join("index.js"));
+    }
+    candidates
+}
+
+/// First existing sidecar script, along with every location considered
+pub fn find_script(resource_dir: Option<&Path>) -> (Option<PathBuf>, Vec<ScriptCandidate>) {
+    let paths = script_candidates(resource_dir);
+    let found = paths.iter().find(|p| p.exists()).cloned();
+    let candidates = paths
+        .iter()
+        .map(|p| ScriptCandidate {
+            path: p.to_string_lossy().to_string(),
+            exists: p.exists(),
+        })
+        .collect();
+    (found, candidates)
+}
+
+/// Report which node and script the sidecar would start with
+pub fn diagnose(configured_node: Option<&str>, resource_dir: Option<&Path>) -> SidecarDiagnosis {
+    let (node, node_candidates) = discover_node(configured_node, &NodeSearchDirs::from_env(), true);
+    let (script, script_candidates) = find_script(resource_dir);
+    SidecarDiagnosis {
+        node: node.as_ref().map(|n| n.path.to_string_lossy().to_string()),
+        node_version: node.map(|n| n.version),
+        min_node_version: min_node_version(),
+        node_candidates,
+        script: script.map(|p| p.to_string_lossy().to_string()),
+        script_candidates,
+    }
+}
+
+/// Human readable list of rejected candidates for error messages
+pub fn describe_failures(candidates: &[NodeCandidate]) -> String {
+    candidates
+        .iter()
+        .filter_map(|c| {
+            c.error
+                .as_ref()
+                .map(|e| format!("  - {} ({}): {}", c.path, c.source, e))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sidecar-runtime-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A node binary at `bin` that prints `version`
+    fn fake_node(bin: &Path, version: &str) {
+        fs::create_dir_all(bin.parent().unwrap()).unwrap();
+        fs::write(bin, format!("#!/bin/sh\necho {}\n", version)).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(bin, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    fn bin_dir(dir: &Path) -> PathBuf {
+        if cfg!(windows) {
+            dir.to_path_buf()
+        } else {
+            dir.join("bin")
+        }
+    }
+
+    #[test]
+    fn parses_node_versions() {
+        let cases = [
+            ("v18.19.0", Some((18, 19, 0))),
+            ("20.11.1\n", Some((20, 11, 1))),
+            ("v21.2", Some((21, 2, 0))),
+            ("v22", Some((22, 0, 0))),
+            ("v23.0.1-nightly2024", Some((23, 0, 1))),
+            ("v20.1.x", Some((20, 1, 0))),
+            ("", None),
+            ("node", None),
+            ("v18.x", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_version(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_versions_below_the_minimum() {
+        assert_eq!(min_node_version(), "18.0.0");
+        assert_eq!(check_version("v18.0.0").unwrap(), "v18.0.0");
+        assert!(check_version("v22.3.0").is_ok());
+
+        let (version, error) = check_version("v17.9.1").unwrap_err();
+        assert_eq!(version.as_deref(), Some("v17.9.1"));
+        assert!(error.contains("older than the required 18.0.0"), "{}", error);
+
+        let (version, error) = check_version("command not found").unwrap_err();
+        assert_eq!(version, None);
+        assert!(error.starts_with("Unrecognized version output"), "{}", error);
+    }
+
+    #[test]
+    fn candidates_follow_config_path_managers_system_order() {
+        let root = test_dir("order");
+        let path_dir = root.join("usr-bin");
+        fake_node(&path_dir.join(NODE_BINARY), "v20.0.0");
+        let nvm = root.join("nvm");
+        for version in ["v18.19.0", "v20.11.1", "v9.0.0", "not-a-version"] {
+            fs::create_dir_all(nvm.join(version)).unwrap();
+        }
+        let fnm = root.join("fnm");
+        fs::create_dir_all(fnm.join("node-versions").join("v19.1.0")).unwrap();
+
+        let search = NodeSearchDirs {
+            path: vec![root.join("missing"), path_dir.clone()],
+            volta: vec![root.join("volta")],
+            nvm: vec![nvm.clone()],
+            fnm: vec![fnm.clone()],
+            system: vec![PathBuf::from("/usr/bin/node")],
+        };
+        let candidates = node_candidates(Some(" /opt/node/bin/node "), &search);
+
+        let expected = vec![
+            (PathBuf::from("/opt/node/bin/node"), "config"),
+            (path_dir.join(NODE_BINARY), "PATH"),
+            (root.join("volta").join("bin").join(NODE_BINARY), "volta"),
+            (bin_dir(&nvm.join("v20.11.1")).join(NODE_BINARY), "nvm"),
+            (bin_dir(&nvm.join("v18.19.0")).join(NODE_BINARY), "nvm"),
+            (bin_dir(&nvm.join("v9.0.0")).join(NODE_BINARY), "nvm"),
+            (bin_dir(&fnm.join("aliases").join("default")).join(NODE_BINARY), "fnm"),
+            (
+                bin_dir(&fnm.join("node-versions").join("v19.1.0").join("installation")).join(NODE_BINARY),
+                "fnm",
+            ),
+            (PathBuf::from("/usr/bin/node"), "system"),
+        ];
+        assert_eq!(candidates, expected);
+
+        // A blank configured path is ignored
+        assert_eq!(node_candidates(Some("  "), &search)[0].1, "PATH");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discovery_skips_unusable_binaries() {
+        let root = test_dir("discover");
+        let path_dir = root.join("path");
+        fake_node(&path_dir.join(NODE_BINARY), "v16.20.2");
+        let nvm = root.join("nvm");
+        fake_node(&nvm.join("v20.11.1").join("bin").join(NODE_BINARY), "v20.11.1");
+        let system = root.join("system-node");
+        fake_node(&system, "v22.0.0");
+
+        let search = NodeSearchDirs {
+            // Listed twice, probed once
+            path: vec![path_dir.clone(), path_dir.clone()],
+            nvm: vec![nvm.clone()],
+            system: vec![system.clone()],
+            ..Default::default()
+        };
+        let configured = root.join("missing-node");
+
+        let (node, candidates) = discover_node(configured.to_str(), &search, false);
+        let node = node.unwrap();
+        assert_eq!(node.version, "v20.11.1");
+        assert_eq!(node.path, nvm.join("v20.11.1").join("bin").join(NODE_BINARY));
+
+        let summary: Vec<(&str, Option<&str>)> = candidates
+            .iter()
+            .map(|c| (c.source.as_str(), c.version.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("config", None), ("PATH", Some("v16.20.2")), ("nvm", Some("v20.11.1"))]
+        );
+        assert_eq!(candidates[0].error.as_deref(), Some("File does not exist"));
+        assert!(candidates[2].error.is_none());
+
+        // Diagnostics probe every candidate but still pick the first usable one
+        let (node, candidates) = discover_node(configured.to_str(), &search, true);
+        assert_eq!(node.unwrap().version, "v20.11.1");
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[3].source, "system");
+
+        let failures = describe_failures(&candidates);
+        assert_eq!(failures.lines().count(), 2);
+        assert!(failures.contains("(PATH): Version v16.20.2 is older than the required 18.0.0"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,275 @@
+//! Coalescing of high-frequency sidecar events
+//!
+//! Streaming sessions produce a text chunk and a progressive usage update many times per
+//! second. Emitting each as its own Tauri event floods the webview, so they are buffered per
+//! session and emitted in batches on a short interval. Any other event for a session flushes
+//! its buffer first, so text still arrives before the tool call or `done` that followed it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use tauri::{AppHandle, Emitter};
+
+/// Flush interval used when the config does not set one
+pub const DEFAULT_FLUSH_INTERVAL_MS: u64 = 50;
+
+#[derive(Default)]
+struct StreamBuffer {
+    /// Text chunks in arrival order; each one is a separate message in the UI
+    text: Vec<String>,
+    /// Progressive usage is cumulative, so only the latest update is kept
+    progressive_usage: Option<serde_json::Value>,
+    /// When the oldest buffered update arrived
+    since: Option<Instant>,
+}
+
+pub struct StreamCoalescer {
+    /// 0 disables coalescing
+    interval_ms: AtomicU64,
+    buffers: Mutex<HashMap<String, StreamBuffer>>,
+}
+
+impl StreamCoalescer {
+    pub fn new(interval_ms: u64) -> Self {
+        Self {
+            interval_ms: AtomicU64::new(interval_ms),
+            buffers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_interval_ms(&self, interval_ms: u64) {
+        self.interval_ms.store(interval_ms, Ordering::Relaxed);
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn push_text(&self, app: &AppHandle, id: &str, content: String) {
+        self.push(
+            id,
+            |buffer| buffer.text.push(content),
+            |id, buffer| Self::emit(app, id, buffer),
+        );
+    }
+
+    pub fn push_progressive_usage(&self, app: &AppHandle, id: &str, usage: serde_json::Value) {
+        self.push(
+            id,
+            |buffer| buffer.progressive_usage = Some(usage),
+            |id, buffer| Self::emit(app, id, buffer),
+        );
+    }
+
+    /// Emit the buffered updates of one session. Call before emitting any other event for it.
+    pub fn flush(&self, app: &AppHandle, id: &str) {
+        self.flush_with(id, |id, buffer| Self::emit(app, id, buffer));
+    }
+
+    /// Emit every buffer whose oldest update has waited a full interval, or all with `force`
+    pub fn flush_due(&self, app: &AppHandle, force: bool) {
+        self.flush_due_with(force, |id, buffer| Self::emit(app, id, buffer));
+    }
+
+    fn push(
+        &self,
+        id: &str,
+        add: impl FnOnce(&mut StreamBuffer),
+        emit: impl FnOnce(&str, StreamBuffer),
+    ) {
+        let interval = self.interval();
+        let mut buffers = self.buffers.lock();
+        let buffer = buffers.entry(id.to_string()).or_default();
+        add(buffer);
+        let since = *buffer.since.get_or_insert_with(Instant::now);
+        // A steady stream is flushed here; the flush timer only picks up the tail
+        if interval.is_zero() || since.elapsed() >= interval {
+            if let Some(buffer) = buffers.remove(id) {
+                emit(id, buffer);
+            }
+        }
+    }
+
+    fn flush_with(&self, id: &str, emit: impl FnOnce(&str, StreamBuffer)) {
+        // The lock is held while emitting so a concurrent flush cannot reorder events
+        let mut buffers = self.buffers.lock();
+        if let Some(buffer) = buffers.remove(id) {
+            emit(id, buffer);
+        }
+    }
+
+    fn flush_due_with(&self, force: bool, mut emit: impl FnMut(&str, StreamBuffer)) {
+        let interval = self.interval();
+        let mut buffers = self.buffers.lock();
+        let due: Vec<String> = buffers
+            .iter()
+            .filter(|(_, b)| force || b.since.is_none_or(|since| since.elapsed() >= interval))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in due {
+            if let Some(buffer) = buffers.remove(&id) {
+                emit(&id, buffer);
+            }
+        }
+    }
+
+    fn emit(app: &AppHandle, id: &str, buffer: StreamBuffer) {
+        if !buffer.text.is_empty() {
+            if let Err(e) = app.emit(&format!("sdk-text-{}", id), &buffer.text) {
+                eprintln!("[sidecar] Failed to emit text event: {}", e);
+            }
+        }
+        if let Some(usage) = buffer.progressive_usage {
+            let _ = app.emit(&format!("sdk-progressive-usage-{}", id), usage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// One emitted batch: session, text chunks and progressive usage
+    type Batch = (String, Vec<String>, Option<serde_json::Value>);
+
+    fn record(batches: &mut Vec<Batch>) -> impl FnMut(&str, StreamBuffer) + '_ {
+        |id, buffer| batches.push((id.to_string(), buffer.text, buffer.progressive_usage))
+    }
+
+    fn text(stream: &StreamCoalescer, batches: &mut Vec<Batch>, id: &str, content: &str) {
+        stream.push(id, |b| b.text.push(content.to_string()), record(batches));
+    }
+
+    fn usage(stream: &StreamCoalescer, batches: &mut Vec<Batch>, id: &str, output_tokens: u64) {
+        let value = json!({ "outputTokens": output_tokens });
+        stream.push(id, |b| b.progressive_usage = Some(value), record(batches));
+    }
+
+    #[test]
+    fn text_chunks_are_batched_in_order() {
+        let stream = StreamCoalescer::new(60_000);
+        let mut batches = Vec::new();
+        for chunk in ["Hel", "lo", " world"] {
+            text(&stream, &mut batches, "s1", chunk);
+        }
+        assert!(batches.is_empty());
+
+        stream.flush_with("s1", record(&mut batches));
+        assert_eq!(
+            batches,
+            vec![(
+                "s1".to_string(),
+                vec!["Hel".to_string(), "lo".to_string(), " world".to_string()],
+                None
+            )]
+        );
+
+        // Nothing is left to flush
+        stream.flush_with("s1", record(&mut batches));
+        assert_eq!(batches.len(), 1);
+    }
+
+    #[test]
+    fn only_the_latest_progressive_usage_is_kept() {
+        let stream = StreamCoalescer::new(60_000);
+        let mut batches = Vec::new();
+        for output_tokens in [10, 25, 40] {
+            usage(&stream, &mut batches, "s1", output_tokens);
+        }
+        stream.flush_with("s1", record(&mut batches));
+
+        assert_eq!(
+            batches,
+            vec![(
+                "s1".to_string(),
+                vec![],
+                Some(json!({ "outputTokens": 40 }))
+            )]
+        );
+    }
+
+    #[test]
+    fn buffered_updates_flush_before_other_events() {
+        let stream = StreamCoalescer::new(60_000);
+        let mut batches = Vec::new();
+        let mut events: Vec<String> = Vec::new();
+        // What `handle_message` does for any other event: flush its session, then emit it
+        let mut other_event = |batches: &mut Vec<Batch>, event: &str| {
+            stream.flush_with("s1", record(batches));
+            events.extend(
+                batches
+                    .drain(..)
+                    .map(|(_, text, usage)| format!("{:?} {}", text, usage.unwrap())),
+            );
+            events.push(event.to_string());
+        };
+
+        text(&stream, &mut batches, "s1", "Let me check.");
+        usage(&stream, &mut batches, "s1", 5);
+        other_event(&mut batches, "tool_start");
+        other_event(&mut batches, "tool_result");
+        text(&stream, &mut batches, "s1", "Done");
+        usage(&stream, &mut batches, "s1", 9);
+        other_event(&mut batches, "done");
+
+        assert_eq!(
+            events,
+            vec![
+                r#"["Let me check."] {"outputTokens":5}"#,
+                "tool_start",
+                "tool_result",
+                r#"["Done"] {"outputTokens":9}"#,
+                "done",
+            ]
+        );
+    }
+
+    #[test]
+    fn sessions_are_buffered_separately() {
+        let stream = StreamCoalescer::new(60_000);
+        let mut batches = Vec::new();
+        text(&stream, &mut batches, "s1", "one");
+        text(&stream, &mut batches, "s2", "two");
+
+        stream.flush_with("s1", record(&mut batches));
+        assert_eq!(
+            batches,
+            vec![("s1".to_string(), vec!["one".to_string()], None)]
+        );
+
+        // Not due yet, so only a forced flush emits the rest
+        stream.flush_due_with(false, record(&mut batches));
+        assert_eq!(batches.len(), 1);
+        stream.flush_due_with(true, record(&mut batches));
+        assert_eq!(
+            batches[1],
+            ("s2".to_string(), vec!["two".to_string()], None)
+        );
+    }
+
+    #[test]
+    fn zero_interval_and_elapsed_interval_emit_on_push() {
+        let stream = StreamCoalescer::new(0);
+        let mut batches = Vec::new();
+        text(&stream, &mut batches, "s1", "a");
+        text(&stream, &mut batches, "s1", "b");
+        assert_eq!(batches.len(), 2);
+
+        stream.set_interval_ms(20);
+        batches.clear();
+        text(&stream, &mut batches, "s1", "c");
+        std::thread::sleep(Duration::from_millis(30));
+        text(&stream, &mut batches, "s1", "d");
+        assert_eq!(
+            batches,
+            vec![(
+                "s1".to_string(),
+                vec!["c".to_string(), "d".to_string()],
+                None
+            )]
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//! Transcripts of Task subagents
+//!
+//! When a subagent finishes, the sidecar reports the JSONL transcript the SDK wrote for it
+//! (`subagent_stop`). Each line is one entry of the subagent's conversation: its prompt, the
+//! assistant's content blocks (text, thinking, tool uses, with the API usage of the response)
+//! and the tool results. The transcript is parsed into typed messages, and a summary of its
+//! tokens, estimated cost (from the configured Claude prices) and tool calls is attached to
+//! the parent session's `subagent_stop` message.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::ClaudeModelPrice;
+
+/// Tool results longer than this are cut off in the parsed transcript
+const MAX_TOOL_OUTPUT_CHARS: usize = 20_000;
+
+/// One entry of a subagent transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptMessage {
+    /// Text from the user side (the subagent's prompt) or the assistant
+    Text {
+        role: String,
+        text: String,
+        timestamp: Option<u64>,
+    },
+    Thinking {
+        text: String,
+        timestamp: Option<u64>,
+    },
+    ToolUse {
+        #[serde(rename = "toolUseId")]
+        tool_use_id: String,
+        tool: String,
+        input: Value,
+        timestamp: Option<u64>,
+    },
+    ToolResult {
+        #[serde(rename = "toolUseId")]
+        tool_use_id: String,
+        /// Name of the tool, from the matching tool use
+        tool: Option<String>,
+        output: String,
+        #[serde(rename = "isError")]
+        is_error: bool,
+        timestamp: Option<u64>,
+    },
+    /// API usage of one assistant response
+    Usage {
+        model: Option<String>,
+        #[serde(rename = "inputTokens")]
+        input_tokens: u64,
+        #[serde(rename = "outputTokens")]
+        output_tokens: u64,
+        #[serde(rename = "cacheReadTokens")]
+        cache_read_tokens: u64,
+        #[serde(rename = "cacheCreationTokens")]
+        cache_creation_tokens: u64,
+        timestamp: Option<u64>,
+    },
+}
+
+/// Calls of one tool in a transcript
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubagentToolStats {
+    pub calls: u64,
+    /// Results that were reported as errors
+    pub errors: u64,
+}
+
+/// What a subagent did, as attached to the parent session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubagentSummary {
+    /// Assistant responses (API requests)
+    pub turns: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// Estimated from the configured Claude prices; responses of unknown models are not included
+    pub cost_usd: f64,
+    /// Responses whose model has no price (not included in the cost)
+    #[serde(default)]
+    pub unpriced_turns: u64,
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Tool calls by tool name
+    #[serde(default)]
+    pub tools: BTreeMap<String, SubagentToolStats>,
+    /// From the first to the last timestamped entry
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubagentTranscript {
+    pub path: String,
+    pub messages: Vec<TranscriptMessage>,
+    pub summary: SubagentSummary,
+    /// Lines that were not valid JSON (e.g. a line still being written)
+    pub skipped_lines: usize,
+}
+
+pub fn load_transcript(
+    path: &str,
+    pricing: &[ClaudeModelPrice],
+) -> Result<SubagentTranscript, String> {
+    if Path::new(path).extension().and_then(|e| e.to_str()) != Some("jsonl") {
+        return Err(format!("Not a transcript file: {}", path));
+    }
+    let jsonl = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read transcript {}: {}", path, e))?;
+    Ok(parse_transcript(path, &jsonl, pricing))
+}
+
+pub fn parse_transcript(
+    path: &str,
+    jsonl: &str,
+    pricing: &[ClaudeModelPrice],
+) -> SubagentTranscript {
+    let mut messages = Vec::new();
+    let mut skipped_lines = 0;
+    // Each content block of a response is written as its own line with the response's usage,
+    // so usage is only counted once per response id
+    let mut seen_responses: HashSet<String> = HashSet::new();
+    let mut tool_names: BTreeMap<String, String> = BTreeMap::new();
+
+    for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: Value = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(_) => {
+                skipped_lines += 1;
+                continue;
+            }
+        };
+        let timestamp = entry
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_millis() as u64);
+        let Some(message) = entry.get("message") else {
+            continue;
+        };
+
+        match entry.get("type").and_then(Value::as_str) {
+            Some("user") => {
+                parse_user_content(message, timestamp, &tool_names, &mut messages);
+            }
+            Some("assistant") => {
+                parse_assistant_content(message, timestamp, &mut tool_names, &mut messages);
+                let response_id = message.get("id").and_then(Value::as_str);
+                let first_line = response_id.is_none_or(|id| seen_responses.insert(id.to_string()));
+                if first_line {
+                    if let Some(usage) = parse_usage(message, timestamp) {
+                        messages.push(usage);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let summary = summarize(&messages, pricing);
+    SubagentTranscript {
+        path: path.to_string(),
+        messages,
+        summary,
+        skipped_lines,
+    }
+}
+
+fn parse_user_content(
+    message: &Value,
+    timestamp: Option<u64>,
+    tool_names: &BTreeMap<String, String>,
+    messages: &mut Vec<TranscriptMessage>,
+) {
+    let blocks = match message.get("content") {
+        Some(Value::String(text)) => {
+            messages.push(TranscriptMessage::Text {
+                role: "user".to_string(),
+                text: text.clone(),
+                timestamp,
+            });
+            return;
+        }
+        Some(Value::Array(blocks)) => blocks,
+        _ => return,
+    };
+
+    for block in blocks {
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    messages.push(TranscriptMessage::Text {
+                        role: "user".to_string(),
+                        text: text.to_string(),
+                        timestamp,
+                    });
+                }
+            }
+            Some("tool_result") => {
+                let tool_use_id = block
+                    .get("tool_use_id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                messages.push(TranscriptMessage::ToolResult {
+                    tool: tool_names.get(&tool_use_id).cloned(),
+                    tool_use_id,
+                    output: tool_output(block.get("content")),
+                    is_error: block
+                        .get("is_error")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                    timestamp,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_assistant_content(
+    message: &Value,
+    timestamp: Option<u64>,
+    tool_names: &mut BTreeMap<String, String>,
+    messages: &mut Vec<TranscriptMessage>,
+) {
+    let Some(blocks) = message.get("content").and_then(Value::as_array) else {
+        return;
+    };
+
+    for block in blocks {
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    messages.push(TranscriptMessage::Text {
+                        role: "assistant".to_string(),
+                        text: text.to_string(),
+                        timestamp,
+                    });
+                }
+            }
+            Some("thinking") => {
+                if let Some(text) = block.get("thinking").and_then(Value::as_str) {
+                    messages.push(TranscriptMessage::Thinking {
+                        text: text.to_string(),
+                        timestamp,
+                    });
+                }
+            }
+            Some("tool_use") => {
+                let tool_use_id = block
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let tool = block
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown")
+                    .to_string();
+                tool_names.insert(tool_use_id.clone(), tool.clone());
+                messages.push(TranscriptMessage::ToolUse {
+                    tool_use_id,
+                    tool,
+                    input: block.get("input").cloned().unwrap_or(Value::Null),
+                    timestamp,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tool result content is either a string or a list of blocks, of which the text is kept
+fn tool_output(content: Option<&Value>) -> String {
+    let output = match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|block| match block.get("type").and_then(Value::as_str) {
+                Some("text") => block
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                Some(other) => Some(format!("[{}]", other)),
+                None => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+
+    match output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+        Some((cut, _)) => format!("{}\n... (truncated)", &output[..cut]),
+        None => output,
+    }
+}
+
+fn parse_usage(message: &Value, timestamp: Option<u64>) -> Option<TranscriptMessage> {
+    let usage = message.get("usage")?;
+    let tokens = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+    Some(TranscriptMessage::Usage {
+        model: message
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        input_tokens: tokens("input_tokens"),
+        output_tokens: tokens("output_tokens"),
+        cache_read_tokens: tokens("cache_read_input_tokens"),
+        cache_creation_tokens: tokens("cache_creation_input_tokens"),
+        timestamp,
+    })
+}
+
+pub fn summarize(messages: &[TranscriptMessage], pricing: &[ClaudeModelPrice]) -> SubagentSummary {
+    let mut summary = SubagentSummary::default();
+    let mut first_timestamp: Option<u64> = None;
+    let mut last_timestamp: Option<u64> = None;
+
+    for message in messages {
+        let timestamp = match message {
+            TranscriptMessage::Text { timestamp, .. }
+            | TranscriptMessage::Thinking { timestamp, .. }
+            | TranscriptMessage::ToolUse { timestamp, .. }
+            | TranscriptMessage::ToolResult { timestamp, .. }
+            | TranscriptMessage::Usage { timestamp, .. } => *timestamp,
+        };
+        if let Some(timestamp) = timestamp {
+            first_timestamp = Some(first_timestamp.map_or(timestamp, |t| t.min(timestamp)));
+            last_timestamp = Some(last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+        }
+
+        match message {
+            TranscriptMessage::ToolUse { tool, .. } => {
+                summary.tools.entry(tool.clone()).or_default().calls += 1;
+            }
+            TranscriptMessage::ToolResult {
+                tool: Some(tool),
+                is_error: true,
+                ..
+            } => {
+                summary.tools.entry(tool.clone()).or_default().errors += 1;
+            }
+            TranscriptMessage::Usage {
+                model,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_creation_tokens,
+                ..
+            } => {
+                summary.turns += 1;
+                summary.input_tokens += input_tokens;
+                summary.output_tokens += output_tokens;
+                summary.cache_read_tokens += cache_read_tokens;
+                summary.cache_creation_tokens += cache_creation_tokens;
+
+                if let Some(model) = model {
+                    if !summary.models.contains(model) {
+                        summary.models.push(model.clone());
+                    }
+                }
+                match model
+                    .as_deref()
+                    .and_then(|model| ClaudeModelPrice::find(pricing, model))
+                {
+                    Some(price) => {
+                        summary.cost_usd += price.cost_usd(
+                            *input_tokens,
+                            *output_tokens,
+                            *cache_read_tokens,
+                            *cache_creation_tokens,
+                        );
+                    }
+                    None => summary.unpriced_turns += 1,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    summary.duration_ms = first_timestamp
+        .zip(last_timestamp)
+        .map(|(first, last)| last - first);
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A subagent that reads a file (the response is written as one line per content block)
+    /// and answers, plus a line still being written
+    const TRANSCRIPT: &str = r#"{"type":"user","timestamp":"2025-06-01T10:00:00Z","message":{"role":"user","content":"Find the config loader"}}
+{"type":"assistant","timestamp":"2025-06-01T10:00:02Z","message":{"id":"msg_1","model":"claude-sonnet-4-5-20250929","content":[{"type":"thinking","thinking":"Search first"}],"usage":{"input_tokens":1000,"output_tokens":200,"cache_read_input_tokens":10000,"cache_creation_input_tokens":2000}}}
+{"type":"assistant","timestamp":"2025-06-01T10:00:03Z","message":{"id":"msg_1","model":"claude-sonnet-4-5-20250929","content":[{"type":"tool_use","id":"toolu_1","name":"Read","input":{"file_path":"src/config.rs"}}],"usage":{"input_tokens":1000,"output_tokens":200,"cache_read_input_tokens":10000,"cache_creation_input_tokens":2000}}}
+
+{"type":"user","timestamp":"2025-06-01T10:00:04Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":[{"type":"text","text":"No such file"},{"type":"image"}],"is_error":true}]}}
+{"type":"assistant","timestamp":"2025-06-01T10:00:10Z","message":{"id":"msg_2","model":"claude-sonnet-4-5-20250929","content":[{"type":"text","text":"It is in src/settings.rs"}],"usage":{"input_tokens":500,"output_tokens":100}}}
+{"type":"assistant","message":{"id":"msg_3","#;
+
+    fn pricing() -> Vec<ClaudeModelPrice> {
+        vec![ClaudeModelPrice {
+            model: "claude-sonnet-4-5".to_string(),
+            input_per_million_usd: 3.0,
+            output_per_million_usd: 15.0,
+        }]
+    }
+
+    fn usage(model: Option<&str>, input_tokens: u64, output_tokens: u64) -> TranscriptMessage {
+        TranscriptMessage::Usage {
+            model: model.map(str::to_string),
+            input_tokens,
+            output_tokens,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn parse_transcript_messages() {
+        let transcript = parse_transcript("agent-1.jsonl", TRANSCRIPT, &pricing());
+        assert_eq!(transcript.path, "agent-1.jsonl");
+        assert_eq!(transcript.skipped_lines, 1);
+
+        let kinds: Vec<&str> = transcript
+            .messages
+            .iter()
+            .map(|m| match m {
+                TranscriptMessage::Text { .. } => "text",
+                TranscriptMessage::Thinking { .. } => "thinking",
+                TranscriptMessage::ToolUse { .. } => "tool_use",
+                TranscriptMessage::ToolResult { .. } => "tool_result",
+                TranscriptMessage::Usage { .. } => "usage",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "text",
+                "thinking",
+                "usage",
+                "tool_use",
+                "tool_result",
+                "text",
+                "usage"
+            ]
+        );
+
+        assert_eq!(
+            transcript.messages[0],
+            TranscriptMessage::Text {
+                role: "user".to_string(),
+                text: "Find the config loader".to_string(),
+                timestamp: Some(1748772000000),
+            }
+        );
+        assert_eq!(
+            transcript.messages[4],
+            TranscriptMessage::ToolResult {
+                tool_use_id: "toolu_1".to_string(),
+                tool: Some("Read".to_string()),
+                output: "No such file\n[image]".to_string(),
+                is_error: true,
+                timestamp: Some(1748772004000),
+            }
+        );
+    }
+
+    #[test]
+    fn usage_is_counted_once_per_response() {
+        let summary = parse_transcript("agent-1.jsonl", TRANSCRIPT, &pricing()).summary;
+        assert_eq!(summary.turns, 2);
+        assert_eq!(summary.input_tokens, 1500);
+        assert_eq!(summary.output_tokens, 300);
+        assert_eq!(summary.cache_read_tokens, 10000);
+        assert_eq!(summary.cache_creation_tokens, 2000);
+        assert_eq!(summary.models, ["claude-sonnet-4-5-20250929"]);
+        assert_eq!(
+            summary.tools["Read"],
+            SubagentToolStats {
+                calls: 1,
+                errors: 1
+            }
+        );
+        assert_eq!(summary.duration_ms, Some(10_000));
+
+        // Responses without an id can't be told apart, so each line counts
+        let jsonl = r#"{"type":"assistant","message":{"content":[],"usage":{"input_tokens":1}}}
+{"type":"assistant","message":{"content":[],"usage":{"input_tokens":1}}}"#;
+        assert_eq!(parse_transcript("a.jsonl", jsonl, &[]).summary.turns, 2);
+    }
+
+    #[test]
+    fn cost_uses_configured_prices() {
+        let summary = parse_transcript("agent-1.jsonl", TRANSCRIPT, &pricing()).summary;
+        // msg_1: (1000 + 2000 * 1.25 + 10000 * 0.1) * 3 + 200 * 15; msg_2: 500 * 3 + 100 * 15
+        let expected = (4500.0 * 3.0 + 200.0 * 15.0 + 500.0 * 3.0 + 100.0 * 15.0) / 1_000_000.0;
+        assert!(
+            (summary.cost_usd - expected).abs() < 1e-12,
+            "{}",
+            summary.cost_usd
+        );
+        assert_eq!(summary.unpriced_turns, 0);
+
+        let summary = parse_transcript("agent-1.jsonl", TRANSCRIPT, &[]).summary;
+        assert_eq!(summary.cost_usd, 0.0);
+        assert_eq!(summary.unpriced_turns, 2);
+    }
+
+    #[test]
+    fn summarize_counts_unknown_models_as_unpriced() {
+        let messages = [
+            usage(Some("claude-sonnet-4-5-20250929"), 1_000_000, 0),
+            usage(Some("claude-sonnet-4-5-20250929"), 0, 1_000_000),
+            usage(Some("gpt-4o"), 1_000_000, 0),
+            usage(None, 1_000_000, 0),
+        ];
+        let summary = summarize(&messages, &pricing());
+        assert_eq!(summary.turns, 4);
+        assert!((summary.cost_usd - 18.0).abs() < 1e-9);
+        assert_eq!(summary.unpriced_turns, 2);
+        assert_eq!(summary.models, ["claude-sonnet-4-5-20250929", "gpt-4o"]);
+        assert_eq!(summary.duration_ms, None);
+    }
+
+    #[test]
+    fn long_tool_output_is_truncated() {
+        let long = "é".repeat(MAX_TOOL_OUTPUT_CHARS + 5);
+        let output = tool_output(Some(&Value::String(long)));
+        assert_eq!(
+            output.chars().filter(|&c| c == 'é').count(),
+            MAX_TOOL_OUTPUT_CHARS
+        );
+        assert!(output.ends_with("\n... (truncated)"));
+
+        let short = "é".repeat(MAX_TOOL_OUTPUT_CHARS);
+        assert_eq!(tool_output(Some(&Value::String(short.clone()))), short);
+        assert_eq!(tool_output(None), "");
+    }
+
+    #[test]
+    fn load_transcript_rejects_other_files() {
+        assert!(load_transcript("/etc/passwd", &[])
+            .unwrap_err()
+            .starts_with("Not a transcript file"));
+    }
+}
@@ -16,6 +16,9 @@ import {
   needsUserConfirmation,
   buildRepoContextForCleanup,
   buildAllReposContextForCleanup,
+  isTaskSpecSystemPromptEnabled,
+  type PromptIntakeResult,
+  type RepoCandidate,
 } from '$lib/utils/llm';
 import { processVoiceCommand } from '$lib/utils/voiceCommands';
 import { isAutoModel } from '$lib/utils/models';
@@ -55,6 +58,8 @@ export interface RepoRecommendation {
   repoIndex: number;
   reasoning: string;
   confidence: string;
+  /** Ranked candidates (best first), including the recommended repository */
+  candidates?: RepoCandidate[];
 }
 
 export interface ModelRecommendation {
@@ -68,6 +73,8 @@ export interface SystemPromptOptions {
   repoName: string;
   includeTranscriptionNotice: boolean;
   allRepos: Array<{ path: string; name: string }>;
+  /** Task spec rendered as a system prompt (prompt enhancement) */
+  taskSpecPrompt?: string;
 }
 
 /**
@@ -102,19 +109,20 @@ export function processVoiceCommands(
 }
 
 /**
- * Clean up a transcript using LLM
+ * Clean up a transcript using LLM (or take the cleanup from a combined intake result)
  */
 export async function cleanupTranscript(
   transcript: string,
   voskTranscript?: string,
-  repoContext?: string
+  repoContext?: string,
+  intake?: PromptIntakeResult | null
 ): Promise<CleanupResult> {
   if (!isTranscriptionCleanupEnabled()) {
     return { text: transcript, wasCleanedUp: false };
   }
 
   try {
-    const cleanupResult = await cleanTranscription(transcript, voskTranscript, repoContext);
+    const cleanupResult = await cleanTranscription(transcript, voskTranscript, repoContext, intake);
 
     if (cleanupResult.wasCleanedUp) {
       console.log(
@@ -137,11 +145,12 @@ export async function cleanupTranscript(
 }
 
 /**
- * Get model recommendation for a transcript
+ * Get model recommendation for a transcript (or take it from a combined intake result)
  */
 export async function getModelRecommendation(
   transcript: string,
-  enabledModels: string[]
+  enabledModels: string[],
+  intake?: PromptIntakeResult | null
 ): Promise<{ model: string; thinkingLevel: ThinkingLevel | null; recommendation?: ModelRecommendation }> {
   const currentSettings = get(settings);
   let model = currentSettings.default_model;
@@ -159,7 +168,7 @@ export async function getModelRecommendation(
   }
 
   try {
-    const recommendation = await recommendModel(transcript);
+    const recommendation = await recommendModel(transcript, intake);
 
     if (recommendation) {
       // Only use if the model is enabled
@@ -198,11 +207,12 @@ export async function getModelRecommendation(
 }
 
 /**
- * Get repository recommendation for a transcript
+ * Get repository recommendation for a transcript (or take it from a combined intake result)
  */
 export async function getRepoRecommendation(
   transcript: string,
-  repos: Array<{ path: string; name: string }>
+  repos: Array<{ path: string; name: string }>,
+  intake?: PromptIntakeResult | null
 ): Promise<RepoRecommendation | null> {
   if (repos.length <= 1 || !isRepoAutoSelectEnabled()) {
     return null;
@@ -210,7 +220,7 @@ export async function getRepoRecommendation(
 
   try {
     // Pass isTranscribed=true since this is from voice transcription
-    const recommendation = await recommendRepo(transcript, true);
+    const recommendation = await recommendRepo(transcript, true, intake);
 
     if (!recommendation) {
       console.log('[llm] No repo recommendation returned');
@@ -258,6 +268,10 @@ export function buildSystemPrompt(options: SystemPromptOptions): string | undefi
     parts.push(repoConfirmationPrompt);
   }
 
+  if (options.taskSpecPrompt && isTaskSpecSystemPromptEnabled()) {
+    parts.push(options.taskSpecPrompt);
+  }
+
   return parts.length > 0 ? parts.join('\n\n') : undefined;
 }
 
@@ -1,11 +1,11 @@
 import { writable, derived, get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
-import { settings } from './settings';
+import { settings, type McpServerConfig } from './settings';
 import { playCompletionSound } from '$lib/utils/sound';
 import { usageStats } from './usageStats';
-import { saveSessionsToDisk } from './sessionPersistence';
-import { analyzeSessionCompletion, generateSessionNameFromPrompt, isLlmEnabled, type QuickAction } from '$lib/utils/llm';
+import { persistedToSdkSession, saveSessionsToDisk, type PersistedSdkSession } from './sessionPersistence';
+import { analyzeSessionCompletion, compactSessionHistory, generateSessionNameFromPrompt, isInteractionDetectionEnabled, isLlmEnabled, type HistoryTokenEstimate, type PromptEnhancementResult, type QuickAction, type RepoCandidate } from '$lib/utils/llm';
 import { isAutoModel, resolveModelForApi } from '$lib/utils/models';
 
 // =============================================================================
@@ -47,10 +47,57 @@ export interface SdkMessage {
   agentId?: string;
   agentType?: string;
   transcriptPath?: string;
+  /** What a finished subagent did (subagent_stop messages) */
+  subagentSummary?: SubagentSummary | null;
   thinkingDurationMs?: number;
   timestamp: number;
 }
 
+/** Calls of one tool by a subagent */
+export interface SubagentToolStats {
+  calls: number;
+  errors: number;
+}
+
+/** Tokens, estimated cost and tool calls of a finished subagent, from its transcript */
+export interface SubagentSummary {
+  turns: number;
+  inputTokens: number;
+  outputTokens: number;
+  cacheReadTokens: number;
+  cacheCreationTokens: number;
+  costUsd: number;
+  /** Responses of models without a known price (not included in the cost) */
+  unpricedTurns: number;
+  models: string[];
+  tools: Record<string, SubagentToolStats>;
+  durationMs?: number | null;
+}
+
+/** One entry of a subagent transcript */
+export type TranscriptMessage =
+  | { type: 'text'; role: 'user' | 'assistant'; text: string; timestamp?: number | null }
+  | { type: 'thinking'; text: string; timestamp?: number | null }
+  | { type: 'tool_use'; toolUseId: string; tool: string; input: unknown; timestamp?: number | null }
+  | { type: 'tool_result'; toolUseId: string; tool?: string | null; output: string; isError: boolean; timestamp?: number | null }
+  | {
+      type: 'usage';
+      model?: string | null;
+      inputTokens: number;
+      outputTokens: number;
+      cacheReadTokens: number;
+      cacheCreationTokens: number;
+      timestamp?: number | null;
+    };
+
+/** Returned by `read_subagent_transcript` */
+export interface SubagentTranscript {
+  path: string;
+  messages: TranscriptMessage[];
+  summary: SubagentSummary;
+  skippedLines: number;
+}
+
 export interface SdkUsage {
   inputTokens: number;
   outputTokens: number;
@@ -128,6 +175,140 @@ export interface PlanModeState {
   isComplete: boolean;
 }
 
+/** A checklist item of a plan file (`- [ ] Task`) */
+export interface PlanItem {
+  text: string;
+  checked: boolean;
+  /** 1-based line in the file */
+  line: number;
+}
+
+export interface PlanSection {
+  /** Empty for text before the first heading */
+  heading: string;
+  level: number;
+  content: string;
+  items: PlanItem[];
+}
+
+export interface PlanDocument {
+  path: string;
+  title?: string | null;
+  sections: PlanSection[];
+  totalItems: number;
+  checkedItems: number;
+}
+
+/** Payload of the `plan-progress-{id}` event */
+export interface PlanProgress {
+  plan: PlanDocument;
+  newlyChecked: PlanItem[];
+  newlyUnchecked: PlanItem[];
+}
+
+/** Returned by `spawn_plan_implementation` */
+export interface PlanImplementation {
+  sessionId: string;
+  prompt: string;
+  plan: PlanDocument;
+}
+
+/** The plan an implementation session works through */
+export interface ImplementingPlan {
+  planPath: string;
+  featureName: string;
+  /** The plan mode session that wrote the plan */
+  planSessionId?: string | null;
+  checkedItems: number;
+  totalItems: number;
+}
+
+/** A tool call waiting for the user's approval (no permission rule matched it) */
+export interface SdkPermissionRequest {
+  tool: string;
+  input: Record<string, unknown>;
+  toolUseId: string;
+  requestedAt: number;
+  /** Why the last answer to this request failed, e.g. "always allow" outside a configured repo */
+  error?: string;
+}
+
+export type PermissionDecision = 'allow' | 'deny' | 'allow_always';
+
+/** Payload of the `sidecar-crashed` event */
+export interface SidecarCrash {
+  exitCode: number | null;
+  /** Most recent stderr lines of the crashed process */
+  stderr: string[];
+  /** Sessions that will be re-created after the restart */
+  sessions: string[];
+  /** Sessions whose running query was lost */
+  interrupted: string[];
+}
+
+/** Handshake reported by the running sidecar (`get_sidecar_info`) */
+export interface SidecarInfo {
+  protocolVersion: number;
+  sdkVersion: string | null;
+  capabilities: string[];
+}
+
+/** A node binary considered by `diagnose_sidecar`; `error` is null for a usable one */
+export interface NodeCandidate {
+  path: string;
+  source: 'config' | 'PATH' | 'nvm' | 'volta' | 'fnm' | 'system';
+  version: string | null;
+  error: string | null;
+}
+
+/** Which node and sidecar script would be used, returned by `diagnose_sidecar` */
+export interface SidecarDiagnosis {
+  node: string | null;
+  nodeVersion: string | null;
+  minNodeVersion: string;
+  nodeCandidates: NodeCandidate[];
+  script: string | null;
+  scriptCandidates: { path: string; exists: boolean }[];
+}
+
+/** Payload of the `sidecar-protocol-error` event */
+export interface SidecarProtocolError {
+  /** `handshake`: the sidecar is missing or incompatible; `parse`: a line could not be parsed */
+  kind: 'handshake' | 'parse';
+  message: string;
+  messageType?: string | null;
+  line?: string;
+}
+
+/** A prompt sent while the session was busy; it runs when the current query finishes */
+export interface QueuedPrompt {
+  id: string;
+  prompt: string;
+  images?: SdkImageContent[] | null;
+  /** When the prompt was queued (Unix ms) */
+  queuedAt: number;
+}
+
+/** Payload of the `sdk-budget-exceeded-{id}` event */
+export interface BudgetExceeded {
+  kind: 'cost' | 'turns' | 'duration';
+  /** The configured limit (USD, turns or minutes) */
+  limit: number;
+  /** What the session has used, in the same unit */
+  used: number;
+}
+
+export function describeBudgetExceeded(b: BudgetExceeded): string {
+  switch (b.kind) {
+    case 'cost':
+      return `cost $${b.used.toFixed(2)} of $${b.limit.toFixed(2)}`;
+    case 'turns':
+      return `${b.used} of ${b.limit} turns`;
+    case 'duration':
+      return `${b.used.toFixed(1)} of ${b.limit} minutes`;
+  }
+}
+
 export type ThinkingLevel = null | 'on';
 export type SettingsThinkingLevel = 'off' | 'on';
 
@@ -152,6 +333,8 @@ export interface PendingRepoSelection {
   recommendedIndex: number | null;
   reasoning: string;
   confidence: string;
+  /** Ranked candidates (best first), used to order the repository list */
+  candidates?: RepoCandidate[];
 }
 
 export type PendingTranscriptionStatus = 'recording' | 'transcribing' | 'processing';
@@ -180,6 +363,8 @@ export interface PendingTranscriptionInfo {
     reasoning: string;
     confidence: string;
   };
+  /** Structured task spec generated from the transcript (prompt enhancement) */
+  taskSpec?: PromptEnhancementResult;
 }
 
 export interface SdkSession {
@@ -204,13 +389,26 @@ export interface SdkSession {
   planMode?: PlanModeState;
   draftPrompt?: string;
   draftImages?: SdkImageContent[];
+  /** HEAD commit when the session was created - base for commit message / PR generation */
+  baseCommit?: string;
+  /** Tool calls waiting for approval */
+  pendingPermissions?: SdkPermissionRequest[];
+  /** Session this one was forked from */
+  parentId?: string;
+  /** Index of the last parent message copied into the fork */
+  forkedAtMessage?: number;
+  /** Prompts waiting for the running query to finish */
+  queuedPrompts?: QueuedPrompt[];
+  /** Plan this session implements, with its checklist progress */
+  implementingPlan?: ImplementingPlan;
 }
 
 export type HistoryMessage =
   | { type: 'user'; content: string }
   | { type: 'assistant'; content: string }
   | { type: 'tool_use'; tool: string; input: unknown }
-  | { type: 'tool_result'; tool: string; output: string };
+  | { type: 'tool_result'; tool: string; output: string }
+  | { type: 'summary'; content: string };
 
 // =============================================================================
 // Helper Functions
@@ -340,6 +538,7 @@ function createSdkSessionsStore() {
   const listeners = new Map<string, UnlistenFn[]>();
   const liveSessions = new Set<string>();
   let sidecarStarted = false;
+  let sidecarListeners: UnlistenFn[] | null = null;
 
   // ---------------------------------------------------------------------------
   // Event Listener Setup (Single implementation, used by all initialization paths)
@@ -348,17 +547,20 @@ function createSdkSessionsStore() {
   async function setupEventListeners(id: string): Promise<UnlistenFn[]> {
     const unlisteners: UnlistenFn[] = [];
 
-    // Text events
+    // Text events - the backend batches chunks, each chunk is its own message
     unlisteners.push(
-      await listen<string>(`sdk-text-${id}`, (e) => {
+      await listen<string[]>(`sdk-text-${id}`, (e) => {
+        const timestamp = Date.now();
+        // Timestamps key the message list, so keep them distinct within a batch
+        const textMessages = e.payload.map((content, i) => ({ type: 'text' as const, content, timestamp: timestamp + i }));
         update(sessions =>
           sessions.map(s =>
             s.id === id
               ? {
                   ...s,
-                  startedAt: s.startedAt || Date.now(),
-                  currentWorkStartedAt: s.currentWorkStartedAt || Date.now(),
-                  messages: [...s.messages, { type: 'text' as const, content: e.payload, timestamp: Date.now() }],
+                  startedAt: s.startedAt || timestamp,
+                  currentWorkStartedAt: s.currentWorkStartedAt || timestamp,
+                  messages: [...s.messages, ...textMessages],
                 }
               : s
           )
@@ -477,13 +679,14 @@ function createSdkSessionsStore() {
             const workPeriod = calculateWorkPeriod(s);
             const updatedMessages = [...s.messages, { type: 'done' as const, timestamp: Date.now() }];
             sessionMessages = updatedMessages;
-            needsAiAnalysis = isLlmEnabled() && !s.planMode?.isActive && (!s.aiMetadata?.outcome || s.aiMetadata?.needsInteraction === undefined);
+            needsAiAnalysis = (isLlmEnabled() || isInteractionDetectionEnabled()) && !s.planMode?.isActive && (!s.aiMetadata?.outcome || s.aiMetadata?.needsInteraction === undefined);
 
             return {
               ...s,
               status: 'idle' as const,
               ...workPeriod,
               messages: updatedMessages,
+              pendingPermissions: undefined,
               unread: currentSettings.mark_sessions_unread ? true : s.unread,
             };
           })
@@ -522,6 +725,7 @@ function createSdkSessionsStore() {
               status: 'error' as const,
               ...workPeriod,
               messages: [...s.messages, { type: 'error' as const, content: e.payload, timestamp: Date.now() }],
+              pendingPermissions: undefined,
               unread: currentSettings.mark_sessions_unread ? true : s.unread,
             };
           })
@@ -530,6 +734,64 @@ function createSdkSessionsStore() {
       })
     );
 
+    // Prompt queue changes (queued, cancelled, reordered or dispatched)
+    unlisteners.push(
+      await listen<QueuedPrompt[]>(`sdk-queue-${id}`, (e) => {
+        update(sessions =>
+          sessions.map(s => s.id === id ? { ...s, queuedPrompts: e.payload.length > 0 ? e.payload : undefined } : s)
+        );
+        debouncedSave();
+      })
+    );
+
+    // A queued prompt was sent to the sidecar
+    unlisteners.push(
+      await listen<QueuedPrompt>(`sdk-queue-dispatched-${id}`, (e) => {
+        const item = e.payload;
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? {
+                  ...s,
+                  status: 'querying' as const,
+                  messages: [...s.messages, { type: 'user' as const, content: item.prompt, images: item.images ?? undefined, timestamp: Date.now() }],
+                }
+              : s
+          )
+        );
+      })
+    );
+
+    // Checklist items of the implemented plan were checked off
+    unlisteners.push(
+      await listen<PlanProgress>(`plan-progress-${id}`, (e) => {
+        const { plan } = e.payload;
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id && s.implementingPlan
+              ? { ...s, implementingPlan: { ...s.implementingPlan, checkedItems: plan.checkedItems, totalItems: plan.totalItems } }
+              : s
+          )
+        );
+        debouncedSave();
+      })
+    );
+
+    // Budget events: the backend has already stopped the query
+    unlisteners.push(
+      await listen<BudgetExceeded>(`sdk-budget-exceeded-${id}`, (e) => {
+        const content = `Session budget reached (${describeBudgetExceeded(e.payload)}). The query was stopped; raise the limit in settings to continue.`;
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? { ...s, messages: [...s.messages, { type: 'error' as const, content, timestamp: Date.now() }] }
+              : s
+          )
+        );
+        debouncedSave();
+      })
+    );
+
     // Usage events
     unlisteners.push(
       await listen<SdkUsage>(`sdk-usage-${id}`, (e) => {
@@ -587,7 +849,12 @@ function createSdkSessionsStore() {
                   ...s,
                   messages: [
                     ...s.messages,
-                    { type: 'subagent_stop' as const, agentId: e.payload.agentId, transcriptPath: e.payload.transcriptPath, timestamp: Date.now() },
+                    {
+                      type: 'subagent_stop' as const,
+                      agentId: e.payload.agentId,
+                      transcriptPath: e.payload.transcriptPath,
+                      timestamp: Date.now(),
+                    },
                   ],
                 }
               : s
@@ -597,6 +864,27 @@ function createSdkSessionsStore() {
       })
     );
 
+    // Subagent summaries, read from the transcript after the subagent stopped
+    unlisteners.push(
+      await listen<{ agentId: string; summary: SubagentSummary }>(`sdk-subagent-summary-${id}`, (e) => {
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? {
+                  ...s,
+                  messages: s.messages.map(m =>
+                    m.type === 'subagent_stop' && m.agentId === e.payload.agentId
+                      ? { ...m, subagentSummary: e.payload.summary }
+                      : m
+                  ),
+                }
+              : s
+          )
+        );
+        debouncedSave();
+      })
+    );
+
     // Planning questions events
     unlisteners.push(
       await listen<PlanningQuestion[]>(`sdk-planning-questions-${id}`, (e) => {
@@ -619,6 +907,24 @@ function createSdkSessionsStore() {
       })
     );
 
+    // Permission request events (tool calls no permission rule answered)
+    unlisteners.push(
+      await listen<{ tool: string; input: Record<string, unknown>; toolUseId: string }>(`sdk-permission-request-${id}`, (e) => {
+        const currentSettings = get(settings);
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? {
+                  ...s,
+                  pendingPermissions: [...(s.pendingPermissions ?? []), { ...e.payload, requestedAt: Date.now() }],
+                  unread: currentSettings.mark_sessions_unread ? true : s.unread,
+                }
+              : s
+          )
+        );
+      })
+    );
+
     // Planning complete events
     unlisteners.push(
       await listen<{ planPath: string; featureName: string; summary: string }>(`sdk-planning-complete-${id}`, (e) => {
@@ -639,22 +945,60 @@ function createSdkSessionsStore() {
     return unlisteners;
   }
 
+  // ---------------------------------------------------------------------------
+  // Sidecar Lifecycle Listeners
+  // ---------------------------------------------------------------------------
+
+  async function setupSidecarListeners(): Promise<UnlistenFn[]> {
+    const unlisteners: UnlistenFn[] = [];
+
+    // The backend restarts a crashed sidecar with backoff and re-creates live sessions itself;
+    // interrupted queries are reported through their sdk-error events
+    unlisteners.push(
+      await listen<SidecarCrash>('sidecar-crashed', (e) => {
+        console.error(
+          `[sdkSessions] Sidecar crashed (exit code ${e.payload.exitCode ?? 'unknown'}), ${e.payload.sessions.length} live sessions`,
+          e.payload.stderr.join('\n')
+        );
+      })
+    );
+
+    unlisteners.push(
+      await listen<{ sessions: string[] }>('sidecar-restarted', (e) => {
+        console.log(`[sdkSessions] Sidecar restarted, restored ${e.payload.sessions.length} sessions`);
+      })
+    );
+
+    // Usually a stale sidecar/dist build that does not match this app version
+    unlisteners.push(
+      await listen<SidecarProtocolError>('sidecar-protocol-error', (e) => {
+        if (e.payload.kind === 'parse') {
+          console.error(`[sdkSessions] Unparseable sidecar message (type: ${e.payload.messageType ?? 'unknown'}): ${e.payload.message}`, e.payload.line);
+        } else {
+          console.error('[sdkSessions] Sidecar handshake failed:', e.payload.message);
+        }
+      })
+    );
+
+    // Restarting gave up: start from scratch on the next prompt and re-register sessions then
+    unlisteners.push(
+      await listen<{ attempts: number; stderr: string[] }>('sidecar-restart-failed', (e) => {
+        console.error(`[sdkSessions] Sidecar could not be restarted after ${e.payload.attempts} attempts`, e.payload.stderr.join('\n'));
+        sidecarStarted = false;
+        liveSessions.clear();
+      })
+    );
+
+    return unlisteners;
+  }
+
   // ---------------------------------------------------------------------------
   // Backend Session Registration
   // ---------------------------------------------------------------------------
 
-  async function registerSessionWithBackend(
-    id: string,
-    cwd: string,
-    model: string,
-    thinkingLevel: ThinkingLevel,
-    systemPrompt?: string | null,
-    historyMessages?: HistoryMessage[] | null,
-    planMode?: boolean
-  ): Promise<void> {
+  /** MCP servers for a session in `cwd`: the repo's servers, or all enabled global ones */
+  async function resolveMcpServers(cwd: string): Promise<McpServerConfig[] | null> {
     const currentSettings = get(settings);
-    const resolvedModel = resolveModelForApi(model, currentSettings.enabled_models);
-
     // Determine which MCP servers to use
     // 1. Check if the current repo has specific MCP servers configured
     // 2. Otherwise, use all enabled global servers
@@ -717,6 +1061,21 @@ function createSdkSessionsStore() {
     }
 
     console.log('[MCP Debug] Final mcpServers to send:', mcpServers?.length ?? 0, mcpServers);
+    return mcpServers && mcpServers.length > 0 ? mcpServers : null;
+  }
+
+  async function registerSessionWithBackend(
+    id: string,
+    cwd: string,
+    model: string,
+    thinkingLevel: ThinkingLevel,
+    systemPrompt?: string | null,
+    historyMessages?: HistoryMessage[] | null,
+    planMode?: boolean
+  ): Promise<void> {
+    const currentSettings = get(settings);
+    const resolvedModel = resolveModelForApi(model, currentSettings.enabled_models);
+    const mcpServers = await resolveMcpServers(cwd);
 
     await invoke('create_sdk_session', {
       id,
@@ -725,7 +1084,7 @@ function createSdkSessionsStore() {
       systemPrompt: systemPrompt ?? null,
       messages: historyMessages && historyMessages.length > 0 ? historyMessages : null,
       planMode: planMode ?? null,
-      mcpServers: mcpServers && mcpServers.length > 0 ? mcpServers : null,
+      mcpServers,
     });
 
     if (thinkingLevel) {
@@ -746,6 +1105,9 @@ function createSdkSessionsStore() {
 
     async ensureSidecarStarted(): Promise<void> {
       if (sidecarStarted) return;
+      if (!sidecarListeners) {
+        sidecarListeners = await setupSidecarListeners();
+      }
       await invoke('start_sidecar');
       sidecarStarted = true;
     },
@@ -773,7 +1135,20 @@ function createSdkSessionsStore() {
       const unlisteners = await setupEventListeners(id);
       listeners.set(id, unlisteners);
 
-      await registerSessionWithBackend(id, cwd, model, thinkingLevel, systemPrompt, null, planMode);
+      try {
+        await registerSessionWithBackend(id, cwd, model, thinkingLevel, systemPrompt, null, planMode);
+      } catch (error) {
+        // The sidecar rejected the session or never answered; don't leave a dead session behind
+        unlisteners.forEach(unlisten => unlisten());
+        listeners.delete(id);
+        update(sessions => sessions.filter(s => s.id !== id));
+        throw error;
+      }
+
+      // Remember where the repo was so the session's changes can be diffed later
+      invoke<string>('get_git_head', { repoPath: cwd })
+        .then(baseCommit => update(sessions => sessions.map(s => s.id === id ? { ...s, baseCommit } : s)))
+        .catch(() => { /* Not a git repo or no commits yet */ });
 
       const currentSettings = get(settings);
       const resolvedModel = resolveModelForApi(model, currentSettings.enabled_models);
@@ -795,8 +1170,66 @@ function createSdkSessionsStore() {
       const unlisteners = await setupEventListeners(id);
       listeners.set(id, unlisteners);
 
-      const historyMessages = convertToHistoryMessages(session.messages);
+      let historyMessages = convertToHistoryMessages(session.messages);
+      const compacted = await compactSessionHistory(historyMessages);
+      if (compacted) {
+        historyMessages = compacted.messages;
+      }
       await registerSessionWithBackend(id, session.cwd, session.model, session.thinkingLevel, null, historyMessages);
+
+      // Saved queues come back paused so nothing runs until the user asks for it
+      if (session.queuedPrompts?.length) {
+        await invoke('restore_sdk_prompt_queue', { id, items: session.queuedPrompts });
+      }
+
+      if (session.implementingPlan) {
+        invoke('watch_plan_file', { sessionId: id, cwd: session.cwd, planPath: session.implementingPlan.planPath })
+          .catch(err => console.error('[sdkSessions] Failed to watch plan file:', err));
+      }
+    },
+
+    /**
+     * Fork a session from one of its messages. The new session keeps the messages up to and
+     * including `messageIndex` and continues from there; the original is left untouched.
+     */
+    async forkSession(id: string, messageIndex: number): Promise<string> {
+      let session: SdkSession | undefined;
+      subscribe(sessions => { session = sessions.find(s => s.id === id); })();
+      if (!session) throw new Error(`Session ${id} not found`);
+
+      await this.ensureSidecarStarted();
+      // The backend forks from the saved copy, so make sure it is current
+      await saveSessionsToDisk();
+
+      const currentSettings = get(settings);
+      const persisted = await invoke<PersistedSdkSession>('fork_sdk_session', {
+        sessionId: id,
+        messageIndex,
+        model: resolveModelForApi(session.model, currentSettings.enabled_models),
+        mcpServers: await resolveMcpServers(session.cwd),
+      });
+
+      const fork = persistedToSdkSession(persisted);
+      update(sessions => [...sessions, fork]);
+      liveSessions.add(fork.id);
+      listeners.set(fork.id, await setupEventListeners(fork.id));
+      return fork.id;
+    },
+
+    /** Whether the session is registered with the sidecar (restored sessions are not until resumed) */
+    isSessionLive(id: string): boolean {
+      return liveSessions.has(id);
+    },
+
+    /** Estimate the history tokens a restored session will send when resumed */
+    async estimateRestore(id: string): Promise<HistoryTokenEstimate | null> {
+      let session: SdkSession | undefined;
+      subscribe(sessions => { session = sessions.find(s => s.id === id); })();
+
+      if (!session || session.messages.length === 0) return null;
+      return invoke<HistoryTokenEstimate>('estimate_history_tokens', {
+        messages: convertToHistoryMessages(session.messages),
+      });
     },
 
     async sendPrompt(id: string, prompt: string, images?: SdkImageContent[]): Promise<void> {
@@ -804,28 +1237,36 @@ function createSdkSessionsStore() {
 
       let sessionCwd: string | undefined;
       let needsNameGeneration = false;
+      let busy = false;
       subscribe(sessions => {
         const session = sessions.find(s => s.id === id);
         sessionCwd = session?.cwd;
         needsNameGeneration = !session?.aiMetadata?.name && session?.messages.filter(m => m.type === 'user').length === 0;
+        busy = session?.status === 'querying';
       })();
 
       usageStats.trackPrompt(sessionCwd);
 
-      update(sessions =>
-        sessions.map(s =>
-          s.id === id
-            ? {
-                ...s,
-                status: 'querying' as const,
-                messages: [...s.messages, { type: 'user' as const, content: prompt, images, timestamp: Date.now() }],
-                aiMetadata: s.aiMetadata ? { ...s.aiMetadata, needsInteraction: undefined, interactionReason: undefined, interactionUrgency: undefined, waitingFor: undefined } : s.aiMetadata,
-              }
-            : s
-        )
-      );
+      const showPrompt = () =>
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? {
+                  ...s,
+                  status: 'querying' as const,
+                  messages: [...s.messages, { type: 'user' as const, content: prompt, images, timestamp: Date.now() }],
+                  aiMetadata: s.aiMetadata ? { ...s.aiMetadata, needsInteraction: undefined, interactionReason: undefined, interactionUrgency: undefined, waitingFor: undefined } : s.aiMetadata,
+                }
+              : s
+          )
+        );
 
-      if (needsNameGeneration && isLlmEnabled()) {
+      // A prompt for a busy session is queued by the backend and shown once it is sent
+      if (!busy) {
+        showPrompt();
+      }
+
+      if (needsNameGeneration) {
         generateSessionNameFromPrompt(prompt)
           .then(aiMetadata => {
             if (aiMetadata.name) {
@@ -836,7 +1277,11 @@ function createSdkSessionsStore() {
       }
 
       try {
-        await invoke('send_sdk_prompt', { id, prompt, images: images ?? null });
+        const queued = await invoke<QueuedPrompt | null>('send_sdk_prompt', { id, prompt, images: images ?? null });
+        // The query finished before the prompt arrived, so it was sent right away
+        if (busy && !queued) {
+          showPrompt();
+        }
       } catch (error) {
         update(sessions =>
           sessions.map(s =>
@@ -849,6 +1294,60 @@ function createSdkSessionsStore() {
       }
     },
 
+    async cancelQueuedPrompt(id: string, itemId: string): Promise<void> {
+      await this.ensureSessionLive(id);
+      await invoke('cancel_queued_sdk_prompt', { id, itemId });
+    },
+
+    /** Move a queued prompt to `index` (0 runs next) */
+    async moveQueuedPrompt(id: string, itemId: string, index: number): Promise<void> {
+      await this.ensureSessionLive(id);
+      await invoke('move_queued_sdk_prompt', { id, itemId, index });
+    },
+
+    /** Resume a paused queue (after a stop, an error or a restore) */
+    async runPromptQueue(id: string): Promise<void> {
+      await this.ensureSessionLive(id);
+      await invoke<boolean>('run_sdk_prompt_queue', { id });
+    },
+
+    /** Answer a pending tool permission request; "allow_always" also adds a repo rule */
+    async respondToPermission(id: string, toolUseId: string, decision: PermissionDecision): Promise<void> {
+      const request = get({ subscribe }).find(s => s.id === id)?.pendingPermissions?.find(p => p.toolUseId === toolUseId);
+      update(sessions =>
+        sessions.map(s =>
+          s.id === id
+            ? { ...s, pendingPermissions: s.pendingPermissions?.filter(p => p.toolUseId !== toolUseId) }
+            : s
+        )
+      );
+
+      try {
+        await invoke('respond_sdk_permission', {
+          id,
+          toolUseId,
+          decision,
+          updatedInput: null,
+          message: decision === 'deny' ? 'The user denied this tool call' : null,
+        });
+      } catch (error) {
+        // The request is still waiting for an answer, so show it again
+        if (request) {
+          const failed = { ...request, error: String(error) };
+          update(sessions =>
+            sessions.map(s =>
+              s.id === id ? { ...s, pendingPermissions: [...(s.pendingPermissions ?? []), failed] } : s
+            )
+          );
+        }
+        throw error;
+      }
+
+      if (decision === 'allow_always') {
+        await settings.load();
+      }
+    },
+
     async stopQuery(id: string): Promise<void> {
       if (!liveSessions.has(id)) {
         update(sessions => sessions.map(s => s.id === id ? { ...s, status: 'idle' as const } : s));
@@ -869,6 +1368,10 @@ function createSdkSessionsStore() {
         }
       }
 
+      if (this.getSession(id)?.implementingPlan) {
+        await invoke('unwatch_plan_file', { sessionId: id }).catch(() => { /* Not watched */ });
+      }
+
       const unlisteners = listeners.get(id);
       if (unlisteners) {
         for (const unlisten of unlisteners) unlisten();
@@ -890,6 +1393,7 @@ function createSdkSessionsStore() {
       const currentSettings = get(settings);
       const shouldResolve = liveSessions.has(id) && isAutoModel(model);
       const resolvedModel = shouldResolve ? resolveModelForApi(model, currentSettings.enabled_models) : model;
+      const previousModel = this.getSession(id)?.model;
 
       update(sessions => sessions.map(s => s.id === id ? { ...s, model: resolvedModel } : s));
 
@@ -899,10 +1403,14 @@ function createSdkSessionsStore() {
         await invoke('update_sdk_model', { id, model: resolvedModel });
       } catch (error) {
         console.error('Failed to update SDK model:', error);
+        if (previousModel !== undefined) {
+          update(sessions => sessions.map(s => s.id === id ? { ...s, model: previousModel } : s));
+        }
       }
     },
 
     async updateSessionThinking(id: string, thinkingLevel: ThinkingLevel): Promise<void> {
+      const previousLevel = this.getSession(id)?.thinkingLevel;
       update(sessions => sessions.map(s => s.id === id ? { ...s, thinkingLevel } : s));
 
       if (!liveSessions.has(id)) return;
@@ -912,6 +1420,9 @@ function createSdkSessionsStore() {
         await invoke('update_sdk_thinking', { id, maxThinkingTokens });
       } catch (error) {
         console.error('Failed to update SDK thinking:', error);
+        if (previousLevel !== undefined) {
+          update(sessions => sessions.map(s => s.id === id ? { ...s, thinkingLevel: previousLevel } : s));
+        }
       }
     },
 
@@ -933,6 +1444,11 @@ function createSdkSessionsStore() {
       }
     },
 
+    /** Name a session up front (e.g. from the combined intake), so sendPrompt does not request a name */
+    setSessionName(id: string, name: string, category?: string): void {
+      update(sessions => sessions.map(s => s.id === id ? { ...s, aiMetadata: { ...s.aiMetadata, name, category } } : s));
+    },
+
     markAsRead(id: string): void {
       update(sessions => sessions.map(s => s.id === id ? { ...s, unread: false } : s));
     },
@@ -1343,21 +1859,47 @@ function createSdkSessionsStore() {
 
       if (!session?.planMode) throw new Error('Session not in plan mode');
 
-      const formattedAnswers = session.planMode.answers.map(answer => {
+      // The backend checks the labels against the questions and sends them as the next turn
+      const answers = session.planMode.answers.map(answer => {
         const question = session!.planMode!.questions[answer.questionIndex];
-        const selectedLabels = answer.selectedOptions.map(i => question.options[i]?.label || `Option ${i}`);
         return {
-          question: question.question,
-          header: question.header,
-          selectedOptions: selectedLabels,
-          customInput: answer.textInput || null,
+          questionIndex: answer.questionIndex,
+          selected: answer.selectedOptions.map(i => question.options[i]?.label).filter((label): label is string => !!label),
+          other: answer.textInput || null,
         };
       });
 
-      const answerText = `Here are my answers to the planning questions:\n\n${JSON.stringify(formattedAnswers, null, 2)}`;
+      let answerText: string;
+      try {
+        await this.ensureSessionLive(id);
+        // A restored session is answered against the questions saved with it
+        await saveSessionsToDisk();
+        answerText = await invoke<string>('answer_planning_questions', { id, answers });
+      } catch (error) {
+        // Keep the wizard so the answers can be fixed and submitted again
+        update(sessions =>
+          sessions.map(s =>
+            s.id === id
+              ? { ...s, messages: [...s.messages, { type: 'error' as const, content: String(error), timestamp: Date.now() }] }
+              : s
+          )
+        );
+        throw error;
+      }
 
       this.clearPlanningQuestions(id);
-      await this.sendPrompt(id, answerText);
+      update(sessions =>
+        sessions.map(s =>
+          s.id === id
+            ? {
+                ...s,
+                status: 'querying' as const,
+                messages: [...s.messages, { type: 'user' as const, content: answerText, timestamp: Date.now() }],
+              }
+            : s
+        )
+      );
+      debouncedSave();
     },
 
     clearPlanningQuestions(id: string): void {
@@ -1392,26 +1934,87 @@ function createSdkSessionsStore() {
         return null;
       }
 
-      const { planFilePath, featureName } = planSession.planMode;
+      const { planFilePath } = planSession.planMode;
+      const featureName = planSession.planMode.featureName ?? planFilePath;
+      const { cwd, model, thinkingLevel } = planSession;
+
+      await this.ensureSidecarStarted();
+
+      // The backend parses the plan, creates the session and starts watching the file
+      const currentSettings = get(settings);
+      const implementation = await invoke<PlanImplementation>('spawn_plan_implementation', {
+        cwd,
+        planPath: planFilePath,
+        featureName,
+        model: resolveModelForApi(model, currentSettings.enabled_models),
+        mcpServers: await resolveMcpServers(cwd),
+      });
+      const id = implementation.sessionId;
+
+      const session: SdkSession = {
+        id,
+        cwd,
+        model,
+        autoModelRequested: isAutoModel(model),
+        thinkingLevel,
+        messages: [],
+        status: 'idle',
+        createdAt: Date.now(),
+        accumulatedDurationMs: 0,
+        aiMetadata: { name: `Implementing: ${featureName}`, category: 'implementation' },
+        implementingPlan: {
+          planPath: planFilePath,
+          featureName,
+          planSessionId,
+          checkedItems: implementation.plan.checkedItems,
+          totalItems: implementation.plan.totalItems,
+        },
+      };
 
-      const implementationId = await this.createSession(planSession.cwd, planSession.model, planSession.thinkingLevel);
+      update(sessions => [...sessions, session]);
 
+      const unlisteners = await setupEventListeners(id);
+      listeners.set(id, unlisteners);
+      liveSessions.add(id);
+
+      if (thinkingLevel) {
+        await invoke('update_sdk_thinking', { id, maxThinkingTokens: THINKING_BUDGETS[thinkingLevel] });
+      }
+
+      invoke<string>('get_git_head', { repoPath: cwd })
+        .then(baseCommit => update(sessions => sessions.map(s => s.id === id ? { ...s, baseCommit } : s)))
+        .catch(() => { /* Not a git repo or no commits yet */ });
+
+      usageStats.trackSession('sdk', resolveModelForApi(model, currentSettings.enabled_models), cwd);
+
+      await this.sendPrompt(id, implementation.prompt);
+      return id;
+    },
+
+    /**
+     * Read the transcript of a finished subagent of a session. The summary is attached to the
+     * session's subagent_stop message, so it is shown for subagents that finished before
+     * summaries were recorded too.
+     */
+    async loadSubagentTranscript(id: string, agentId: string): Promise<SubagentTranscript> {
+      const stop = this.getSession(id)?.messages.find(m => m.type === 'subagent_stop' && m.agentId === agentId);
+      if (!stop?.transcriptPath) throw new Error(`No transcript for subagent ${agentId}`);
+
+      const transcript = await invoke<SubagentTranscript>('read_subagent_transcript', { transcriptPath: stop.transcriptPath });
       update(sessions =>
         sessions.map(s =>
-          s.id === implementationId
-            ? { ...s, aiMetadata: { ...s.aiMetadata, name: `Implementing: ${featureName}`, category: 'implementation' } }
+          s.id === id
+            ? {
+                ...s,
+                messages: s.messages.map(m =>
+                  m.type === 'subagent_stop' && m.agentId === agentId ? { ...m, subagentSummary: transcript.summary } : m
+                ),
+              }
             : s
         )
       );
-
-      const implementationPrompt = `Please implement the plan defined in \`${planFilePath}\`.
-
-Read the plan file first to understand what needs to be done, then proceed with the implementation step by step. As you complete each task, update the plan file to mark completed items with [x].
-
-Focus on quality and maintainability. Ask questions if any requirements are unclear.`;
-
-      await this.sendPrompt(implementationId, implementationPrompt);
-      return implementationId;
+      debouncedSave();
+      return transcript;
     },
 
     selectSession(id: string): void {
@@ -1,8 +1,9 @@
 import { invoke } from '@tauri-apps/api/core';
 import { get } from 'svelte/store';
 import { settings } from './settings';
-import { sdkSessions, activeSdkSessionId, type SdkSession, type SdkMessage, type SdkImageContent, type ThinkingLevel, type SessionAiMetadata, type PendingRepoSelection, type SdkSessionUsage, type PendingTranscriptionInfo } from './sdkSessions';
+import { sdkSessions, activeSdkSessionId, type SdkSession, type SdkMessage, type SdkImageContent, type ThinkingLevel, type SessionAiMetadata, type PendingRepoSelection, type SdkSessionUsage, type PendingTranscriptionInfo, type QueuedPrompt, type PlanModeState, type ImplementingPlan, type SubagentSummary } from './sdkSessions';
 import { sessions, activeSessionId, type TerminalSession } from './sessions';
+import type { PromptEnhancementResult } from '$lib/utils/llm';
 
 // ============================================================================
 // AUTO-PERSISTENCE SYSTEM
@@ -28,6 +29,7 @@ const NON_PERSISTABLE_FIELDS: Record<string, Set<string>> = {
     'currentWorkStartedAt', // Runtime-only timer, accumulated time is persisted instead
     'draftPrompt', // Transient input state - user is still typing
     'draftImages', // Transient input state - images pending to be sent
+    'pendingPermissions', // Runtime-only - the sidecar forgets unanswered requests on restart
   ]),
   // PendingTranscriptionInfo fields that shouldn't be persisted
   PendingTranscriptionInfo: new Set([
@@ -170,6 +172,7 @@ export interface PersistedSdkMessage {
   agentId?: string;
   agentType?: string;
   transcriptPath?: string;
+  subagentSummary?: SubagentSummary | null;
   timestamp: number;
 }
 
@@ -200,6 +203,7 @@ export interface PersistedPendingTranscriptionInfo {
     reasoning: string;
     confidence: string;
   };
+  taskSpec?: PromptEnhancementResult;
 }
 
 /**
@@ -224,6 +228,14 @@ export interface PersistedSdkSession {
   pendingRepoSelection?: PendingRepoSelection;
   pendingPrompt?: string;
   pendingApprovalPrompt?: string;
+  baseCommit?: string;
+  parentId?: string;
+  forkedAtMessage?: number;
+  queuedPrompts?: QueuedPrompt[];
+  /** Plan mode wizard state, so a session can be resumed mid-wizard */
+  planMode?: PlanModeState;
+  /** Plan this session implements */
+  implementingPlan?: ImplementingPlan;
 }
 
 export interface PersistedTerminalSession {
@@ -272,7 +284,7 @@ function sdkSessionToPersisted(session: SdkSession): PersistedSdkSession {
  * Convert persisted SDK session to frontend format.
  * Applies defaults for fields that need runtime initialization.
  */
-function persistedToSdkSession(persisted: PersistedSdkSession): SdkSession {
+export function persistedToSdkSession(persisted: PersistedSdkSession): SdkSession {
   // Determine if this is a pending session
   const isPending = persisted.status === 'pending_transcription' ||
                     persisted.status === 'pending_repo' ||
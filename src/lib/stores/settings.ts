@@ -142,12 +142,19 @@ export interface SystemConfig {
   minimize_to_tray: boolean;
   start_minimized: boolean;
   autostart: boolean;
+  sidecar_journal: boolean;
+  node_path: string | null;
+  stream_flush_interval_ms: number;
 }
 
 export interface SessionPersistenceConfig {
   enabled: boolean;
   max_sessions: number;
   restore_sessions: number;
+  /** Replace older turns with an LLM summary when resuming a restored session */
+  compact_history: boolean;
+  /** Most recent turns replayed verbatim when compacting */
+  restore_recent_turns: number;
 }
 
 export interface RepoConfig {
@@ -162,6 +169,26 @@ export interface RepoConfig {
   vocabulary?: string[];
   /** List of MCP server IDs to use for this repository (overrides global servers) */
   mcp_servers?: string[];
+  /** Tool permission rules for SDK sessions in this repository */
+  permissions?: PermissionRules;
+  /** Budget limits for SDK sessions in this repository; unset limits fall back to the global ones */
+  budget?: BudgetLimits;
+}
+
+/** Limits on what a single SDK session may spend. A session that reaches one is stopped. */
+export interface BudgetLimits {
+  max_cost_usd: number | null;
+  /** Agent turns (model round trips) across all queries */
+  max_turns: number | null;
+  /** Time spent running queries, in minutes */
+  max_duration_minutes: number | null;
+}
+
+/** Tool permission patterns such as `Read`, `Bash(cargo test*)` or `mcp__github__*`.
+ * `*` matches any run of characters; deny rules are checked before allow rules. */
+export interface PermissionRules {
+  allow: string[];
+  deny: string[];
 }
 
 // Import and re-export MCP types
@@ -221,6 +248,13 @@ export interface LlmFeaturesConfig {
   recommend_model: boolean;
   /** Auto-select repository based on prompt content */
   auto_select_repo: boolean;
+  /** Run naming, cleanup and model/repo recommendation as one combined request */
+  combined_intake: boolean;
+  route_voice_intents: boolean;
+  /** Turn prompts into a structured task spec shown before sending */
+  enhance_prompts: boolean;
+  /** Send the task spec as the session's system prompt */
+  task_spec_system_prompt: boolean;
 }
 // Alias for backwards compatibility
 export type GeminiFeaturesConfig = LlmFeaturesConfig;
@@ -240,6 +274,28 @@ export interface LlmConfig {
   confirm_repo_selection: boolean;
   /** Minimum confidence level required for auto-selecting a repository */
   min_auto_select_confidence: RepoAutoSelectConfidence;
+  /** Maximum number of repos sent to the LLM; larger lists are pre-filtered lexically */
+  repo_shortlist_size: number;
+  repo_description_from_scan: boolean;
+  /** On-disk cache for repeated requests (session names, outcomes, quick actions) */
+  cache: LlmCacheConfig;
+  /** Per-model prices used to estimate the cost of LLM requests */
+  pricing: LlmModelPrice[];
+}
+
+/** Price of a provider/model in USD per million tokens */
+export interface LlmModelPrice {
+  provider: LlmProvider;
+  /** Also matches dated/suffixed variants of the model name */
+  model: string;
+  input_per_million_usd: number;
+  output_per_million_usd: number;
+}
+
+export interface LlmCacheConfig {
+  enabled: boolean;
+  ttl_hours: number;
+  max_entries: number;
 }
 // Alias for backwards compatibility
 export type GeminiConfig = LlmConfig;
@@ -276,6 +332,18 @@ export interface AppConfig {
   gemini?: LlmConfig;
   /** MCP server configuration */
   mcp: McpConfig;
+  /** Default budget limits for SDK sessions */
+  session_budget: BudgetLimits;
+  /** Claude prices for estimating SDK session and subagent costs */
+  claude_pricing: ClaudeModelPrice[];
+}
+
+/** Price of a Claude model in USD per million tokens (cache writes cost 1.25x, reads 0.1x input) */
+export interface ClaudeModelPrice {
+  /** Also matches dated/suffixed variants of the model name */
+  model: string;
+  input_per_million_usd: number;
+  output_per_million_usd: number;
 }
 
 const defaultConfig: AppConfig = {
@@ -360,12 +428,17 @@ const defaultConfig: AppConfig = {
     minimize_to_tray: false,
     start_minimized: false,
     autostart: false,
+    sidecar_journal: false,
+    node_path: null,
+    stream_flush_interval_ms: 50,
   },
   show_branch_in_sessions: true,
   session_persistence: {
     enabled: true,
     max_sessions: 50,
     restore_sessions: 5,
+    compact_history: false,
+    restore_recent_turns: 4,
   },
   session_sort_order: "Chronological",
   mark_sessions_unread: true,
@@ -393,13 +466,53 @@ const defaultConfig: AppConfig = {
       use_dual_transcription: false,
       recommend_model: false,
       auto_select_repo: false,
+      combined_intake: false,
+      route_voice_intents: false,
+      enhance_prompts: false,
+      task_spec_system_prompt: false,
     },
     confirm_repo_selection: false,
     min_auto_select_confidence: "high",
+    repo_shortlist_size: 8,
+    repo_description_from_scan: false,
+    cache: {
+      enabled: true,
+      ttl_hours: 168,
+      max_entries: 500,
+    },
+    pricing: [
+      { provider: "Gemini", model: "gemini-2.5-flash-lite", input_per_million_usd: 0.1, output_per_million_usd: 0.4 },
+      { provider: "Gemini", model: "gemini-2.5-flash", input_per_million_usd: 0.3, output_per_million_usd: 2.5 },
+      { provider: "OpenAI", model: "gpt-4o-mini", input_per_million_usd: 0.15, output_per_million_usd: 0.6 },
+      { provider: "OpenAI", model: "gpt-4o", input_per_million_usd: 2.5, output_per_million_usd: 10 },
+      { provider: "OpenAI", model: "gpt-4-turbo", input_per_million_usd: 10, output_per_million_usd: 30 },
+      { provider: "OpenAI", model: "gpt-3.5-turbo", input_per_million_usd: 0.5, output_per_million_usd: 1.5 },
+      { provider: "Groq", model: "meta-llama/llama-4-maverick-17b-128e-instruct", input_per_million_usd: 0.2, output_per_million_usd: 0.6 },
+      { provider: "Groq", model: "meta-llama/llama-4-scout-17b-16e-instruct", input_per_million_usd: 0.11, output_per_million_usd: 0.34 },
+      { provider: "Groq", model: "llama-3.3-70b-versatile", input_per_million_usd: 0.59, output_per_million_usd: 0.79 },
+      { provider: "Groq", model: "llama-3.1-8b-instant", input_per_million_usd: 0.05, output_per_million_usd: 0.08 },
+      { provider: "Groq", model: "openai/gpt-oss-120b", input_per_million_usd: 0.15, output_per_million_usd: 0.75 },
+      { provider: "Groq", model: "openai/gpt-oss-20b", input_per_million_usd: 0.1, output_per_million_usd: 0.5 },
+    ],
   },
   mcp: {
     servers: [],
   },
+  session_budget: {
+    max_cost_usd: null,
+    max_turns: null,
+    max_duration_minutes: null,
+  },
+  claude_pricing: [
+    { model: "claude-opus-4-5", input_per_million_usd: 5, output_per_million_usd: 25 },
+    { model: "claude-opus-4-1", input_per_million_usd: 15, output_per_million_usd: 75 },
+    { model: "claude-opus-4", input_per_million_usd: 15, output_per_million_usd: 75 },
+    { model: "claude-sonnet-4-5", input_per_million_usd: 3, output_per_million_usd: 15 },
+    { model: "claude-sonnet-4", input_per_million_usd: 3, output_per_million_usd: 15 },
+    { model: "claude-3-7-sonnet", input_per_million_usd: 3, output_per_million_usd: 15 },
+    { model: "claude-haiku-4-5", input_per_million_usd: 1, output_per_million_usd: 5 },
+    { model: "claude-3-5-haiku", input_per_million_usd: 0.8, output_per_million_usd: 4 },
+  ],
 };
 
 function createSettingsStore() {
@@ -22,32 +22,81 @@ export interface TokenStats {
   total_cost_usd: number;
 }
 
+/** Requests, tokens and estimated cost for one slice of LLM usage */
+export interface LlmUsageCounter {
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+}
+
+/** LLM usage for a day or month: totals plus the per-feature breakdown */
+export interface LlmPeriodUsage {
+  total: LlmUsageCounter;
+  by_feature: Record<string, LlmUsageCounter>;
+}
+
 export interface LlmTokenStats {
   total_input_tokens: number;
   total_output_tokens: number;
   total_requests: number;
-  // Per-feature breakdown (requests + input/output tokens)
-  session_naming_requests: number;
-  session_naming_input_tokens: number;
-  session_naming_output_tokens: number;
-  session_outcome_requests: number;
-  session_outcome_input_tokens: number;
-  session_outcome_output_tokens: number;
-  interaction_analysis_requests: number;
-  interaction_analysis_input_tokens: number;
-  interaction_analysis_output_tokens: number;
-  transcription_cleanup_requests: number;
-  transcription_cleanup_input_tokens: number;
-  transcription_cleanup_output_tokens: number;
-  model_recommendation_requests: number;
-  model_recommendation_input_tokens: number;
-  model_recommendation_output_tokens: number;
-  repo_description_requests: number;
-  repo_description_input_tokens: number;
-  repo_description_output_tokens: number;
-  repo_recommendation_requests: number;
-  repo_recommendation_input_tokens: number;
-  repo_recommendation_output_tokens: number;
+  /** Estimated cost from the LLM price table */
+  total_cost_usd: number;
+  /** Requests whose model has no price table entry (not included in the cost) */
+  unpriced_requests: number;
+  /**
+   * Breakdown by feature. A prompt intake request counts as a request of each
+   * feature it included, with its tokens and cost split between them.
+   */
+  by_feature: Record<string, LlmUsageCounter>;
+  /** Breakdown by provider and model, e.g. `Groq/llama-3.1-8b-instant` */
+  by_model: Record<string, LlmUsageCounter>;
+  /** Requests answered from the response cache; tokens and cost are the ones saved */
+  cache_saved: LlmUsageCounter;
+  /** Per-month usage, oldest first */
+  monthly: LlmMonthlyStats[];
+}
+
+export interface LlmMonthlyStats extends LlmPeriodUsage {
+  month: string; // YYYY-MM
+}
+
+/** Display names for the LLM features, in display order */
+export const LLM_FEATURE_LABELS: Record<string, string> = {
+  session_naming: 'Session Naming',
+  session_outcome: 'Session Outcome',
+  interaction_analysis: 'Interaction Analysis',
+  quick_actions: 'Quick Actions',
+  transcription_cleanup: 'Transcription Cleanup',
+  model_recommendation: 'Model Recommendation',
+  repo_description: 'Repo Description',
+  repo_recommendation: 'Repo Recommendation',
+  prompt_intake: 'Combined Intake',
+  change_summary: 'Commit & PR Messages',
+  voice_intent: 'Voice Commands',
+  prompt_enhancement: 'Task Specs',
+  history_compaction: 'History Compaction',
+};
+
+export function getLlmFeatureLabel(feature: string): string {
+  return (
+    LLM_FEATURE_LABELS[feature] ??
+    feature.replace(/_/g, ' ').replace(/\b\w/g, (c) => c.toUpperCase())
+  );
+}
+
+/** Feature breakdown entries in display order (known features first, then any others) */
+export function sortLlmFeatures(
+  byFeature: Record<string, LlmUsageCounter>
+): [string, LlmUsageCounter][] {
+  const order = Object.keys(LLM_FEATURE_LABELS);
+  const rank = (feature: string) => {
+    const index = order.indexOf(feature);
+    return index === -1 ? order.length : index;
+  };
+  return Object.entries(byFeature)
+    .filter(([, counter]) => counter.requests > 0)
+    .sort(([a], [b]) => rank(a) - rank(b) || a.localeCompare(b));
 }
 
 export interface ModelUsageStats {
@@ -68,6 +117,7 @@ export interface DailyStats {
   prompts: number;
   recordings: number;
   tool_calls: number;
+  llm: LlmPeriodUsage;
 }
 
 export interface UsageStats {
@@ -108,27 +158,12 @@ const defaultStats: UsageStats = {
     total_input_tokens: 0,
     total_output_tokens: 0,
     total_requests: 0,
-    session_naming_requests: 0,
-    session_naming_input_tokens: 0,
-    session_naming_output_tokens: 0,
-    session_outcome_requests: 0,
-    session_outcome_input_tokens: 0,
-    session_outcome_output_tokens: 0,
-    interaction_analysis_requests: 0,
-    interaction_analysis_input_tokens: 0,
-    interaction_analysis_output_tokens: 0,
-    transcription_cleanup_requests: 0,
-    transcription_cleanup_input_tokens: 0,
-    transcription_cleanup_output_tokens: 0,
-    model_recommendation_requests: 0,
-    model_recommendation_input_tokens: 0,
-    model_recommendation_output_tokens: 0,
-    repo_description_requests: 0,
-    repo_description_input_tokens: 0,
-    repo_description_output_tokens: 0,
-    repo_recommendation_requests: 0,
-    repo_recommendation_input_tokens: 0,
-    repo_recommendation_output_tokens: 0,
+    total_cost_usd: 0,
+    unpriced_requests: 0,
+    by_feature: {},
+    by_model: {},
+    cache_saved: { requests: 0, input_tokens: 0, output_tokens: 0, cost_usd: 0 },
+    monthly: [],
   },
   model_usage: {
     opus_sessions: 0,
@@ -234,13 +269,15 @@ function createUsageStatsStore() {
     async trackLlmTokenUsage(
       feature: string,
       inputTokens: number,
-      outputTokens: number
+      outputTokens: number,
+      model?: string
     ) {
       try {
         await invoke('track_llm_token_usage', {
           feature,
           inputTokens,
           outputTokens,
+          model: model || null,
         });
         // Don't reload for every LLM call
       } catch (error) {
@@ -294,6 +331,14 @@ export function formatCost(usd: number): string {
   return `$${usd.toFixed(0)}`;
 }
 
+export function formatMonth(month: string): string {
+  const [year, monthIndex] = month.split('-').map(Number);
+  return new Date(year, monthIndex - 1, 1).toLocaleDateString(undefined, {
+    year: 'numeric',
+    month: 'long',
+  });
+}
+
 export function formatDate(timestamp: number): string {
   return new Date(timestamp).toLocaleDateString(undefined, {
     year: 'numeric',
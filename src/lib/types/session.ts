@@ -1,6 +1,7 @@
 // Shared session types used across SessionList and SessionCard components
 
 import type { SessionAiMetadata, PlanModeState } from '$lib/stores/sdkSessions';
+import type { RepoCandidate } from '$lib/utils/llm';
 
 /**
  * Unified session type for display in the session list and grid views.
@@ -39,6 +40,7 @@ export interface DisplaySession {
     recommendedIndex: number | null;
     reasoning: string;
     confidence: string;
+    candidates?: RepoCandidate[];
   };
 
   // Plan mode state
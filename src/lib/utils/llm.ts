@@ -1,7 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import { get } from 'svelte/store';
 import { settings } from '$lib/stores/settings';
-import type { SessionAiMetadata, SdkMessage } from '$lib/stores/sdkSessions';
+import type { HistoryMessage, SessionAiMetadata, SdkMessage, SdkSession } from '$lib/stores/sdkSessions';
 
 export interface SessionNameResult {
   name: string;
@@ -38,6 +38,18 @@ export interface RepoRecommendation {
   recommended_name: string;
   confidence: 'low' | 'medium' | 'high';
   reasoning: string;
+  /** Ranked candidates (best first), including the recommended repository */
+  candidates: RepoCandidate[];
+  /** Where the recommendation came from */
+  source: 'llm' | 'lexical';
+}
+
+export interface RepoCandidate {
+  /** Index into the configured repositories (0-based) */
+  index: number;
+  name: string;
+  /** Relevance score from 0.0 to 1.0 */
+  score: number;
 }
 
 export interface QuickAction {
@@ -49,6 +61,75 @@ export interface QuickActionsResult {
   actions: QuickAction[];
 }
 
+/** Result of the combined prompt intake request (only enabled features are populated) */
+export interface PromptIntakeResult {
+  session_name: SessionNameResult | null;
+  transcription_cleanup: TranscriptionCleanupResult | null;
+  model_recommendation: ModelRecommendation | null;
+  repo_recommendation: RepoRecommendation | null;
+}
+
+export interface CommitMessageResult {
+  subject: string;
+  body: string;
+}
+
+export interface PrDescriptionResult {
+  title: string;
+  body: string;
+}
+
+/** Structured task derived from a dictated prompt */
+export interface TaskSpec {
+  goal: string;
+  constraints: string[];
+  files_and_symbols: string[];
+  acceptance_criteria: string[];
+  open_questions: string[];
+}
+
+export interface PromptEnhancementResult {
+  spec: TaskSpec;
+  /** Spec rendered as Markdown for use as the session system prompt */
+  system_prompt: string;
+}
+
+export interface ConversationSummary {
+  summary: string;
+  files_touched: string[];
+  decisions: string[];
+  open_todos: string[];
+}
+
+/** History tokens a restored session sends when resumed */
+export interface HistoryTokenEstimate {
+  full_tokens: number;
+  compacted_tokens: number;
+  total_turns: number;
+  older_turns: number;
+}
+
+export interface CompactedHistory {
+  messages: HistoryMessage[];
+  summary: ConversationSummary | null;
+  estimate: HistoryTokenEstimate;
+}
+
+/** App-control action recognized in a voice transcript (model is a full model ID) */
+export type VoiceIntentAction =
+  | { action: 'prompt' }
+  | { action: 'switch_repo'; repo_index: number; repo_name: string }
+  | { action: 'change_model'; model: string }
+  | { action: 'set_thinking'; enabled: boolean }
+  | { action: 'stop_session' }
+  | { action: 'close_session' }
+  | { action: 'queue_prompt'; prompt: string; target: string };
+
+export type VoiceIntent = VoiceIntentAction & {
+  confidence: string;
+  source: 'rules' | 'llm';
+};
+
 /**
  * Check if LLM features are enabled
  */
@@ -61,19 +142,19 @@ export function isLlmEnabled(): boolean {
 export const isGeminiEnabled = isLlmEnabled;
 
 /**
- * Check if auto-naming is enabled
+ * Check if auto-naming is enabled (uses keyword-based naming when the LLM is disabled)
  */
 export function isAutoNamingEnabled(): boolean {
   const currentSettings = get(settings);
-  return (currentSettings.llm?.enabled && currentSettings.llm?.features?.auto_name_sessions) ?? false;
+  return currentSettings.llm?.features?.auto_name_sessions ?? false;
 }
 
 /**
- * Check if interaction detection is enabled
+ * Check if interaction detection is enabled (uses rule-based detection when the LLM is disabled)
  */
 export function isInteractionDetectionEnabled(): boolean {
   const currentSettings = get(settings);
-  return (currentSettings.llm?.enabled && currentSettings.llm?.features?.detect_interaction_needed) ?? false;
+  return currentSettings.llm?.features?.detect_interaction_needed ?? false;
 }
 
 /**
@@ -106,10 +187,11 @@ export function isModelRecommendationEnabled(): boolean {
 
 /**
  * Check if auto-select repository is enabled
+ * (works without the LLM via the backend's lexical matcher)
  */
 export function isRepoAutoSelectEnabled(): boolean {
   const currentSettings = get(settings);
-  return (currentSettings.llm?.enabled && currentSettings.llm?.features?.auto_select_repo) ?? false;
+  return currentSettings.llm?.features?.auto_select_repo ?? false;
 }
 
 /**
@@ -130,6 +212,39 @@ export function isQuickActionsEnabled(): boolean {
   return (currentSettings.llm?.enabled && currentSettings.llm?.features?.generate_quick_actions) ?? false;
 }
 
+/**
+ * Check if the combined prompt intake request is enabled
+ */
+export function isCombinedIntakeEnabled(): boolean {
+  const currentSettings = get(settings);
+  return (currentSettings.llm?.enabled && currentSettings.llm?.features?.combined_intake) ?? false;
+}
+
+/**
+ * Check if voice intent routing (spoken app-control instructions) is enabled.
+ * The local rules work without the LLM; the LLM is only used for ambiguous transcripts.
+ */
+export function isVoiceIntentRoutingEnabled(): boolean {
+  const currentSettings = get(settings);
+  return currentSettings.llm?.features?.route_voice_intents ?? false;
+}
+
+/**
+ * Check if structured prompt enhancement is enabled
+ */
+export function isPromptEnhancementEnabled(): boolean {
+  const currentSettings = get(settings);
+  return (currentSettings.llm?.enabled && currentSettings.llm?.features?.enhance_prompts) ?? false;
+}
+
+/**
+ * Check if the task spec should be passed to Claude as the system prompt
+ */
+export function isTaskSpecSystemPromptEnabled(): boolean {
+  const currentSettings = get(settings);
+  return isPromptEnhancementEnabled() && (currentSettings.llm?.features?.task_spec_system_prompt ?? false);
+}
+
 /**
  * Get the minimum confidence level required for auto-selection
  */
@@ -253,7 +368,7 @@ export async function generateSessionOutcome(
   userPrompt: string,
   assistantMessages: string
 ): Promise<SessionOutcomeResult | null> {
-  if (!isAutoNamingEnabled()) {
+  if (!isLlmEnabled() || !isAutoNamingEnabled()) {
     return null;
   }
 
@@ -300,7 +415,8 @@ export async function analyzeInteractionNeeded(
 export async function cleanTranscription(
   whisperTranscription: string,
   voskTranscription?: string,
-  repoContext?: string
+  repoContext?: string,
+  intake?: PromptIntakeResult | null
 ): Promise<{ text: string; wasCleanedUp: boolean; corrections: string[]; usedDualSource: boolean }> {
   if (!isTranscriptionCleanupEnabled()) {
     return { text: whisperTranscription, wasCleanedUp: false, corrections: [], usedDualSource: false };
@@ -308,6 +424,17 @@ export async function cleanTranscription(
 
   // Only pass Vosk transcription if dual-source is enabled
   const voskToUse = isDualTranscriptionEnabled() ? voskTranscription : undefined;
+  const toCleanup = (result: TranscriptionCleanupResult) => ({
+    text: result.cleaned_text,
+    wasCleanedUp: result.corrections_made.length > 0,
+    corrections: result.corrections_made,
+    usedDualSource: !!voskToUse,
+  });
+
+  // Already answered by the combined intake request
+  if (intake?.transcription_cleanup) {
+    return toCleanup(intake.transcription_cleanup);
+  }
 
   try {
     const result = await invoke<TranscriptionCleanupResult>('clean_transcription', {
@@ -316,12 +443,7 @@ export async function cleanTranscription(
       repoContext: repoContext || null,
     });
     console.log('[llm] Transcription cleaned:', result.corrections_made, voskToUse ? '(dual-source)' : '(whisper only)');
-    return {
-      text: result.cleaned_text,
-      wasCleanedUp: result.corrections_made.length > 0,
-      corrections: result.corrections_made,
-      usedDualSource: !!voskToUse,
-    };
+    return toCleanup(result);
   } catch (error) {
     console.error('[llm] Failed to clean transcription:', error);
     // Fall back to original text on error
@@ -356,7 +478,8 @@ const THINKING_LEVEL_MAP: Record<string, string | null> = {
  * Returns null if recommendation is disabled or fails
  */
 export async function recommendModel(
-  prompt: string
+  prompt: string,
+  intake?: PromptIntakeResult | null
 ): Promise<{
   modelId: string;
   thinkingLevel: string | null;
@@ -368,7 +491,8 @@ export async function recommendModel(
   }
 
   try {
-    const result = await invoke<ModelRecommendation>('recommend_model', { prompt });
+    // Already answered by the combined intake request
+    const result = intake?.model_recommendation ?? await invoke<ModelRecommendation>('recommend_model', { prompt });
     console.log('[llm] Model recommendation:', result);
 
     const modelId = MODEL_ID_MAP[result.recommended_model] || MODEL_ID_MAP.sonnet;
@@ -396,12 +520,14 @@ export async function recommendModel(
  */
 export async function recommendRepo(
   prompt: string,
-  isTranscribed: boolean = false
+  isTranscribed: boolean = false,
+  intake?: PromptIntakeResult | null
 ): Promise<{
   repoIndex: number;
   repoName: string;
   reasoning: string;
   confidence: string;
+  candidates: RepoCandidate[];
 } | null> {
   if (!isRepoAutoSelectEnabled()) {
     return null;
@@ -414,15 +540,16 @@ export async function recommendRepo(
     return null;
   }
 
-  // Check if any repos have descriptions
+  // Check if any repos have descriptions (the lexical fallback can still match on names)
   const hasDescriptions = currentSettings.repos.some((r) => r.description);
-  if (!hasDescriptions) {
+  if (!hasDescriptions && isLlmEnabled()) {
     console.log('[llm] No repo descriptions found, skipping auto-select');
     return null;
   }
 
   try {
-    const result = await invoke<RepoRecommendation>('recommend_repo', { prompt, isTranscribed });
+    // Already answered by the combined intake request
+    const result = intake?.repo_recommendation ?? await invoke<RepoRecommendation>('recommend_repo', { prompt, isTranscribed });
     console.log('[llm] Repo recommendation:', result);
 
     // -1 index or empty name means no clear match
@@ -435,6 +562,7 @@ export async function recommendRepo(
       repoName: result.recommended_name,
       reasoning: result.reasoning,
       confidence: result.confidence,
+      candidates: result.candidates ?? [],
     };
   } catch (error) {
     console.error('[llm] Failed to recommend repo:', error);
@@ -467,6 +595,171 @@ export async function generateQuickActions(
   }
 }
 
+/**
+ * Run session naming, transcription cleanup and model/repo recommendation in a single request.
+ * The backend only includes the features that are enabled in settings. Pass the result to
+ * `cleanTranscription`, `recommendModel` and `recommendRepo`, which then skip their own requests.
+ * Returns null if the combined intake is disabled or fails
+ */
+export async function runPromptIntake(
+  prompt: string,
+  options: {
+    voskTranscription?: string;
+    repoContext?: string;
+    isTranscribed?: boolean;
+    /** Leave out features the caller has no use for (e.g. the model when it is not on auto) */
+    recommendModel?: boolean;
+    recommendRepo?: boolean;
+  } = {}
+): Promise<PromptIntakeResult | null> {
+  if (!isCombinedIntakeEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<PromptIntakeResult>('run_prompt_intake', {
+      prompt,
+      voskTranscription: isDualTranscriptionEnabled() ? options.voskTranscription || null : null,
+      repoContext: options.repoContext || null,
+      isTranscribed: options.isTranscribed ?? false,
+      needs: { model: options.recommendModel ?? true, repo: options.recommendRepo ?? true },
+    });
+    console.log('[llm] Prompt intake:', result);
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to run prompt intake:', error);
+    return null;
+  }
+}
+
+/**
+ * Rewrite a prompt into a structured task spec (goal, constraints, files/symbols,
+ * acceptance criteria, open questions) using the target repo's vocabulary as context.
+ * Returns null if the feature is disabled or fails
+ */
+export async function enhancePrompt(prompt: string, repoPath?: string): Promise<PromptEnhancementResult | null> {
+  if (!isPromptEnhancementEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<PromptEnhancementResult>('enhance_prompt', {
+      prompt,
+      repoPath: repoPath ?? null,
+    });
+    console.log('[llm] Task spec generated:', result.spec.goal);
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to enhance prompt:', error);
+    return null;
+  }
+}
+
+/**
+ * Check if restored sessions should replay a summary of older turns instead of the full history
+ */
+export function isHistoryCompactionEnabled(): boolean {
+  const currentSettings = get(settings);
+  return (currentSettings.llm?.enabled && currentSettings.session_persistence?.compact_history) ?? false;
+}
+
+/**
+ * Replace the older turns of a restored session's history with a summary.
+ * Returns null if compaction is disabled or fails (replay the full history)
+ */
+export async function compactSessionHistory(messages: HistoryMessage[]): Promise<CompactedHistory | null> {
+  if (!isHistoryCompactionEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<CompactedHistory>('compact_session_history', { messages });
+    if (result.summary) {
+      console.log(
+        `[llm] Compacted ${result.estimate.older_turns} older turns (~${result.estimate.full_tokens} -> ~${result.estimate.compacted_tokens} tokens)`
+      );
+    }
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to compact session history:', error);
+    return null;
+  }
+}
+
+/**
+ * Decide whether a transcript is an app-control instruction or a prompt for Claude.
+ * Returns null if routing is disabled or fails (treat the transcript as a prompt)
+ */
+export async function routeVoiceIntent(transcript: string): Promise<VoiceIntent | null> {
+  if (!isVoiceIntentRoutingEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<VoiceIntent>('route_voice_intent', { transcript });
+    console.log('[llm] Voice intent:', result.action, `(${result.source}, ${result.confidence})`);
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to route voice intent:', error);
+    return null;
+  }
+}
+
+/**
+ * Generate a conventional commit message for a session's changes.
+ * Uses the diff since the session's base commit, or only staged changes when stagedOnly is set.
+ * Returns null if the LLM is disabled, there are no changes, or generation fails
+ */
+export async function generateCommitMessage(
+  session: Pick<SdkSession, 'cwd' | 'baseCommit' | 'messages'>,
+  options: { stagedOnly?: boolean } = {}
+): Promise<CommitMessageResult | null> {
+  if (!isLlmEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<CommitMessageResult>('generate_commit_message', {
+      repoPath: session.cwd,
+      prompt: extractFirstUserPrompt(session.messages) ?? '',
+      baseCommit: session.baseCommit ?? null,
+      stagedOnly: options.stagedOnly ?? false,
+    });
+    console.log('[llm] Commit message generated:', result.subject);
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to generate commit message:', error);
+    return null;
+  }
+}
+
+/**
+ * Generate a pull request title and body for a session's changes.
+ * Returns null if the LLM is disabled, there are no changes, or generation fails
+ */
+export async function generatePrDescription(
+  session: Pick<SdkSession, 'cwd' | 'baseCommit' | 'messages'>,
+  options: { stagedOnly?: boolean } = {}
+): Promise<PrDescriptionResult | null> {
+  if (!isLlmEnabled()) {
+    return null;
+  }
+
+  try {
+    const result = await invoke<PrDescriptionResult>('generate_pr_description', {
+      repoPath: session.cwd,
+      prompt: extractFirstUserPrompt(session.messages) ?? '',
+      baseCommit: session.baseCommit ?? null,
+      stagedOnly: options.stagedOnly ?? false,
+    });
+    console.log('[llm] PR description generated:', result.title);
+    return result;
+  } catch (error) {
+    console.error('[llm] Failed to generate PR description:', error);
+    return null;
+  }
+}
+
 /**
  * Generate a system prompt that instructs Claude to question the repo selection if it seems wrong
  * @param repoName The name of the selected repository
@@ -548,8 +841,8 @@ export function extractLastAssistantMessage(messages: SdkMessage[]): string | nu
 export async function analyzeSessionCompletion(messages: SdkMessage[]): Promise<SessionAiMetadata> {
   const metadata: SessionAiMetadata = {};
 
-  // Only proceed if at least one LLM feature is enabled
-  if (!isLlmEnabled()) {
+  // Interaction detection also works without the LLM (rule-based fallback)
+  if (!isLlmEnabled() && !isInteractionDetectionEnabled()) {
     return metadata;
   }
 
@@ -558,7 +851,7 @@ export async function analyzeSessionCompletion(messages: SdkMessage[]): Promise<
   const lastMessage = extractLastAssistantMessage(messages);
 
   // Generate session outcome if we have the exchange
-  if (isAutoNamingEnabled() && userPrompt && assistantMessages) {
+  if (isLlmEnabled() && isAutoNamingEnabled() && userPrompt && assistantMessages) {
     const outcomeResult = await generateSessionOutcome(userPrompt, assistantMessages);
     if (outcomeResult) {
       metadata.outcome = outcomeResult.outcome;
@@ -0,0 +1,76 @@
+import { get } from "svelte/store";
+import { settings } from "$lib/stores/settings";
+import { sdkSessions, activeSdkSessionId } from "$lib/stores/sdkSessions";
+import type { VoiceIntent } from "$lib/utils/llm";
+
+/** Last transcript sent as a prompt - what "send that to the last session" refers to */
+let lastPromptTranscript: string | null = null;
+
+/**
+ * Remember a transcript that was sent to Claude as a prompt
+ */
+export function rememberPromptTranscript(transcript: string): void {
+  lastPromptTranscript = transcript;
+}
+
+/**
+ * Most recently created live session, excluding the given one
+ */
+function findLastSession(excludeId: string | null): string | null {
+  const candidates = get(sdkSessions)
+    .filter(s => s.id !== excludeId && (s.status === 'idle' || s.status === 'querying' || s.status === 'done'))
+    .sort((a, b) => b.createdAt - a.createdAt);
+  return candidates[0]?.id ?? null;
+}
+
+/**
+ * Execute an app-control intent through the existing store methods
+ * (which call set_active_repo, update_sdk_model, update_sdk_thinking, stop_sdk_query,
+ * close_sdk_session and send_sdk_prompt).
+ *
+ * @param intent - Routed voice intent (must not be a plain prompt)
+ * @param excludeSessionId - Pending transcription session that should not be targeted
+ * @returns true if the intent was handled
+ */
+export async function executeVoiceIntent(intent: VoiceIntent, excludeSessionId: string | null = null): Promise<boolean> {
+  const activeId = get(activeSdkSessionId);
+  const targetId = activeId !== excludeSessionId ? activeId : null;
+
+  switch (intent.action) {
+    case 'prompt':
+      return false;
+
+    case 'switch_repo':
+      if (intent.repo_index >= get(settings).repos.length) return false;
+      await settings.setActiveRepo(intent.repo_index);
+      return true;
+
+    case 'change_model':
+      if (!targetId) return false;
+      await sdkSessions.updateSessionModel(targetId, intent.model);
+      return true;
+
+    case 'set_thinking':
+      if (!targetId) return false;
+      await sdkSessions.updateSessionThinking(targetId, intent.enabled ? 'on' : null);
+      return true;
+
+    case 'stop_session':
+      if (!targetId) return false;
+      await sdkSessions.stopQuery(targetId);
+      return true;
+
+    case 'close_session':
+      if (!targetId) return false;
+      await sdkSessions.closeSession(targetId);
+      return true;
+
+    case 'queue_prompt': {
+      const prompt = intent.prompt || lastPromptTranscript;
+      const sessionId = intent.target === 'active' ? targetId : findLastSession(excludeSessionId);
+      if (!prompt || !sessionId) return false;
+      await sdkSessions.sendPrompt(sessionId, prompt);
+      return true;
+    }
+  }
+}